@@ -0,0 +1,225 @@
+// Copyright 2019 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Analyses over the control flow graph described by the `cfg_edge` facts.
+
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::collections::{BTreeMap, BTreeSet};
+
+use facts::Atom;
+use scc;
+
+/// The successors and predecessors of each point in the CFG.
+struct Graph<Point: Atom> {
+    successors: BTreeMap<Point, Vec<Point>>,
+    predecessors: BTreeMap<Point, Vec<Point>>,
+}
+
+impl<Point: Atom> Graph<Point> {
+    fn new(cfg_edge: &[(Point, Point)]) -> Self {
+        let mut successors: BTreeMap<Point, Vec<Point>> = BTreeMap::new();
+        let mut predecessors: BTreeMap<Point, Vec<Point>> = BTreeMap::new();
+        for &(p, q) in cfg_edge {
+            successors.entry(p).or_default().push(q);
+            successors.entry(q).or_default();
+            predecessors.entry(q).or_default().push(p);
+        }
+
+        Graph {
+            successors,
+            predecessors,
+        }
+    }
+
+    /// The entry points: the points without predecessors and, for each cycle that no other point
+    /// reaches, e.g. a loop whose header is the first point, the smallest point of the cycle.
+    /// They are sorted.
+    fn entries(&self) -> Vec<Point> {
+        let edges: Vec<(Point, Point)> = self
+            .successors
+            .iter()
+            .flat_map(|(&p, successors)| successors.iter().map(move |&q| (p, q)))
+            .collect();
+        let components = scc::strongly_connected_components(&edges);
+        let component_of: FxHashMap<Point, usize> = components
+            .iter()
+            .enumerate()
+            .flat_map(|(index, component)| component.iter().map(move |&p| (p, index)))
+            .collect();
+
+        // A component is entered from no other point when all the predecessors of its points
+        // are in the component.
+        components
+            .iter()
+            .enumerate()
+            .filter(|&(index, component)| {
+                component.iter().all(|p| {
+                    self.predecessors
+                        .get(p)
+                        .into_iter()
+                        .flatten()
+                        .all(|q| component_of[q] == index)
+                })
+            })
+            .map(|(_, component)| component[0])
+            .collect()
+    }
+
+    /// The points reachable from an entry point, in postorder of a depth-first traversal.
+    fn postorder(&self) -> Vec<Point> {
+        let mut postorder = Vec::new();
+        let mut visited = FxHashSet::default();
+        for entry in self.entries() {
+            visited.insert(entry);
+            let mut stack = vec![(entry, 0)];
+            while let Some((p, next_successor)) = stack.pop() {
+                match self.successors[&p].get(next_successor) {
+                    Some(&q) => {
+                        stack.push((p, next_successor + 1));
+                        if visited.insert(q) {
+                            stack.push((q, 0));
+                        }
+                    }
+                    None => postorder.push(p),
+                }
+            }
+        }
+        postorder
+    }
+}
+
+/// Returns the points reachable from `point` in the CFG, including `point` itself.
+pub(crate) fn reachable_from<Point: Atom>(
+    cfg_edge: &[(Point, Point)],
+    point: Point,
+) -> BTreeSet<Point> {
+    let graph = Graph::new(cfg_edge);
+    let mut reachable = BTreeSet::new();
+    reachable.insert(point);
+    let mut stack = vec![point];
+    while let Some(p) = stack.pop() {
+        for &q in graph.successors.get(&p).into_iter().flatten() {
+            if reachable.insert(q) {
+                stack.push(q);
+            }
+        }
+    }
+    reachable
+}
+
+/// Returns the points reachable from an entry point of the CFG in reverse postorder: unless it
+/// is the target of a back edge, a point comes after all of its predecessors.
+pub(crate) fn reverse_postorder<Point: Atom>(cfg_edge: &[(Point, Point)]) -> Vec<Point> {
+    let mut points = Graph::new(cfg_edge).postorder();
+    points.reverse();
+    points
+}
+
+/// Returns the `points` in CFG order: in reverse postorder from the entry points, followed by
+/// the points which are not reachable from them, sorted.
+pub(crate) fn in_cfg_order<Point: Atom>(
+    cfg_edge: &[(Point, Point)],
+    mut points: BTreeSet<Point>,
+) -> Vec<Point> {
+    let mut ordered_points: Vec<Point> = reverse_postorder(cfg_edge)
+        .into_iter()
+        .filter(|p| points.remove(p))
+        .collect();
+    ordered_points.extend(points);
+    ordered_points
+}
+
+/// Computes the immediate dominator of each point reachable from an entry point of the CFG
+/// (a point without predecessors, or the smallest point of a cycle which no other point
+/// reaches), using the iterative algorithm from "A Simple, Fast
+/// Dominance Algorithm" (Cooper, Harvey, and Kennedy).
+///
+/// All the entry points are attached to a virtual root, so that CFGs with multiple entries
+/// have a single dominator tree. Points whose immediate dominator is this virtual root, i.e.
+/// the entry points themselves and the points which no other point dominates, are mapped
+/// to themselves.
+pub(crate) fn dominators<Point: Atom>(cfg_edge: &[(Point, Point)]) -> FxHashMap<Point, Point> {
+    let graph = Graph::new(cfg_edge);
+    let entries: FxHashSet<Point> = graph.entries().into_iter().collect();
+
+    // Number the points in postorder, the virtual root being the last one.
+    let postorder = graph.postorder();
+    let postorder_index: FxHashMap<Point, usize> = postorder
+        .iter()
+        .enumerate()
+        .map(|(index, &p)| (p, index))
+        .collect();
+    let root = postorder.len();
+
+    let intersect = |idom: &[Option<usize>], mut a: usize, mut b: usize| {
+        while a != b {
+            while a < b {
+                a = idom[a].unwrap();
+            }
+            while b < a {
+                b = idom[b].unwrap();
+            }
+        }
+        a
+    };
+
+    let mut idom: Vec<Option<usize>> = vec![None; root + 1];
+    idom[root] = Some(root);
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+
+        // Visit the points in reverse postorder, skipping the root.
+        for (index, p) in postorder.iter().enumerate().rev() {
+            let mut new_idom = None;
+            let node_predecessors = graph.predecessors.get(p).map(|v| &v[..]).unwrap_or(&[]);
+            // Predecessors which are not reachable from an entry point are ignored, and the entry
+            // points are the successors of the virtual root, even those entering a cycle.
+            let node_predecessors = node_predecessors
+                .iter()
+                .filter_map(|q| postorder_index.get(q).cloned())
+                .chain(if entries.contains(p) {
+                    Some(root)
+                } else {
+                    None
+                });
+
+            for predecessor in node_predecessors {
+                if idom[predecessor].is_none() {
+                    continue;
+                }
+
+                new_idom = Some(match new_idom {
+                    None => predecessor,
+                    Some(current) => intersect(&idom, predecessor, current),
+                });
+            }
+
+            if idom[index] != new_idom {
+                idom[index] = new_idom;
+                changed = true;
+            }
+        }
+    }
+
+    postorder
+        .iter()
+        .enumerate()
+        .map(|(index, &p)| {
+            let dominator = idom[index].unwrap();
+            if dominator == root {
+                (p, p)
+            } else {
+                (p, postorder[dominator])
+            }
+        })
+        .collect()
+}
@@ -1,6 +1,16 @@
+use cfg;
+#[cfg(feature = "petgraph")]
+use petgraph::graph::{Graph, NodeIndex};
+use rustc_hash::FxHashMap;
+use scc;
+use std::collections::BTreeSet;
 use std::fmt::Debug;
 use std::hash::Hash;
 
+/// The version of the relations of `AllFacts`, incremented whenever a relation is added or
+/// changed, so that the facts written for another version can be detected.
+pub const FACTS_SCHEMA_VERSION: u32 = 1;
+
 /// The "facts" which are the basis of the NLL borrow analysis.
 #[derive(Clone, Debug)]
 pub struct AllFacts<R: Atom, L: Atom, P: Atom, V: Atom> {
@@ -46,6 +56,58 @@ pub struct AllFacts<R: Atom, L: Atom, P: Atom, V: Atom> {
     /// `var_initialized_on_exit(V, P) when the variable `V` is initialized on
     /// exit from point `P` in the program flow.
     pub var_initialized_on_exit: Vec<(V, P)>,
+
+    /// `borrow_reserved_at(L, P)` -- the two-phase borrow L is reserved at the point P, where
+    /// it is created but not yet used mutably. Not used by the analysis yet.
+    pub borrow_reserved_at: Vec<(L, P)>,
+
+    /// `borrow_activated_at(L, P)` -- the two-phase borrow L is activated at the point P, its
+    /// first mutable use. Not used by the analysis yet.
+    pub borrow_activated_at: Vec<(L, P)>,
+
+    /// `closure_requires(R, P)` -- the closure created at the point P requires the region R to
+    /// outlive it, e.g. as it captures a reference in R and escapes: R must not contain any of
+    /// the function's loans there.
+    pub closure_requires: Vec<(R, P)>,
+
+    /// `loan_reborrows(L1, L2)` -- the loan L1 is a reborrow of (data borrowed by) the loan
+    /// L2: it is killed wherever L2 is killed.
+    pub loan_reborrows: Vec<(L, L)>,
+
+    /// `loan_borrows_var(L, V)` -- the loan L borrows (a path of) the variable V. Only used to
+    /// describe the loans in diagnostics.
+    pub loan_borrows_var: Vec<(L, V)>,
+}
+
+/// Invokes the macro `$macro` with the names of the relations of `AllFacts`, after the `$args`
+/// tokens if any, each name followed by a comma: `for_each_relation!(m!(x;))` expands to
+/// `m!(x; borrow_region, universal_region, ...)`. This is the list of relations to update when
+/// one is added, for the code handling every relation alike, like the loading of the facts.
+#[macro_export]
+macro_rules! for_each_relation {
+    ($macro:ident!($($args:tt)*)) => {
+        $macro!(
+            $($args)*
+            borrow_region,
+            universal_region,
+            cfg_edge,
+            killed,
+            outlives,
+            region_live_at,
+            invalidates,
+            var_defined,
+            var_used,
+            var_drop_used,
+            var_uses_region,
+            var_drops_region,
+            var_initialized_on_exit,
+            borrow_reserved_at,
+            borrow_activated_at,
+            closure_requires,
+            loan_reborrows,
+            loan_borrows_var,
+        )
+    };
 }
 
 impl<R: Atom, L: Atom, P: Atom, V: Atom> Default for AllFacts<R, L, P, V> {
@@ -64,12 +126,478 @@ impl<R: Atom, L: Atom, P: Atom, V: Atom> Default for AllFacts<R, L, P, V> {
             var_uses_region: Vec::default(),
             var_drops_region: Vec::default(),
             var_initialized_on_exit: Vec::default(),
+            borrow_reserved_at: Vec::default(),
+            borrow_activated_at: Vec::default(),
+            closure_requires: Vec::default(),
+            loan_reborrows: Vec::default(),
+            loan_borrows_var: Vec::default(),
         }
     }
 }
 
+impl<R: Atom, L: Atom, P: Atom, V: Atom> AllFacts<R, L, P, V> {
+    /// Computes the immediate dominator of each point of the CFG described by `cfg_edge`.
+    ///
+    /// CFGs with multiple entry points are handled by introducing a virtual root: the entry
+    /// points, and the points which are not dominated by any other point, are their own
+    /// immediate dominator. Points unreachable from an entry point are absent from the map.
+    pub fn dominators(&self) -> FxHashMap<P, P> {
+        cfg::dominators(&self.cfg_edge)
+    }
+
+    /// Builds the CFG described by `cfg_edge` as a `petgraph` graph, whose nodes are the points,
+    /// along with the index of each point's node, to run the `petgraph` algorithms on it.
+    /// Duplicate edges are only added once.
+    #[cfg(feature = "petgraph")]
+    pub fn cfg_graph(&self) -> (Graph<P, ()>, FxHashMap<P, NodeIndex>) {
+        let mut graph = Graph::new();
+        let mut nodes = FxHashMap::default();
+        for &(p, q) in &self.cfg_edge {
+            let p = *nodes.entry(p).or_insert_with(|| graph.add_node(p));
+            let q = *nodes.entry(q).or_insert_with(|| graph.add_node(q));
+            graph.update_edge(p, q, ());
+        }
+        (graph, nodes)
+    }
+
+    /// Returns the points where the loan `loan` is invalidated, in CFG order (reverse
+    /// postorder from the entry points). Points which are not reachable in the CFG come last.
+    pub fn invalidation_points(&self, loan: L) -> Vec<P> {
+        let points: BTreeSet<P> = self
+            .invalidates
+            .iter()
+            .filter(|&&(_, l)| l == loan)
+            .map(|&(p, _)| p)
+            .collect();
+        cfg::in_cfg_order(&self.cfg_edge, points)
+    }
+
+    /// Returns the join points of the CFG described by `cfg_edge`: the points with more than one
+    /// predecessor, duplicate edges counting once. They are in CFG order, like the points of
+    /// `invalidation_points`, with the unreachable ones last.
+    pub fn join_points(&self) -> Vec<P> {
+        let edges: BTreeSet<(P, P)> = self.cfg_edge.iter().cloned().collect();
+        let mut predecessor_counts: FxHashMap<P, usize> = FxHashMap::default();
+        for &(_, q) in &edges {
+            *predecessor_counts.entry(q).or_default() += 1;
+        }
+        let points: BTreeSet<P> = predecessor_counts
+            .into_iter()
+            .filter(|&(_, count)| count > 1)
+            .map(|(p, _)| p)
+            .collect();
+        cfg::in_cfg_order(&self.cfg_edge, points)
+    }
+
+    /// Returns the region and point of the `borrow_region` fact introducing the loan `loan`, or
+    /// `None` if no fact introduces it.
+    pub fn loan_origin(&self, loan: L) -> Option<(R, P)> {
+        self.borrow_region
+            .iter()
+            .find(|&&(_, l, _)| l == loan)
+            .map(|&(r, _, p)| (r, p))
+    }
+
+    /// Returns the variable borrowed by the loan `loan`, according to the `loan_borrows_var`
+    /// facts, or `None` if they don't describe it.
+    pub fn loan_variable(&self, loan: L) -> Option<V> {
+        self.loan_borrows_var
+            .iter()
+            .find(|&&(l, _)| l == loan)
+            .map(|&(_, v)| v)
+    }
+
+    /// Returns the cycles of the `outlives` facts at the point `point`, before any propagation:
+    /// the strongly-connected components of more than one region. The regions of a cycle are
+    /// all equal at this point.
+    pub fn outlives_cycles_at(&self, point: P) -> Vec<Vec<R>> {
+        let edges: Vec<(R, R)> = self
+            .outlives
+            .iter()
+            .filter(|&&(_, _, p)| p == point)
+            .map(|&(r1, r2, _)| (r1, r2))
+            .collect();
+
+        scc::strongly_connected_components(&edges)
+            .into_iter()
+            .filter(|component| component.len() > 1)
+            .collect()
+    }
+
+    /// Inlines the function described by the `callee` facts at the point `call_point` of these
+    /// facts: the edges leaving `call_point` are replaced by edges to the entry points of the
+    /// callee's CFG, and its exit points get edges to the former successors of `call_point`.
+    /// The regions of the callee are substituted according to `mapping`, and its facts are
+    /// added to the caller's: its universal regions are the caller's only.
+    ///
+    /// The callee's points, loans and variables are kept as is, so both sets of facts must use
+    /// the same atoms, e.g. by being interned in the same tables, without sharing any point,
+    /// loan or variable. The mapping must map each universal region of the callee, usually
+    /// to the regions of the call's arguments and return value. Its unmapped regions are kept,
+    /// and must not be regions of the caller either.
+    ///
+    /// # Panics
+    ///
+    /// Panics if these requirements are not met: if `call_point` is not in the caller's CFG,
+    /// if the callee's CFG has no entry point, if a point, loan or variable is in both sets of
+    /// facts, if a universal region of the callee is not mapped, or if an unmapped region of the
+    /// callee is also a region of the caller. Also panics if the points of the callee are not
+    /// all reachable from `call_point` once spliced.
+    pub fn splice(&self, call_point: P, callee: &Self, mapping: RegionMapping<R>) -> Self {
+        fn points<P: Atom>(cfg_edge: &[(P, P)]) -> BTreeSet<P> {
+            cfg_edge.iter().flat_map(|&(p, q)| vec![p, q]).collect()
+        }
+
+        let caller_points = points(&self.cfg_edge);
+        let callee_points = points(&callee.cfg_edge);
+        assert!(
+            caller_points.contains(&call_point),
+            "the call point {:?} is not in the caller's CFG",
+            call_point
+        );
+        if let Some(point) = caller_points.intersection(&callee_points).next() {
+            panic!("the point {:?} is in both the caller and the callee", point);
+        }
+
+        let loans = |facts: &Self| -> BTreeSet<L> {
+            facts.borrow_region.iter().map(|&(_, l, _)| l).collect()
+        };
+        if let Some(loan) = loans(self).intersection(&loans(callee)).next() {
+            panic!("the loan {:?} is in both the caller and the callee", loan);
+        }
+
+        let variables = |facts: &Self| -> BTreeSet<V> {
+            facts
+                .var_defined
+                .iter()
+                .chain(&facts.var_used)
+                .chain(&facts.var_drop_used)
+                .map(|&(v, _)| v)
+                .chain(facts.var_uses_region.iter().map(|&(v, _)| v))
+                .chain(facts.var_drops_region.iter().map(|&(v, _)| v))
+                .collect()
+        };
+        if let Some(variable) = variables(self).intersection(&variables(callee)).next() {
+            panic!(
+                "the variable {:?} is in both the caller and the callee",
+                variable
+            );
+        }
+
+        for &region in &callee.universal_region {
+            assert!(
+                mapping.regions.contains_key(&region),
+                "the universal region {:?} of the callee is not mapped",
+                region
+            );
+        }
+
+        let regions = |facts: &Self| -> BTreeSet<R> {
+            facts
+                .borrow_region
+                .iter()
+                .map(|&(r, _, _)| r)
+                .chain(facts.universal_region.iter().cloned())
+                .chain(facts.outlives.iter().flat_map(|&(r1, r2, _)| vec![r1, r2]))
+                .chain(facts.region_live_at.iter().map(|&(r, _)| r))
+                .chain(facts.var_uses_region.iter().map(|&(_, r)| r))
+                .chain(facts.var_drops_region.iter().map(|&(_, r)| r))
+                .chain(facts.closure_requires.iter().map(|&(r, _)| r))
+                .collect()
+        };
+        let caller_regions = regions(self);
+        if let Some(region) = regions(callee)
+            .into_iter()
+            .find(|region| !mapping.regions.contains_key(region) && caller_regions.contains(region))
+        {
+            panic!(
+                "the unmapped region {:?} is in both the caller and the callee",
+                region
+            );
+        }
+
+        let entries: Vec<P> = callee_points
+            .iter()
+            .cloned()
+            .filter(|&p| callee.cfg_edge.iter().all(|&(_, q)| q != p))
+            .collect();
+        let exits: Vec<P> = callee_points
+            .iter()
+            .cloned()
+            .filter(|&p| callee.cfg_edge.iter().all(|&(q, _)| q != p))
+            .collect();
+        assert!(!entries.is_empty(), "the callee's CFG has no entry point");
+
+        let mut facts = self.clone();
+        let return_points: Vec<P> = self
+            .cfg_edge
+            .iter()
+            .filter(|&&(p, _)| p == call_point)
+            .map(|&(_, q)| q)
+            .collect();
+        facts.cfg_edge.retain(|&(p, _)| p != call_point);
+        facts
+            .cfg_edge
+            .extend(entries.iter().map(|&entry| (call_point, entry)));
+        facts.cfg_edge.extend(callee.cfg_edge.iter().cloned());
+        for &exit in &exits {
+            facts
+                .cfg_edge
+                .extend(return_points.iter().map(|&q| (exit, q)));
+        }
+
+        let r = |region| mapping.apply(region);
+        facts.borrow_region.extend(
+            callee
+                .borrow_region
+                .iter()
+                .map(|&(region, l, p)| (r(region), l, p)),
+        );
+        facts.killed.extend(callee.killed.iter().cloned());
+        facts
+            .outlives
+            .extend(callee.outlives.iter().map(|&(r1, r2, p)| (r(r1), r(r2), p)));
+        facts.region_live_at.extend(
+            callee
+                .region_live_at
+                .iter()
+                .map(|&(region, p)| (r(region), p)),
+        );
+        facts.invalidates.extend(callee.invalidates.iter().cloned());
+        facts.var_used.extend(callee.var_used.iter().cloned());
+        facts.var_defined.extend(callee.var_defined.iter().cloned());
+        facts
+            .var_drop_used
+            .extend(callee.var_drop_used.iter().cloned());
+        facts.var_uses_region.extend(
+            callee
+                .var_uses_region
+                .iter()
+                .map(|&(v, region)| (v, r(region))),
+        );
+        facts.var_drops_region.extend(
+            callee
+                .var_drops_region
+                .iter()
+                .map(|&(v, region)| (v, r(region))),
+        );
+        facts
+            .var_initialized_on_exit
+            .extend(callee.var_initialized_on_exit.iter().cloned());
+        facts
+            .borrow_reserved_at
+            .extend(callee.borrow_reserved_at.iter().cloned());
+        facts
+            .borrow_activated_at
+            .extend(callee.borrow_activated_at.iter().cloned());
+        facts.closure_requires.extend(
+            callee
+                .closure_requires
+                .iter()
+                .map(|&(region, p)| (r(region), p)),
+        );
+        facts
+            .loan_reborrows
+            .extend(callee.loan_reborrows.iter().cloned());
+        facts
+            .loan_borrows_var
+            .extend(callee.loan_borrows_var.iter().cloned());
+
+        let reachable = cfg::reachable_from(&facts.cfg_edge, call_point);
+        if let Some(point) = callee_points.difference(&reachable).next() {
+            panic!(
+                "the point {:?} of the callee is not reachable from the call point once spliced",
+                point
+            );
+        }
+        facts
+    }
+
+    /// Returns these facts without the points `block_points`, e.g. the points of a block, to
+    /// check whether the block is responsible for an error by recomputing the output without
+    /// it. The facts at these points are removed, and the CFG is rewired around them: each
+    /// remaining point is connected to the remaining points it reached through them.
+    ///
+    /// Panics if a remaining point is no longer reachable from the entry points it was reachable
+    /// from, which would be a bug of the rewiring. The points only reachable from an entry point
+    /// which is removed become entry points themselves.
+    pub fn without_block(&self, block_points: &[P]) -> Self {
+        let block: BTreeSet<P> = block_points.iter().cloned().collect();
+        let mut successors: FxHashMap<P, Vec<P>> = FxHashMap::default();
+        for &(p, q) in &self.cfg_edge {
+            successors.entry(p).or_default().push(q);
+        }
+
+        let mut edges = BTreeSet::new();
+        let mut cfg_edge = Vec::new();
+        for &(p, q) in &self.cfg_edge {
+            if block.contains(&p) {
+                continue;
+            }
+            if !block.contains(&q) {
+                if edges.insert((p, q)) {
+                    cfg_edge.push((p, q));
+                }
+                continue;
+            }
+
+            // Follow the paths from `p` through the block, to the points after it.
+            let mut visited = BTreeSet::new();
+            let mut stack = vec![q];
+            while let Some(point) = stack.pop() {
+                if !visited.insert(point) {
+                    continue;
+                }
+                for &successor in successors.get(&point).into_iter().flatten() {
+                    if block.contains(&successor) {
+                        stack.push(successor);
+                    } else if edges.insert((p, successor)) {
+                        cfg_edge.push((p, successor));
+                    }
+                }
+            }
+        }
+
+        let keep = |p: &P| !block.contains(p);
+        let mut facts = self.clone();
+        facts.cfg_edge = cfg_edge;
+        facts.borrow_region.retain(|(_, _, p)| keep(p));
+        facts.killed.retain(|(_, p)| keep(p));
+        facts.outlives.retain(|(_, _, p)| keep(p));
+        facts.region_live_at.retain(|(_, p)| keep(p));
+        facts.invalidates.retain(|(p, _)| keep(p));
+        facts.var_used.retain(|(_, p)| keep(p));
+        facts.var_defined.retain(|(_, p)| keep(p));
+        facts.var_drop_used.retain(|(_, p)| keep(p));
+        facts.var_initialized_on_exit.retain(|(_, p)| keep(p));
+        facts.borrow_reserved_at.retain(|(_, p)| keep(p));
+        facts.borrow_activated_at.retain(|(_, p)| keep(p));
+        facts.closure_requires.retain(|(_, p)| keep(p));
+
+        let entries: BTreeSet<P> = self
+            .cfg_edge
+            .iter()
+            .map(|&(p, _)| p)
+            .filter(|&p| keep(&p) && self.cfg_edge.iter().all(|&(_, q)| q != p))
+            .collect();
+        for entry in entries {
+            let reachable = cfg::reachable_from(&facts.cfg_edge, entry);
+            let reachable_before = cfg::reachable_from(&self.cfg_edge, entry);
+            if let Some(point) = reachable_before
+                .iter()
+                .find(|&p| keep(p) && !reachable.contains(p))
+            {
+                panic!(
+                    "the point {:?} is no longer reachable from the entry point {:?}",
+                    point, entry
+                );
+            }
+        }
+        facts
+    }
+
+    /// Reduces these facts to a subset for which `predicate` still holds, and where removing
+    /// any single tuple would make it fail: a greedy delta-debugging pass. Chunks of tuples are
+    /// removed from each relation in turn, each removal being kept if `predicate` still holds,
+    /// and the chunk size is halved when no chunk can be removed, until a fixed point is reached.
+    ///
+    /// The `predicate` must hold for these facts.
+    pub fn minimize(&self, predicate: impl Fn(&Self) -> bool) -> Self {
+        let mut facts = self.clone();
+        let predicate = &predicate;
+        loop {
+            let mut changed = false;
+            macro_rules! minimize_relations {
+                ($($relation:ident,)*) => {
+                    $(
+                        changed |= minimize_relation(&mut facts, |f| &mut f.$relation, predicate);
+                    )*
+                };
+            }
+            for_each_relation!(minimize_relations!());
+            if !changed {
+                return facts;
+            }
+        }
+    }
+}
+
+/// Removes chunks of the tuples of `relation` from the `facts` for `AllFacts::minimize`, keeping
+/// each removal for which `predicate` still holds. Returns whether a tuple was removed.
+fn minimize_relation<R: Atom, L: Atom, P: Atom, V: Atom, T, F>(
+    facts: &mut AllFacts<R, L, P, V>,
+    relation: fn(&mut AllFacts<R, L, P, V>) -> &mut Vec<T>,
+    predicate: &F,
+) -> bool
+where
+    F: Fn(&AllFacts<R, L, P, V>) -> bool,
+{
+    let mut changed = false;
+    let mut chunk_size = relation(facts).len();
+    while chunk_size > 0 {
+        let mut start = 0;
+        while start < relation(facts).len() {
+            let end = (start + chunk_size).min(relation(facts).len());
+            let removed: Vec<T> = relation(facts).drain(start..end).collect();
+            if predicate(facts) {
+                changed = true;
+            } else {
+                let rest = relation(facts).split_off(start);
+                relation(facts).extend(removed);
+                relation(facts).extend(rest);
+                start = end;
+            }
+        }
+        chunk_size /= 2;
+    }
+    changed
+}
+
+/// A substitution of the regions of a callee by regions of its caller, as used when inlining
+/// it with `AllFacts::splice`. Regions without a mapping are left unchanged.
+#[derive(Clone, Debug)]
+pub struct RegionMapping<R: Atom> {
+    regions: FxHashMap<R, R>,
+}
+
+impl<R: Atom> RegionMapping<R> {
+    pub fn new() -> Self {
+        RegionMapping {
+            regions: FxHashMap::default(),
+        }
+    }
+
+    /// Maps the region `callee` of the callee to the region `caller` of the caller.
+    pub fn insert(&mut self, callee: R, caller: R) {
+        self.regions.insert(callee, caller);
+    }
+
+    /// Returns the caller's region substituted for `region`, or `region` if it is unmapped.
+    pub fn apply(&self, region: R) -> R {
+        self.regions.get(&region).cloned().unwrap_or(region)
+    }
+}
+
+impl<R: Atom> Default for RegionMapping<R> {
+    fn default() -> Self {
+        RegionMapping::new()
+    }
+}
+
+/// The atoms of the analysis: regions, loans, points and variables are all small indices,
+/// which lets tooling be generic over them.
 pub trait Atom:
     From<usize> + Into<usize> + Copy + Clone + Debug + Eq + Ord + Hash + 'static
 {
     fn index(self) -> usize;
+
+    /// Creates the atom with the given index.
+    fn from_usize(index: usize) -> Self {
+        Self::from(index)
+    }
+
+    /// Returns the index of this atom.
+    fn as_usize(self) -> usize {
+        self.into()
+    }
 }
@@ -1,15 +1,39 @@
-/// Contains the core of the Polonius borrow checking engine.
-/// Input is fed in via AllFacts, and outputs are returned via Output
+//! Contains the core of the Polonius borrow checking engine.
+//! Input is fed in via AllFacts, and outputs are returned via Output
+//!
+//! The engine does no file IO: loading the facts from files is left to its users, like the
+//! `polonius` crate. It requires `std`, and is not meant to be built as `no_std`: `datafrog`
+//! only supports `std`, the outputs use `std` hash maps, and the computations are timed with
+//! `std::time::Instant`.
+
 extern crate datafrog;
 #[macro_use]
 extern crate log;
+#[cfg(feature = "petgraph")]
+extern crate petgraph;
 extern crate rustc_hash;
 
+mod cfg;
+#[macro_use]
 mod facts;
 mod output;
+mod scc;
 
 // Reexports of facts
 pub use facts::AllFacts;
 pub use facts::Atom;
+pub use facts::RegionMapping;
+pub use facts::FACTS_SCHEMA_VERSION;
 pub use output::Algorithm;
+pub use output::AlgorithmReport;
+pub use output::AlgorithmSummary;
+pub use output::ComputeOptions;
+pub use output::CostEstimate;
+pub use output::ErrorKind;
+pub use output::HybridDiff;
+pub use output::JoinOrder;
+pub use output::LivenessDiff;
 pub use output::Output;
+pub use output::Trace;
+pub use output::TraceFormat;
+pub use output::UniversalLivenessPolicy;
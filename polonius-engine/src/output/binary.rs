@@ -0,0 +1,265 @@
+// Copyright 2019 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A compact binary encoding of an `Output`, to cache it.
+//!
+//! The atoms are written as their indices, so an encoded output can only be decoded with the
+//! atoms of the facts it was computed from. All the integers are little-endian `u32`s, and the
+//! collections are written as their length followed by their elements, the maps sorted by key.
+//!
+//! The encoding is bincode-like, but written by hand: the `bincode` crate is not a dependency,
+//! and the atoms are not `Serialize`. The bytes are therefore not readable by `bincode` itself.
+
+use rustc_hash::FxHashMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::hash::Hash;
+
+use super::{ErrorKind, Output};
+use facts::Atom;
+
+/// The first bytes of an encoded output, ending with the version of the format.
+const MAGIC: &[u8] = b"polonius-output\x03";
+
+impl<Region, Loan, Point, Variable> Output<Region, Loan, Point, Variable>
+where
+    Region: Atom,
+    Loan: Atom,
+    Point: Atom,
+    Variable: Atom,
+{
+    /// Encodes the output, all of its relations included, in a compact binary format, which
+    /// `from_bincode` decodes back to an equal output.
+    pub fn to_bincode(&self) -> Vec<u8> {
+        let Output {
+            errors,
+            closure_errors,
+            potentially_live_loans,
+            errors_classified,
+            dump_enabled,
+            truncated,
+            max_output_tuples,
+            drop_liveness_computed,
+            universal_region,
+            borrow_live_at,
+            restricts,
+            restricts_anywhere,
+            region_live_at,
+            invalidates,
+            subset,
+            subset_anywhere,
+            var_live_at,
+            var_drop_live_at,
+            peak_tuples,
+        } = self;
+
+        let mut encoder = Encoder {
+            bytes: MAGIC.to_vec(),
+        };
+        encoder.map(errors, |e, loans| e.atoms(loans));
+        encoder.map(closure_errors, |e, regions| e.atoms(regions));
+        encoder.set(potentially_live_loans);
+        encoder.map(errors_classified, |e, errors| {
+            e.u32(errors.len());
+            for &(loan, kind) in errors {
+                e.atom(loan);
+                e.u32(kind as usize);
+            }
+        });
+        encoder.u32(*dump_enabled as usize);
+        encoder.u32(*truncated as usize);
+        encoder.option(*max_output_tuples);
+        encoder.u32(*drop_liveness_computed as usize);
+        encoder.atoms(universal_region);
+        encoder.map(borrow_live_at, |e, loans| e.atoms(loans));
+        encoder.map(restricts, |e, restricts| e.nested_sets(restricts));
+        encoder.map(restricts_anywhere, |e, loans| e.set(loans));
+        encoder.map(region_live_at, |e, regions| e.atoms(regions));
+        encoder.map(invalidates, |e, loans| e.atoms(loans));
+        encoder.map(subset, |e, subset| e.nested_sets(subset));
+        encoder.map(subset_anywhere, |e, regions| e.set(regions));
+        encoder.map(var_live_at, |e, variables| e.atoms(variables));
+        encoder.map(var_drop_live_at, |e, variables| e.atoms(variables));
+        encoder.option(*peak_tuples);
+        encoder.bytes
+    }
+
+    /// Decodes an output encoded by `to_bincode`. Returns an error if the `bytes` are not an
+    /// encoded output, e.g. if they are truncated, or were encoded by another version.
+    pub fn from_bincode(bytes: &[u8]) -> Result<Self, String> {
+        if !bytes.starts_with(MAGIC) {
+            return Err(String::from("not an encoded output, or of another version"));
+        }
+
+        let mut d = Decoder {
+            bytes: &bytes[MAGIC.len()..],
+        };
+        let output = Output {
+            errors: d.map(|d| d.atoms())?,
+            closure_errors: d.map(|d| d.atoms())?,
+            potentially_live_loans: d.set()?,
+            errors_classified: d.map(|d| {
+                let len = d.u32()?;
+                (0..len).map(|_| Ok((d.atom()?, d.error_kind()?))).collect()
+            })?,
+            dump_enabled: d.bool()?,
+            truncated: d.bool()?,
+            max_output_tuples: d.option()?,
+            drop_liveness_computed: d.bool()?,
+            universal_region: d.atoms()?,
+            borrow_live_at: d.map(|d| d.atoms())?,
+            restricts: d.map(|d| d.nested_sets())?,
+            restricts_anywhere: d.map(|d| d.set())?,
+            region_live_at: d.map(|d| d.atoms())?,
+            invalidates: d.map(|d| d.atoms())?,
+            subset: d.map(|d| d.nested_sets())?,
+            subset_anywhere: d.map(|d| d.set())?,
+            var_live_at: d.map(|d| d.atoms())?,
+            var_drop_live_at: d.map(|d| d.atoms())?,
+            peak_tuples: d.option()?,
+        };
+
+        if !d.bytes.is_empty() {
+            return Err(format!(
+                "{} unexpected bytes after the output",
+                d.bytes.len()
+            ));
+        }
+        Ok(output)
+    }
+}
+
+struct Encoder {
+    bytes: Vec<u8>,
+}
+
+impl Encoder {
+    fn u32(&mut self, value: usize) {
+        assert!(value <= u32::MAX as usize, "{} doesn't fit a u32", value);
+        let value = value as u32;
+        self.bytes
+            .extend((0..4).map(|byte| (value >> (8 * byte)) as u8));
+    }
+
+    fn atom<T: Atom>(&mut self, atom: T) {
+        self.u32(atom.index());
+    }
+
+    fn atoms<T: Atom>(&mut self, atoms: &[T]) {
+        self.u32(atoms.len());
+        for &atom in atoms {
+            self.atom(atom);
+        }
+    }
+
+    fn set<T: Atom>(&mut self, set: &BTreeSet<T>) {
+        self.u32(set.len());
+        for &atom in set {
+            self.atom(atom);
+        }
+    }
+
+    fn nested_sets<K: Atom, T: Atom>(&mut self, map: &BTreeMap<K, BTreeSet<T>>) {
+        self.u32(map.len());
+        for (&key, set) in map {
+            self.atom(key);
+            self.set(set);
+        }
+    }
+
+    fn map<K: Atom, V>(&mut self, map: &FxHashMap<K, V>, mut value: impl FnMut(&mut Self, &V)) {
+        let mut keys: Vec<&K> = map.keys().collect();
+        keys.sort();
+        self.u32(keys.len());
+        for key in keys {
+            self.atom(*key);
+            value(self, &map[key]);
+        }
+    }
+
+    fn option(&mut self, value: Option<usize>) {
+        match value {
+            Some(value) => {
+                self.u32(1);
+                self.u32(value);
+            }
+            None => self.u32(0),
+        }
+    }
+}
+
+struct Decoder<'bytes> {
+    bytes: &'bytes [u8],
+}
+
+impl<'bytes> Decoder<'bytes> {
+    fn u32(&mut self) -> Result<usize, String> {
+        if self.bytes.len() < 4 {
+            return Err(String::from("truncated output"));
+        }
+        let (value, rest) = self.bytes.split_at(4);
+        self.bytes = rest;
+        let value = value.iter().enumerate().fold(0u32, |value, (byte, &b)| {
+            value | (u32::from(b) << (8 * byte))
+        });
+        Ok(value as usize)
+    }
+
+    fn bool(&mut self) -> Result<bool, String> {
+        match self.u32()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            value => Err(format!("invalid boolean {}", value)),
+        }
+    }
+
+    fn error_kind(&mut self) -> Result<ErrorKind, String> {
+        match self.u32()? {
+            0 => Ok(ErrorKind::ConflictingUse),
+            1 => Ok(ErrorKind::EscapingBorrow),
+            2 => Ok(ErrorKind::DropConflict),
+            value => Err(format!("invalid error kind {}", value)),
+        }
+    }
+
+    fn atom<T: Atom>(&mut self) -> Result<T, String> {
+        Ok(T::from(self.u32()?))
+    }
+
+    fn atoms<T: Atom>(&mut self) -> Result<Vec<T>, String> {
+        let len = self.u32()?;
+        (0..len).map(|_| self.atom()).collect()
+    }
+
+    fn set<T: Atom>(&mut self) -> Result<BTreeSet<T>, String> {
+        let len = self.u32()?;
+        (0..len).map(|_| self.atom()).collect()
+    }
+
+    fn nested_sets<K: Atom, T: Atom>(&mut self) -> Result<BTreeMap<K, BTreeSet<T>>, String> {
+        let len = self.u32()?;
+        (0..len).map(|_| Ok((self.atom()?, self.set()?))).collect()
+    }
+
+    fn map<K: Atom + Hash, V>(
+        &mut self,
+        mut value: impl FnMut(&mut Self) -> Result<V, String>,
+    ) -> Result<FxHashMap<K, V>, String> {
+        let len = self.u32()?;
+        (0..len).map(|_| Ok((self.atom()?, value(self)?))).collect()
+    }
+
+    fn option(&mut self) -> Result<Option<usize>, String> {
+        match self.u32()? {
+            0 => Ok(None),
+            1 => Ok(Some(self.u32()?)),
+            value => Err(format!("invalid option tag {}", value)),
+        }
+    }
+}
@@ -12,16 +12,23 @@ use std::collections::{BTreeMap, BTreeSet};
 use std::time::Instant;
 
 use crate::output::liveness;
-use crate::output::Output;
+use crate::output::trace::Tracer;
+#[cfg(feature = "peak-tuples")]
+use crate::output::variable_len;
+use crate::output::{ComputeOptions, JoinOrder, Output};
 
 use datafrog::{Iteration, Relation, RelationLeaper};
 use facts::{AllFacts, Atom};
 
 pub(super) fn compute<Region: Atom, Loan: Atom, Point: Atom, Variable: Atom>(
-    dump_enabled: bool,
+    options: ComputeOptions,
     all_facts: AllFacts<Region, Loan, Point, Variable>,
+    on_error: &mut dyn FnMut(Point, Loan),
 ) -> Output<Region, Loan, Point, Variable> {
-    let mut result = Output::new(dump_enabled);
+    let dump_enabled = options.dump_enabled;
+    let join_order = options.join_order;
+    let mut result = Output::new(&options);
+    let mut tracer = Tracer::new(options.trace, "datafrog_opt");
     let region_live_at = liveness::init_region_live_at(
         all_facts.var_used,
         all_facts.var_drop_used,
@@ -32,6 +39,7 @@ pub(super) fn compute<Region: Atom, Loan: Atom, Point: Atom, Variable: Atom>(
         &all_facts.cfg_edge,
         all_facts.region_live_at,
         all_facts.universal_region,
+        options.universal_liveness,
         &mut result,
     );
 
@@ -51,7 +59,7 @@ pub(super) fn compute<Region: Atom, Loan: Atom, Point: Atom, Variable: Atom>(
 
         // we need `region_live_at` in both variable and relation forms.
         // (respectively, for join and antijoin).
-        let region_live_at_rel: Relation<(Region, Point)> = region_live_at.into();;
+        let region_live_at_rel: Relation<(Region, Point)> = region_live_at.into();
         let region_live_at_var = iteration.variable::<((Region, Point), ())>("region_live_at");
 
         // `borrow_region` input but organized for join
@@ -68,6 +76,21 @@ pub(super) fn compute<Region: Atom, Loan: Atom, Point: Atom, Variable: Atom>(
         // borrow B
         let requires_rp = iteration.variable::<((Region, Point), Loan)>("requires_rp");
 
+        // The intermediate variables of the `Pairwise` join order, for the rules carrying
+        // `subset` and `requires` to the successors of P:
+        // - `subset_p`, the `subset(R1, R2, P)` tuples indexed by P, and `subset_r1q` and
+        //   `subset_r2q` the tuples carried to a successor Q, before checking that R1 then R2
+        //   are live in Q.
+        // - `requires_bp`, the `requires(R, B, P)` tuples indexed by `(B, P)`, `requires_p` those
+        //   whose loan is not killed at P, and `requires_rq` those carried to a successor Q,
+        //   before checking that R is live in Q.
+        let subset_p = iteration.variable::<(Point, (Region, Region))>("subset_p");
+        let subset_r1q = iteration.variable::<((Region, Point), Region)>("subset_r1q");
+        let subset_r2q = iteration.variable::<((Region, Point), Region)>("subset_r2q");
+        let requires_bp = iteration.variable::<((Loan, Point), Region)>("requires_bp");
+        let requires_p = iteration.variable::<(Point, (Region, Loan))>("requires_p");
+        let requires_rq = iteration.variable::<((Region, Point), Loan)>("requires_rq");
+
         // .decl borrow_live_at(B, P) -- true if the restrictions of the borrow B
         // need to be enforced at the point P
         let borrow_live_at = iteration.variable::<((Loan, Point), ())>("borrow_live_at");
@@ -166,6 +189,23 @@ pub(super) fn compute<Region: Atom, Loan: Atom, Point: Atom, Variable: Atom>(
                 .elements
                 .retain(|&((r1, _), r2)| r1 != r2);
 
+            tracer.next_round();
+            tracer.derived("subset", &subset_r1p, |&((r1, p), r2)| {
+                vec![r1.index(), r2.index(), p.index()]
+            });
+            tracer.derived("requires", &requires_rp, |&((r, p), b)| {
+                vec![r.index(), b.index(), p.index()]
+            });
+            tracer.derived("borrow_live_at", &borrow_live_at, |&((b, p), ())| {
+                vec![b.index(), p.index()]
+            });
+            tracer.derived("errors", &errors, |&(b, p): &(Loan, Point)| {
+                vec![b.index(), p.index()]
+            });
+            for &(b, p) in errors.recent.borrow().iter() {
+                on_error(p, b);
+            }
+
             // live_to_dying_regions(R1, R2, P, Q) :-
             //   subset(R1, R2, P),
             //   cfg_edge(P, Q),
@@ -252,15 +292,28 @@ pub(super) fn compute<Region: Atom, Loan: Atom, Point: Atom, Variable: Atom>(
             //
             // Carry `R1 <= R2` from P into Q if both `R1` and
             // `R2` are live in Q.
-            subset_r1p.from_leapjoin(
-                &subset_r1p,
-                (
-                    cfg_edge_rel.extend_with(|&((_, p), _)| p),
-                    region_live_at_rel.extend_with(|&((r1, _), _)| r1),
-                    region_live_at_rel.extend_with(|&((_, _), r2)| r2),
+            match join_order {
+                JoinOrder::Leapjoin => subset_r1p.from_leapjoin(
+                    &subset_r1p,
+                    (
+                        cfg_edge_rel.extend_with(|&((_, p), _)| p),
+                        region_live_at_rel.extend_with(|&((r1, _), _)| r1),
+                        region_live_at_rel.extend_with(|&((_, _), r2)| r2),
+                    ),
+                    |&((r1, _p), r2), &q| ((r1, q), r2),
                 ),
-                |&((r1, _p), r2), &q| ((r1, q), r2),
-            );
+                JoinOrder::Pairwise => {
+                    subset_p.from_map(&subset_r1p, |&((r1, p), r2)| (p, (r1, r2)));
+                    subset_r1q
+                        .from_join(&subset_p, &cfg_edge_rel, |&_p, &(r1, r2), &q| ((r1, q), r2));
+                    subset_r2q.from_join(&subset_r1q, &region_live_at_var, |&(r1, q), &r2, &()| {
+                        ((r2, q), r1)
+                    });
+                    subset_r1p.from_join(&subset_r2q, &region_live_at_var, |&(r2, q), &r1, &()| {
+                        ((r1, q), r2)
+                    });
+                }
+            }
 
             // subset(R1, R3, Q) :-
             //   live_to_dying_regions(R1, R2, P, Q),
@@ -291,15 +344,26 @@ pub(super) fn compute<Region: Atom, Loan: Atom, Point: Atom, Variable: Atom>(
             //   !killed(B, P),
             //   cfg_edge(P, Q),
             //   region_live_at(R, Q).
-            requires_rp.from_leapjoin(
-                &requires_rp,
-                (
-                    killed_rel.filter_anti(|&((_, p), b)| (b, p)),
-                    cfg_edge_rel.extend_with(|&((_, p), _)| p),
-                    region_live_at_rel.extend_with(|&((r, _), _)| r),
+            match join_order {
+                JoinOrder::Leapjoin => requires_rp.from_leapjoin(
+                    &requires_rp,
+                    (
+                        killed_rel.filter_anti(|&((_, p), b)| (b, p)),
+                        cfg_edge_rel.extend_with(|&((_, p), _)| p),
+                        region_live_at_rel.extend_with(|&((r, _), _)| r),
+                    ),
+                    |&((r, _), b), &q| ((r, q), b),
                 ),
-                |&((r, _), b), &q| ((r, q), b),
-            );
+                JoinOrder::Pairwise => {
+                    requires_bp.from_map(&requires_rp, |&((r, p), b)| ((b, p), r));
+                    requires_p.from_antijoin(&requires_bp, &killed_rel, |&(b, p), &r| (p, (r, b)));
+                    requires_rq
+                        .from_join(&requires_p, &cfg_edge_rel, |&_p, &(r, b), &q| ((r, q), b));
+                    requires_rp.from_join(&requires_rq, &region_live_at_var, |&(r, q), &b, &()| {
+                        ((r, q), b)
+                    });
+                }
+            }
 
             // dead_borrow_region_can_reach_root((R, P), B) :-
             //   borrow_region(R, B, P),
@@ -352,50 +416,64 @@ pub(super) fn compute<Region: Atom, Loan: Atom, Point: Atom, Variable: Atom>(
 
             // errors(B, P) :- invalidates(B, P), borrow_live_at(B, P).
             errors.from_join(&invalidates, &borrow_live_at, |&(b, p), &(), &()| (b, p));
+
+            #[cfg(feature = "peak-tuples")]
+            result.record_tuples(
+                variable_len(&subset_r1p)
+                    + variable_len(&requires_rp)
+                    + variable_len(&borrow_live_at),
+            );
         }
 
         if dump_enabled {
-            for (region, location) in &region_live_at_rel.elements {
-                result
-                    .region_live_at
-                    .entry(*location)
-                    .or_insert(vec![])
-                    .push(*region);
-            }
-
             let subset_r1p = subset_r1p.complete();
-            assert!(
-                subset_r1p.iter().filter(|&((r1, _), r2)| r1 == r2).count() == 0,
-                "unwanted subset symmetries"
-            );
-            for ((r1, location), r2) in &subset_r1p.elements {
-                result
-                    .subset
-                    .entry(*location)
-                    .or_insert(BTreeMap::new())
-                    .entry(*r1)
-                    .or_insert(BTreeSet::new())
-                    .insert(*r2);
-            }
-
             let requires_rp = requires_rp.complete();
-            for ((region, location), borrow) in &requires_rp.elements {
-                result
-                    .restricts
-                    .entry(*location)
-                    .or_insert(BTreeMap::new())
-                    .entry(*region)
-                    .or_insert(BTreeSet::new())
-                    .insert(*borrow);
-            }
-
-            let borrow_live_at = borrow_live_at.complete();
-            for ((borrow, location), ()) in &borrow_live_at.elements {
-                result
-                    .borrow_live_at
-                    .entry(*location)
-                    .or_insert(Vec::new())
-                    .push(*borrow);
+            if !result.exceeds_output_budget(subset_r1p.len() + requires_rp.len()) {
+                for (region, location) in &region_live_at_rel.elements {
+                    result
+                        .region_live_at
+                        .entry(*location)
+                        .or_insert(vec![])
+                        .push(*region);
+                }
+
+                assert!(
+                    subset_r1p.iter().filter(|&((r1, _), r2)| r1 == r2).count() == 0,
+                    "unwanted subset symmetries"
+                );
+                for ((r1, location), r2) in &subset_r1p.elements {
+                    result
+                        .subset
+                        .entry(*location)
+                        .or_insert(BTreeMap::new())
+                        .entry(*r1)
+                        .or_insert(BTreeSet::new())
+                        .insert(*r2);
+                    result
+                        .subset_anywhere
+                        .entry(*r1)
+                        .or_insert(BTreeSet::new())
+                        .insert(*r2);
+                }
+
+                for ((region, location), borrow) in &requires_rp.elements {
+                    result
+                        .restricts
+                        .entry(*location)
+                        .or_insert(BTreeMap::new())
+                        .entry(*region)
+                        .or_insert(BTreeSet::new())
+                        .insert(*borrow);
+                }
+
+                let borrow_live_at = borrow_live_at.complete();
+                for ((borrow, location), ()) in &borrow_live_at.elements {
+                    result
+                        .borrow_live_at
+                        .entry(*location)
+                        .or_insert(Vec::new())
+                        .push(*borrow);
+                }
             }
         }
 
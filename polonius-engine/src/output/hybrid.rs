@@ -13,17 +13,18 @@
 
 use crate::output::datafrog_opt;
 use crate::output::location_insensitive;
-use crate::output::Output;
+use crate::output::{ComputeOptions, Output};
 use facts::{AllFacts, Atom};
 
 pub(super) fn compute<Region: Atom, Loan: Atom, Point: Atom, Variable: Atom>(
-    dump_enabled: bool,
+    options: ComputeOptions,
     all_facts: AllFacts<Region, Loan, Point, Variable>,
+    on_error: &mut dyn FnMut(Point, Loan),
 ) -> Output<Region, Loan, Point, Variable> {
-    let lins_output = location_insensitive::compute(dump_enabled, &all_facts);
+    let lins_output = location_insensitive::compute(options.clone(), &all_facts);
     if lins_output.errors.is_empty() {
         lins_output
     } else {
-        datafrog_opt::compute(dump_enabled, all_facts)
+        datafrog_opt::compute(options, all_facts, on_error)
     }
 }
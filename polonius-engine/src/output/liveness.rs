@@ -13,7 +13,7 @@
 use std::collections::BTreeSet;
 use std::time::Instant;
 
-use crate::output::Output;
+use crate::output::{Output, UniversalLivenessPolicy};
 use facts::Atom;
 
 use datafrog::{Iteration, Relation, RelationLeaper};
@@ -53,16 +53,39 @@ pub(super) fn compute_live_regions<Region: Atom, Loan: Atom, Point: Atom, Variab
     // This propagates the relation `var_live(V, P) :- var_used(V, P)`:
     var_live_var.insert(var_used.into());
 
+    // Without drop-uses, no variable is drop-live, and the drop-liveness rules are skipped.
+    output.drop_liveness_computed = !var_drop_used.is_empty();
+
     // This propagates the relation `var_drop_live(V, P) :- var_drop_used(V, P)`:
     var_drop_live_var.insert(var_drop_used.into());
 
     while iteration.changed() {
-        // region_live_at(R, P) :-
-        //   var_drop_live(V, P),
-        //   var_drops_region(V, R).
-        region_live_at_var.from_join(&var_drop_live_var, &var_drops_region_rel, |_v, &p, &r| {
-            ((r, p), ())
-        });
+        if output.drop_liveness_computed {
+            // region_live_at(R, P) :-
+            //   var_drop_live(V, P),
+            //   var_drops_region(V, R).
+            region_live_at_var.from_join(
+                &var_drop_live_var,
+                &var_drops_region_rel,
+                |_v, &p, &r| ((r, p), ()),
+            );
+
+            // var_drop_live(V, P) :-
+            //     var_drop_live(V, Q),
+            //     cfg_edge(P, Q),
+            //     !var_defined(V, P)
+            //     var_initialized_on_exit(V, P).
+            // extend p with v:s from q such that v is not in q, there is an edge from p to q
+            var_drop_live_var.from_leapjoin(
+                &var_drop_live_var,
+                (
+                    var_defined_rel.extend_anti(|&(v, _q)| v),
+                    cfg_edge_reverse_rel.extend_with(|&(_v, q)| q),
+                    var_initialized_on_exit_rel.extend_with(|&(v, _q)| v),
+                ),
+                |&(v, _q), &p| (v, p),
+            );
+        }
 
         // region_live_at(R, P) :-
         //   var_live(V, P),
@@ -84,22 +107,6 @@ pub(super) fn compute_live_regions<Region: Atom, Loan: Atom, Point: Atom, Variab
             ),
             |&(v, _q), &p| (v, p),
         );
-
-        // var_drop_live(V, P) :-
-        //     var_drop_live(V, Q),
-        //     cfg_edge(P, Q),
-        //     !var_defined(V, P)
-        //     var_initialized_on_exit(V, P).
-        // extend p with v:s from q such that v is not in q, there is an edge from p to q
-        var_drop_live_var.from_leapjoin(
-            &var_drop_live_var,
-            (
-                var_defined_rel.extend_anti(|&(v, _q)| v),
-                cfg_edge_reverse_rel.extend_with(|&(_v, q)| q),
-                var_initialized_on_exit_rel.extend_with(|&(v, _q)| v),
-            ),
-            |&(v, _q), &p| (v, p),
-        );
     }
 
     let region_live_at_rel = region_live_at_var.complete();
@@ -136,12 +143,49 @@ pub(super) fn compute_live_regions<Region: Atom, Loan: Atom, Point: Atom, Variab
         .collect()
 }
 
+/// Computes the `var_live` relation like `compute_live_regions`, one round of the backward
+/// dataflow at a time, and returns its tuples after each round: the first round contains the
+/// `var_used` facts, and the last one is the fixpoint.
+pub(super) fn var_live_iterations<Point: Atom, Variable: Atom>(
+    var_used: &[(Variable, Point)],
+    var_defined: &[(Variable, Point)],
+    cfg_edge: &[(Point, Point)],
+) -> Vec<BTreeSet<(Variable, Point)>> {
+    let var_defined: BTreeSet<(Variable, Point)> = var_defined.iter().cloned().collect();
+    let mut var_live: BTreeSet<(Variable, Point)> = var_used.iter().cloned().collect();
+    let mut recent = var_live.clone();
+    let mut iterations = vec![var_live.clone()];
+
+    loop {
+        // var_live(V, P) :-
+        //     var_live(V, Q),
+        //     cfg_edge(P, Q),
+        //     !var_defined(V, P).
+        let mut derived = BTreeSet::new();
+        for &(v, q) in &recent {
+            for &(p, _) in cfg_edge.iter().filter(|&&(_, q2)| q2 == q) {
+                if !var_defined.contains(&(v, p)) && !var_live.contains(&(v, p)) {
+                    derived.insert((v, p));
+                }
+            }
+        }
+
+        if derived.is_empty() {
+            return iterations;
+        }
+        var_live.extend(derived.iter().cloned());
+        iterations.push(var_live.clone());
+        recent = derived;
+    }
+}
+
 pub(super) fn make_universal_region_live<Region: Atom, Point: Atom>(
     region_live_at: &mut Vec<(Region, Point)>,
     cfg_edge: &[(Point, Point)],
     universal_region: Vec<Region>,
+    policy: UniversalLivenessPolicy,
 ) {
-    debug!("make_universal_regions_live()");
+    debug!("make_universal_regions_live({:?})", policy);
 
     let all_points: BTreeSet<Point> = cfg_edge
         .iter()
@@ -149,9 +193,22 @@ pub(super) fn make_universal_region_live<Region: Atom, Point: Atom>(
         .chain(cfg_edge.iter().map(|&(_, q)| q))
         .collect();
 
-    region_live_at.reserve(universal_region.len() * all_points.len());
+    let live_points: Vec<Point> = match policy {
+        UniversalLivenessPolicy::Everywhere => all_points.into_iter().collect(),
+        UniversalLivenessPolicy::ExitOnly => {
+            let points_with_successors: BTreeSet<Point> =
+                cfg_edge.iter().map(|&(p, _)| p).collect();
+            all_points
+                .difference(&points_with_successors)
+                .cloned()
+                .collect()
+        }
+        UniversalLivenessPolicy::Explicit => return,
+    };
+
+    region_live_at.reserve(universal_region.len() * live_points.len());
     for &r in &universal_region {
-        for &p in &all_points {
+        for &p in &live_points {
             region_live_at.push((r, p));
         }
     }
@@ -167,6 +224,7 @@ pub(super) fn init_region_live_at<Region: Atom, Loan: Atom, Point: Atom, Variabl
     cfg_edge: &[(Point, Point)],
     region_live_at: Vec<(Region, Point)>,
     universal_region: Vec<Region>,
+    universal_liveness: UniversalLivenessPolicy,
     output: &mut Output<Region, Loan, Point, Variable>,
 ) -> Vec<(Region, Point)> {
     debug!("init_region_live_at()");
@@ -187,7 +245,12 @@ pub(super) fn init_region_live_at<Region: Atom, Loan: Atom, Point: Atom, Variabl
         region_live_at
     };
 
-    make_universal_region_live(&mut region_live_at, cfg_edge, universal_region);
+    make_universal_region_live(
+        &mut region_live_at,
+        cfg_edge,
+        universal_region,
+        universal_liveness,
+    );
 
     region_live_at
 }
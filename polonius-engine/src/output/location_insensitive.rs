@@ -8,20 +8,24 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use rustc_hash::FxHashSet;
 use std::collections::BTreeSet;
 use std::time::Instant;
 
 use crate::output::liveness;
-use crate::output::Output;
+#[cfg(feature = "peak-tuples")]
+use crate::output::variable_len;
+use crate::output::{ComputeOptions, Output};
 
 use datafrog::{Iteration, Relation, RelationLeaper};
 use facts::{AllFacts, Atom};
 
 pub(super) fn compute<Region: Atom, Loan: Atom, Point: Atom, Variable: Atom>(
-    dump_enabled: bool,
+    options: ComputeOptions,
     all_facts: &AllFacts<Region, Loan, Point, Variable>,
 ) -> Output<Region, Loan, Point, Variable> {
-    let mut result = Output::new(dump_enabled);
+    let dump_enabled = options.dump_enabled;
+    let mut result = Output::new(&options);
     let region_live_at = liveness::init_region_live_at(
         all_facts.var_used.clone(),
         all_facts.var_drop_used.clone(),
@@ -32,6 +36,7 @@ pub(super) fn compute<Region: Atom, Loan: Atom, Point: Atom, Variable: Atom>(
         &all_facts.cfg_edge,
         all_facts.region_live_at.clone(),
         all_facts.universal_region.clone(),
+        options.universal_liveness,
         &mut result,
     );
 
@@ -81,33 +86,50 @@ pub(super) fn compute<Region: Atom, Loan: Atom, Point: Atom, Variable: Atom>(
                 ),
                 |&(_r, b), &p| (b, p),
             );
+
+            #[cfg(feature = "peak-tuples")]
+            result.record_tuples(
+                variable_len(&subset) + variable_len(&requires) + variable_len(&potential_errors),
+            );
         }
 
+        // potentially_live_loans(B) :- requires(R, B), region_live_at(R, _P).
+        let requires = requires.complete();
+        let live_regions: FxHashSet<Region> =
+            region_live_at.elements.iter().map(|&(r, _p)| r).collect();
+        result.potentially_live_loans = requires
+            .elements
+            .iter()
+            .filter(|&&(r, _b)| live_regions.contains(&r))
+            .map(|&(_r, b)| b)
+            .collect();
+
         if dump_enabled {
             let subset = subset.complete();
-            for (r1, r2) in &subset.elements {
-                result
-                    .subset_anywhere
-                    .entry(*r1)
-                    .or_insert(BTreeSet::new())
-                    .insert(*r2);
-            }
-
-            let requires = requires.complete();
-            for (region, borrow) in &requires.elements {
-                result
-                    .restricts_anywhere
-                    .entry(*region)
-                    .or_insert(BTreeSet::new())
-                    .insert(*borrow);
-            }
-
-            for (region, location) in &region_live_at.elements {
-                result
-                    .region_live_at
-                    .entry(*location)
-                    .or_insert(vec![])
-                    .push(*region);
+            if !result.exceeds_output_budget(subset.len() + requires.len()) {
+                for (r1, r2) in &subset.elements {
+                    result
+                        .subset_anywhere
+                        .entry(*r1)
+                        .or_insert(BTreeSet::new())
+                        .insert(*r2);
+                }
+
+                for (region, borrow) in &requires.elements {
+                    result
+                        .restricts_anywhere
+                        .entry(*region)
+                        .or_insert(BTreeSet::new())
+                        .insert(*borrow);
+                }
+
+                for (region, location) in &region_live_at.elements {
+                    result
+                        .region_live_at
+                        .entry(*location)
+                        .or_insert(vec![])
+                        .push(*region);
+                }
             }
         }
 
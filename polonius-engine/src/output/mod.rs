@@ -8,16 +8,25 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 use std::borrow::Cow;
 use std::collections::{BTreeMap, BTreeSet};
+use std::hash::Hash;
+use std::time::{Duration, Instant};
 
+#[cfg(feature = "binary-cache")]
+mod binary;
 mod datafrog_opt;
 mod hybrid;
 mod liveness;
 mod location_insensitive;
 mod naive;
+mod trace;
+use cfg;
 use facts::{AllFacts, Atom};
+use scc;
+
+pub use self::trace::{Trace, TraceFormat};
 
 #[derive(Debug, Clone, Copy)]
 pub enum Algorithm {
@@ -60,12 +69,122 @@ impl ::std::str::FromStr for Algorithm {
     }
 }
 
-#[derive(Clone, Debug)]
+/// Options of the computation of an `Output`.
+#[derive(Clone, Debug, Default)]
+pub struct ComputeOptions {
+    /// Whether to also compute the verbose relations, which are only used for debugging.
+    pub dump_enabled: bool,
+
+    /// The maximum number of tuples of the main verbose relations (`subset` and `restricts`, or
+    /// their location-insensitive equivalents). When the budget is exceeded, all the verbose
+    /// relations are dropped and the output is marked as `truncated`: this never affects the
+    /// `errors`.
+    pub max_output_tuples: Option<usize>,
+
+    /// Whether to ignore the `var_drop_used` facts, so that only the uses of variables make
+    /// their regions live: the output is then the same as if there were no drop-uses.
+    pub ignore_drop_liveness: bool,
+
+    /// Where to write the tuples derived at each round of the fixpoint, for the `Naive` and
+    /// `DatafrogOpt` algorithms. Nothing is traced by default.
+    pub trace: Option<Trace>,
+
+    /// Where the universal regions are live, in addition to the `region_live_at` facts.
+    pub universal_liveness: UniversalLivenessPolicy,
+
+    /// How `DatafrogOpt`, and `Hybrid` when it escalates, join the premises of the rules
+    /// carrying `subset` and `requires` along the CFG edges, to benchmark alternatives. The
+    /// output doesn't depend on it.
+    pub join_order: JoinOrder,
+}
+
+/// Where the universal regions, which outlive the function body, are considered live.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UniversalLivenessPolicy {
+    /// At every point of the CFG: the default.
+    #[default]
+    Everywhere,
+    /// Only at the exit points of the CFG, the points without successors.
+    ExitOnly,
+    /// Only where the `region_live_at` facts, provided or computed, make them live.
+    Explicit,
+}
+
+/// How the premises of a rule are joined, in the rules of `DatafrogOpt` carrying `subset` and
+/// `requires` from a point to its successors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum JoinOrder {
+    /// A single leapjoin of the relation with `cfg_edge` and `region_live_at`, which proposes
+    /// the successors and intersects them with the live regions, tuple by tuple: the default.
+    #[default]
+    Leapjoin,
+    /// Binary joins, through intermediate variables: first with `cfg_edge`, then with
+    /// `region_live_at` at the successors, one region at a time.
+    Pairwise,
+}
+
+impl JoinOrder {
+    pub fn variants() -> [&'static str; 2] {
+        ["Leapjoin", "Pairwise"]
+    }
+}
+
+impl ::std::str::FromStr for JoinOrder {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_ref() {
+            "leapjoin" => Ok(JoinOrder::Leapjoin),
+            "pairwise" => Ok(JoinOrder::Pairwise),
+            _ => Err(String::from("valid values: Leapjoin, Pairwise")),
+        }
+    }
+}
+
+/// The kind of an error, telling why the invalidated loan was still live.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ErrorKind {
+    /// The loan is invalidated by a use, like a write or a move, of the borrowed data.
+    ConflictingUse,
+    /// The loan is invalidated by a use while it may escape the function: its region outlives
+    /// a universal region.
+    EscapingBorrow,
+    /// The loan is invalidated by the drop of a variable.
+    DropConflict,
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Output<Region: Atom, Loan: Atom, Point: Atom, Variable: Atom> {
     pub errors: FxHashMap<Point, Vec<Loan>>,
 
+    /// The unsatisfied `closure_requires` facts: the regions which a closure requires to outlive
+    /// it, but which may contain a loan where it is created. Only computed by `Naive`.
+    pub closure_errors: FxHashMap<Point, Vec<Region>>,
+
+    /// The loans which may be live at some point, whatever the point: a location-insensitive
+    /// over-approximation of the loans in `borrow_live_at`. Only computed by
+    /// `LocationInsensitive`.
+    potentially_live_loans: BTreeSet<Loan>,
+
+    /// The `errors`, along with their kind. Only computed when the dump is enabled, as only
+    /// diagnostics need it.
+    pub errors_classified: FxHashMap<Point, Vec<(Loan, ErrorKind)>>,
+
     pub dump_enabled: bool,
 
+    /// Whether the verbose relations were dropped, as they exceeded the `max_output_tuples`
+    /// budget of the `ComputeOptions`.
+    pub truncated: bool,
+
+    max_output_tuples: Option<usize>,
+
+    /// Whether the drop-liveness of the variables was computed: it is skipped when there are no
+    /// `var_drop_used` facts, e.g. when they were missing from the input, or ignored with
+    /// `ComputeOptions::ignore_drop_liveness`, and when the `region_live_at` facts are provided.
+    pub drop_liveness_computed: bool,
+
+    /// The `universal_region` facts the output was computed from.
+    universal_region: Vec<Region>,
+
     // these are just for debugging
     pub borrow_live_at: FxHashMap<Point, Vec<Loan>>,
     pub restricts: FxHashMap<Point, BTreeMap<Region, BTreeSet<Loan>>>,
@@ -76,6 +195,307 @@ pub struct Output<Region: Atom, Loan: Atom, Point: Atom, Variable: Atom> {
     pub subset_anywhere: FxHashMap<Region, BTreeSet<Region>>,
     pub var_live_at: FxHashMap<Point, Vec<Variable>>,
     pub var_drop_live_at: FxHashMap<Point, Vec<Variable>>,
+
+    /// The largest number of tuples reached by the core relations (`subset`, `requires` and
+    /// `borrow_live_at`, or their equivalents) during the computation: a hardware-independent
+    /// proxy for its peak memory usage. Only tracked with the `peak-tuples` feature.
+    pub peak_tuples: Option<usize>,
+}
+
+/// The differences in liveness between two outputs, computed by `Output::liveness_diff`: for
+/// each point where they differ, the variables or regions live only in the first output, and
+/// those live only in the second one.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LivenessDiff<Region: Atom, Point: Atom, Variable: Atom> {
+    pub var_live_at: BTreeMap<Point, (BTreeSet<Variable>, BTreeSet<Variable>)>,
+    pub region_live_at: BTreeMap<Point, (BTreeSet<Region>, BTreeSet<Region>)>,
+}
+
+impl<Region: Atom, Point: Atom, Variable: Atom> LivenessDiff<Region, Point, Variable> {
+    /// Whether the two outputs have the same liveness.
+    pub fn is_empty(&self) -> bool {
+        self.var_live_at.is_empty() && self.region_live_at.is_empty()
+    }
+}
+
+/// The subset relations `(R1, R2)` at a point, where `R1` is a subset of `R2`.
+type SubsetPairs<Region> = BTreeSet<(Region, Region)>;
+
+/// The differences between the `Hybrid` and `DatafrogOpt` outputs of some facts, computed by
+/// `Output::hybrid_diff`: for each point where they differ, the tuples only in the `Hybrid`
+/// output, and those only in the `DatafrogOpt` output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HybridDiff<Region: Atom, Loan: Atom, Point: Atom> {
+    /// Whether `Hybrid` escalated to the location-sensitive analysis, as the location-insensitive
+    /// one found potential errors. Otherwise, only the errors are compared.
+    pub escalated: bool,
+    pub errors: BTreeMap<Point, (BTreeSet<Loan>, BTreeSet<Loan>)>,
+    pub borrow_live_at: BTreeMap<Point, (BTreeSet<Loan>, BTreeSet<Loan>)>,
+    pub subset: BTreeMap<Point, (SubsetPairs<Region>, SubsetPairs<Region>)>,
+}
+
+impl<Region: Atom, Loan: Atom, Point: Atom> HybridDiff<Region, Loan, Point> {
+    /// Whether the two outputs agree, as they should.
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty() && self.borrow_live_at.is_empty() && self.subset.is_empty()
+    }
+}
+
+/// Heuristics about the size of a computation, computed by `Output::estimate_cost` without
+/// running it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CostEstimate {
+    /// The number of points of the CFG.
+    pub points: usize,
+
+    /// The number of regions appearing in the facts.
+    pub regions: usize,
+
+    /// The number of loans introduced by the `borrow_region` facts.
+    pub loans: usize,
+
+    /// The number of `outlives` facts, i.e. the number of edges of the initial subset graph.
+    pub outlives: usize,
+
+    /// A rough upper bound of the work needed to reach the fixpoint: the number of `requires`
+    /// tuples which could be derived, `points * regions * loans`. It saturates instead of
+    /// overflowing.
+    pub work: usize,
+}
+
+/// The errors of each algorithm on a set of facts, compared to the errors of `Naive`, as computed
+/// by `Output::algorithm_report`.
+#[derive(Clone, Debug)]
+pub struct AlgorithmReport {
+    pub algorithms: Vec<AlgorithmSummary>,
+}
+
+/// The errors of a single algorithm in an `AlgorithmReport`.
+#[derive(Clone, Debug)]
+pub struct AlgorithmSummary {
+    pub algorithm: Algorithm,
+
+    /// The number of `(point, loan)` errors.
+    pub error_count: usize,
+
+    /// Whether the errors are the errors of `Naive`, or, for the `LocationInsensitive`
+    /// approximation, whether they contain them.
+    pub agrees_with_naive: bool,
+
+    /// The duration of the computation, when measured.
+    pub duration: Option<Duration>,
+}
+
+impl AlgorithmReport {
+    /// Returns whether every algorithm agrees with `Naive`.
+    pub fn all_agree(&self) -> bool {
+        self.algorithms
+            .iter()
+            .all(|summary| summary.agrees_with_naive)
+    }
+
+    /// Formats the report as a JSON object, like
+    /// `{"algorithms":[{"algorithm":"Naive","error_count":1,"agrees_with_naive":true,
+    /// "duration_secs":null},...]}`.
+    pub fn to_json(&self) -> String {
+        let algorithms: Vec<String> = self
+            .algorithms
+            .iter()
+            .map(|summary| {
+                let duration = match summary.duration {
+                    Some(duration) => format!(
+                        "{}",
+                        duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) * 1e-9
+                    ),
+                    None => "null".to_string(),
+                };
+                format!(
+                    "{{\"algorithm\":\"{:?}\",\"error_count\":{},\"agrees_with_naive\":{},\
+                     \"duration_secs\":{}}}",
+                    summary.algorithm, summary.error_count, summary.agrees_with_naive, duration
+                )
+            })
+            .collect();
+        format!("{{\"algorithms\":[{}]}}", algorithms.join(","))
+    }
+}
+
+/// Computes, for each point where the two maps differ, the values only in `a` and the values
+/// only in `b`.
+fn diff_by_point<Point: Atom, T: Clone + Ord>(
+    a: &FxHashMap<Point, Vec<T>>,
+    b: &FxHashMap<Point, Vec<T>>,
+) -> BTreeMap<Point, (BTreeSet<T>, BTreeSet<T>)> {
+    let points: BTreeSet<Point> = a.keys().chain(b.keys()).cloned().collect();
+    points
+        .into_iter()
+        .filter_map(|point| {
+            let values_at = |map: &FxHashMap<Point, Vec<T>>| -> BTreeSet<T> {
+                map.get(&point).into_iter().flatten().cloned().collect()
+            };
+            let (a_values, b_values) = (values_at(a), values_at(b));
+            if a_values == b_values {
+                return None;
+            }
+
+            let only_a = a_values.difference(&b_values).cloned().collect();
+            let only_b = b_values.difference(&a_values).cloned().collect();
+            Some((point, (only_a, only_b)))
+        })
+        .collect()
+}
+
+/// Extends the `killed` facts with the kills of the reborrows: a loan reborrowing another loan,
+/// according to `loan_reborrows`, is killed wherever the other loan is, its own reborrows too.
+fn killed_with_reborrows<L: Atom, P: Atom>(
+    killed: &[(L, P)],
+    loan_reborrows: &[(L, L)],
+) -> Vec<(L, P)> {
+    let mut reborrows: FxHashMap<L, Vec<L>> = FxHashMap::default();
+    for &(loan, parent) in loan_reborrows {
+        reborrows.entry(parent).or_default().push(loan);
+    }
+
+    let mut all_killed: BTreeSet<(L, P)> = killed.iter().cloned().collect();
+    let mut stack: Vec<(L, P)> = killed.to_vec();
+    while let Some((parent, point)) = stack.pop() {
+        for &loan in reborrows.get(&parent).into_iter().flatten() {
+            if all_killed.insert((loan, point)) {
+                stack.push((loan, point));
+            }
+        }
+    }
+    all_killed.into_iter().collect()
+}
+
+/// Returns the longest path of the graph described by `edges` ending at one of the `targets`, as
+/// its nodes, or `None` if no path of at least one edge reaches a target. All the nodes of a cycle
+/// are part of the path, as the regions of a cycle of the subset relation are equal: the graph is
+/// walked as a DAG of its strongly-connected components.
+fn longest_path_to<Node: Atom>(
+    edges: &[(Node, Node)],
+    targets: &BTreeSet<Node>,
+) -> Option<Vec<Node>> {
+    /// The length, in nodes, of the longest path from the `component` to a target, and the next
+    /// component on this path, if any.
+    fn longest_from<Node: Atom>(
+        component: usize,
+        components: &[Vec<Node>],
+        successors: &[BTreeSet<usize>],
+        targets: &BTreeSet<Node>,
+        memo: &mut FxHashMap<usize, Option<(usize, Option<usize>)>>,
+    ) -> Option<(usize, Option<usize>)> {
+        if let Some(&known) = memo.get(&component) {
+            return known;
+        }
+
+        let size = components[component].len();
+        let mut longest = if components[component].iter().any(|n| targets.contains(n)) {
+            Some((size, None))
+        } else {
+            None
+        };
+        for &next in &successors[component] {
+            if let Some((length, _)) = longest_from(next, components, successors, targets, memo) {
+                let is_longer = match longest {
+                    Some((longest_length, _)) => size + length > longest_length,
+                    None => true,
+                };
+                if is_longer {
+                    longest = Some((size + length, Some(next)));
+                }
+            }
+        }
+        memo.insert(component, longest);
+        longest
+    }
+
+    let components = scc::strongly_connected_components(edges);
+    let component_of: FxHashMap<Node, usize> = components
+        .iter()
+        .enumerate()
+        .flat_map(|(index, component)| component.iter().map(move |&node| (node, index)))
+        .collect();
+    let mut successors = vec![BTreeSet::new(); components.len()];
+    for &(a, b) in edges {
+        let (a, b) = (component_of[&a], component_of[&b]);
+        if a != b {
+            successors[a].insert(b);
+        }
+    }
+
+    let mut memo = FxHashMap::default();
+    let mut start = None;
+    for component in 0..components.len() {
+        if let Some((length, _)) =
+            longest_from(component, &components, &successors, targets, &mut memo)
+        {
+            let is_longer = match start {
+                Some((_, start_length)) => length > start_length,
+                None => true,
+            };
+            if is_longer {
+                start = Some((component, length));
+            }
+        }
+    }
+
+    let (mut component, length) = start?;
+    if length < 2 {
+        return None;
+    }
+    let mut path = Vec::with_capacity(length);
+    loop {
+        let mut nodes = components[component].clone();
+        match memo[&component] {
+            Some((_, Some(next))) => {
+                path.extend(nodes);
+                component = next;
+            }
+            _ => {
+                // The path ends at a target of the last component.
+                let target = nodes.iter().position(|n| targets.contains(n)).unwrap();
+                let target = nodes.remove(target);
+                path.extend(nodes);
+                path.push(target);
+                return Some(path);
+            }
+        }
+    }
+}
+
+/// The number of tuples currently contained in a datafrog variable.
+#[cfg(feature = "peak-tuples")]
+fn variable_len<Tuple: Ord>(variable: &datafrog::Variable<Tuple>) -> usize {
+    let stable: usize = variable
+        .stable
+        .borrow()
+        .iter()
+        .map(|batch| batch.len())
+        .sum();
+    stable + variable.recent.borrow().len()
+}
+
+/// Returns the tuples of a `relation` of the `facts` whose individual removal doesn't change the
+/// `errors` computed by `algorithm`.
+/// Returns the tuples of `tuples` which are not in `kept`, which is a subset of them: each
+/// tuple of `kept` accounts for one of the duplicates of `tuples`.
+fn removed_tuples<T: Clone + Eq + Hash>(tuples: &[T], kept: &[T]) -> Vec<T> {
+    let mut kept_counts: FxHashMap<&T, usize> = FxHashMap::default();
+    for tuple in kept {
+        *kept_counts.entry(tuple).or_default() += 1;
+    }
+    tuples
+        .iter()
+        .filter(|tuple| match kept_counts.get_mut(tuple) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                false
+            }
+            _ => true,
+        })
+        .cloned()
+        .collect()
 }
 
 /// Compares errors reported by Naive implementation with the errors
@@ -126,15 +546,67 @@ where
         algorithm: Algorithm,
         dump_enabled: bool,
     ) -> Self {
-        match algorithm {
-            Algorithm::Naive => naive::compute(dump_enabled, all_facts.clone()),
-            Algorithm::DatafrogOpt => datafrog_opt::compute(dump_enabled, all_facts.clone()),
+        let options = ComputeOptions {
+            dump_enabled,
+            ..ComputeOptions::default()
+        };
+        Self::compute_with_options(all_facts, algorithm, options)
+    }
+
+    /// Computes the output like `compute`, with the given `options`.
+    pub fn compute_with_options(
+        all_facts: &AllFacts<Region, Loan, Point, Variable>,
+        algorithm: Algorithm,
+        options: ComputeOptions,
+    ) -> Self {
+        Self::compute_with_error_callback(all_facts, algorithm, options, |_, _| {})
+    }
+
+    /// Computes the output like `compute_with_options`, calling `on_error(point, loan)` for each
+    /// error as it is discovered, e.g. to show the errors of a large function incrementally.
+    /// The returned output still contains all the errors.
+    ///
+    /// `Naive`, `DatafrogOpt` and `Hybrid` call it at each round of their fixpoint, with the
+    /// errors derived in the previous round, while `LocationInsensitive` calls it once its
+    /// computation is done; `Compare` reports the errors of `DatafrogOpt`. Each error is
+    /// reported exactly once, but in no particular order, even within a round.
+    pub fn compute_with_error_callback(
+        all_facts: &AllFacts<Region, Loan, Point, Variable>,
+        algorithm: Algorithm,
+        options: ComputeOptions,
+        mut on_error: impl FnMut(Point, Loan),
+    ) -> Self {
+        let mut preprocessed_facts = None;
+        if options.ignore_drop_liveness {
+            preprocessed_facts
+                .get_or_insert_with(|| all_facts.clone())
+                .var_drop_used
+                .clear();
+        }
+        // For now, a reborrow is simply killed along with the loan it reborrows, transitively,
+        // which ends its scope with its parent's.
+        if !all_facts.loan_reborrows.is_empty() {
+            let facts = preprocessed_facts.get_or_insert_with(|| all_facts.clone());
+            facts.killed = killed_with_reborrows(&facts.killed, &facts.loan_reborrows);
+        }
+        let all_facts = preprocessed_facts.as_ref().unwrap_or(all_facts);
+
+        let mut output = match algorithm {
+            Algorithm::Naive => naive::compute(options, all_facts.clone(), &mut on_error),
+            Algorithm::DatafrogOpt => {
+                datafrog_opt::compute(options, all_facts.clone(), &mut on_error)
+            }
             Algorithm::LocationInsensitive => {
-                location_insensitive::compute(dump_enabled, &all_facts)
+                let output = location_insensitive::compute(options, &all_facts);
+                for (point, loan) in output.all_errors() {
+                    on_error(point, loan);
+                }
+                output
             }
             Algorithm::Compare => {
-                let naive_output = naive::compute(dump_enabled, all_facts.clone());
-                let opt_output = datafrog_opt::compute(dump_enabled, all_facts.clone());
+                let naive_output =
+                    naive::compute(options.clone(), all_facts.clone(), &mut |_, _| {});
+                let opt_output = datafrog_opt::compute(options, all_facts.clone(), &mut on_error);
                 if compare_errors(&naive_output.errors, &opt_output.errors) {
                     panic!(concat!(
                         "The errors reported by the naive algorithm differ from ",
@@ -146,11 +618,327 @@ where
                 }
                 opt_output
             }
-            Algorithm::Hybrid => hybrid::compute(dump_enabled, all_facts.clone()),
+            Algorithm::Hybrid => hybrid::compute(options, all_facts.clone(), &mut on_error),
+        };
+        if output.dump_enabled {
+            output.errors_classified = output.classify_errors(all_facts);
+        }
+        output.universal_region = all_facts.universal_region.clone();
+        output
+    }
+
+    /// Computes the output of the `facts` as if they also contained the `outlives` fact `edge`,
+    /// leaving them untouched: comparing its errors to the errors of the `facts` shows the
+    /// effect of a hypothetical lifetime constraint. Only the errors are computed.
+    pub fn with_added_outlives(
+        all_facts: &AllFacts<Region, Loan, Point, Variable>,
+        edge: (Region, Region, Point),
+        algorithm: Algorithm,
+    ) -> Self {
+        let mut all_facts = all_facts.clone();
+        all_facts.outlives.push(edge);
+        Self::compute(&all_facts, algorithm, false)
+    }
+
+    /// Computes the output of the `facts` as if they also contained the fact `killed(loan,
+    /// point)`, like `with_added_outlives`: the loan then stops flowing to the successors of
+    /// `point`, which shows whether shortening its scope there removes an error. Only the errors
+    /// are computed.
+    pub fn with_added_kill(
+        all_facts: &AllFacts<Region, Loan, Point, Variable>,
+        loan: Loan,
+        point: Point,
+        algorithm: Algorithm,
+    ) -> Self {
+        let mut all_facts = all_facts.clone();
+        all_facts.killed.push((loan, point));
+        Self::compute(&all_facts, algorithm, false)
+    }
+
+    /// Returns the tuples of the `facts` which don't affect the errors computed by `algorithm`:
+    /// candidates for trimming when reducing a test case. They are found by a greedy
+    /// delta-debugging pass, `AllFacts::minimize`, so that removing all of them at once doesn't
+    /// change the errors, whereas removing any of the other tuples would.
+    ///
+    /// This computes the output many times, and is therefore much slower than `compute`.
+    pub fn redundant_facts(
+        facts: &AllFacts<Region, Loan, Point, Variable>,
+        algorithm: Algorithm,
+    ) -> AllFacts<Region, Loan, Point, Variable> {
+        let errors = Self::compute(facts, algorithm, false).all_errors();
+        let kept = facts.minimize(|f| Self::compute(f, algorithm, false).all_errors() == errors);
+        macro_rules! redundant_facts {
+            ($($relation:ident,)*) => {
+                AllFacts {
+                    $(
+                        $relation: removed_tuples(&facts.$relation, &kept.$relation),
+                    )*
+                }
+            };
+        }
+
+        for_each_relation!(redundant_facts!())
+    }
+
+    /// Computes the errors of the `facts` with each algorithm, and compares them to the errors
+    /// of `Naive`, like the tests do: the `LocationInsensitive` errors must contain them, and
+    /// the other algorithms' errors must be the same. `Compare` is skipped, as it panics when
+    /// `Naive` and `DatafrogOpt` disagree, which the report shows anyways. The computations are
+    /// timed if `measure` is set.
+    pub fn algorithm_report(
+        facts: &AllFacts<Region, Loan, Point, Variable>,
+        measure: bool,
+    ) -> AlgorithmReport {
+        let compute = |algorithm| {
+            let start = Instant::now();
+            let output = Self::compute(facts, algorithm, false);
+            let duration = if measure { Some(start.elapsed()) } else { None };
+            (output.all_errors(), duration)
+        };
+
+        let (naive_errors, naive_duration) = compute(Algorithm::Naive);
+        let mut algorithms = vec![AlgorithmSummary {
+            algorithm: Algorithm::Naive,
+            error_count: naive_errors.len(),
+            agrees_with_naive: true,
+            duration: naive_duration,
+        }];
+        for &algorithm in &[
+            Algorithm::DatafrogOpt,
+            Algorithm::LocationInsensitive,
+            Algorithm::Hybrid,
+        ] {
+            let (errors, duration) = compute(algorithm);
+            let agrees_with_naive = match algorithm {
+                Algorithm::LocationInsensitive => {
+                    let errors: BTreeSet<_> = errors.iter().collect();
+                    naive_errors.iter().all(|error| errors.contains(error))
+                }
+                _ => errors == naive_errors,
+            };
+            algorithms.push(AlgorithmSummary {
+                algorithm,
+                error_count: errors.len(),
+                agrees_with_naive,
+                duration,
+            });
+        }
+        AlgorithmReport { algorithms }
+    }
+
+    /// Estimates the cost of computing the output of the `facts`, without computing it.
+    pub fn estimate_cost(facts: &AllFacts<Region, Loan, Point, Variable>) -> CostEstimate {
+        let points: FxHashSet<Point> = facts
+            .cfg_edge
+            .iter()
+            .flat_map(|&(p, q)| vec![p, q])
+            .collect();
+        let regions: FxHashSet<Region> = facts
+            .outlives
+            .iter()
+            .flat_map(|&(r1, r2, _)| vec![r1, r2])
+            .chain(facts.borrow_region.iter().map(|&(r, _, _)| r))
+            .chain(facts.region_live_at.iter().map(|&(r, _)| r))
+            .chain(facts.universal_region.iter().cloned())
+            .collect();
+        let loans: FxHashSet<Loan> = facts.borrow_region.iter().map(|&(_, l, _)| l).collect();
+
+        CostEstimate {
+            points: points.len(),
+            regions: regions.len(),
+            loans: loans.len(),
+            outlives: facts.outlives.len(),
+            work: points
+                .len()
+                .saturating_mul(regions.len())
+                .saturating_mul(loans.len()),
+        }
+    }
+
+    /// Computes the kind of each error, from the facts the output was computed from, for
+    /// `errors_classified`:
+    /// - the invalidation is a drop if a variable dropping a region the loan may flow into, like
+    ///   the loan's own region, is drop-used at the error's point or at one of its successors:
+    ///   the loans are invalidated at the start point of the dropping statement, and the
+    ///   variable drop-used at its mid point.
+    /// - otherwise, the borrow escapes if the loan's region outlives, transitively and at any
+    ///   point, a universal region.
+    fn classify_errors(
+        &self,
+        all_facts: &AllFacts<Region, Loan, Point, Variable>,
+    ) -> FxHashMap<Point, Vec<(Loan, ErrorKind)>> {
+        let mut dropped_at: FxHashMap<Point, Vec<Variable>> = FxHashMap::default();
+        for &(v, p) in &all_facts.var_drop_used {
+            dropped_at.entry(p).or_default().push(v);
+        }
+        let mut drops_region: FxHashMap<Variable, Vec<Region>> = FxHashMap::default();
+        for &(v, r) in &all_facts.var_drops_region {
+            drops_region.entry(v).or_default().push(r);
+        }
+        let mut successors: FxHashMap<Point, Vec<Point>> = FxHashMap::default();
+        for &(p, q) in &all_facts.cfg_edge {
+            successors.entry(p).or_default().push(q);
+        }
+
+        let mut outlives: FxHashMap<Region, Vec<Region>> = FxHashMap::default();
+        for &(r1, r2, _) in &all_facts.outlives {
+            outlives.entry(r1).or_default().push(r2);
+        }
+        let universal_regions: FxHashSet<Region> =
+            all_facts.universal_region.iter().cloned().collect();
+
+        // The regions which the loan may flow into, at any point.
+        let reached_regions = |loan: Loan| {
+            let mut stack: Vec<Region> = all_facts
+                .borrow_region
+                .iter()
+                .filter(|&&(_, l, _)| l == loan)
+                .map(|&(r, _, _)| r)
+                .collect();
+            let mut reached: FxHashSet<Region> = stack.iter().cloned().collect();
+            while let Some(region) = stack.pop() {
+                for &successor in outlives.get(&region).into_iter().flatten() {
+                    if reached.insert(successor) {
+                        stack.push(successor);
+                    }
+                }
+            }
+            reached
+        };
+
+        let mut errors_classified = FxHashMap::default();
+        for (&point, loans) in &self.errors {
+            let dropped_regions: FxHashSet<Region> = Some(point)
+                .into_iter()
+                .chain(successors.get(&point).into_iter().flatten().cloned())
+                .flat_map(|p| dropped_at.get(&p).into_iter().flatten())
+                .flat_map(|v| drops_region.get(v).into_iter().flatten())
+                .cloned()
+                .collect();
+            let classified = loans
+                .iter()
+                .map(|&loan| {
+                    let reached = reached_regions(loan);
+                    let kind = if reached.iter().any(|r| dropped_regions.contains(r)) {
+                        ErrorKind::DropConflict
+                    } else if reached.iter().any(|r| universal_regions.contains(r)) {
+                        ErrorKind::EscapingBorrow
+                    } else {
+                        ErrorKind::ConflictingUse
+                    };
+                    (loan, kind)
+                })
+                .collect();
+            errors_classified.insert(point, classified);
         }
+        errors_classified
     }
 
-    fn new(dump_enabled: bool) -> Self {
+    /// Computes the output like `compute`, but skipping the liveness phase: the regions live at
+    /// each point are the `precomputed_region_live_at` ones (e.g. provided by rustc), instead of
+    /// the `region_live_at` facts or the liveness computed from the variables. The universal
+    /// regions are still live at all points.
+    ///
+    /// An empty `precomputed_region_live_at` is treated like missing `region_live_at` facts, and
+    /// the liveness is then computed as usual, with a warning: without the variable facts, the
+    /// output would otherwise look like a correct empty one.
+    pub fn compute_with_liveness(
+        all_facts: &AllFacts<Region, Loan, Point, Variable>,
+        algorithm: Algorithm,
+        precomputed_region_live_at: &FxHashMap<Point, Vec<Region>>,
+        dump_enabled: bool,
+    ) -> Self {
+        if precomputed_region_live_at.values().all(Vec::is_empty) {
+            warn!(
+                "the `precomputed_region_live_at` liveness is empty, computing it from the \
+                 variable facts instead"
+            );
+        }
+        let mut all_facts = all_facts.clone();
+        all_facts.region_live_at = precomputed_region_live_at
+            .iter()
+            .flat_map(|(&point, regions)| regions.iter().map(move |&region| (region, point)))
+            .collect();
+        Self::compute(&all_facts, algorithm, dump_enabled)
+    }
+
+    /// Computes the `var_live_at` relation of the `facts` one round of the backward dataflow at a
+    /// time, and returns a snapshot of it after each round, to show how the liveness reaches its
+    /// fixpoint: the first snapshot contains the uses of the variables, and the last one is the
+    /// `var_live_at` of a complete computation. This is separate from, and much slower than, the
+    /// liveness computed by `compute`.
+    pub fn liveness_iterations(
+        facts: &AllFacts<Region, Loan, Point, Variable>,
+    ) -> Vec<FxHashMap<Point, Vec<Variable>>> {
+        liveness::var_live_iterations(&facts.var_used, &facts.var_defined, &facts.cfg_edge)
+            .into_iter()
+            .map(|var_live| {
+                let mut var_live_at: FxHashMap<Point, Vec<Variable>> = FxHashMap::default();
+                for (var, point) in var_live {
+                    var_live_at.entry(point).or_default().push(var);
+                }
+                var_live_at
+            })
+            .collect()
+    }
+
+    /// Compares the liveness computed in two outputs, e.g. from two extractions of the facts of
+    /// a function, whose atoms must have been interned the same way. Both outputs must have been
+    /// computed with the dump enabled.
+    pub fn liveness_diff(a: &Self, b: &Self) -> LivenessDiff<Region, Point, Variable> {
+        assert!(a.dump_enabled && b.dump_enabled);
+        LivenessDiff {
+            var_live_at: diff_by_point(&a.var_live_at, &b.var_live_at),
+            region_live_at: diff_by_point(&a.region_live_at, &b.region_live_at),
+        }
+    }
+
+    /// Computes the `Hybrid` and `DatafrogOpt` outputs of the `facts`, with the dump enabled, and
+    /// compares their `errors`, `borrow_live_at` and `subset`. When `Hybrid` escalates to the
+    /// location-sensitive analysis, its output should be the same as `DatafrogOpt`'s, and when it
+    /// doesn't, both should have no errors: any difference is a bug.
+    pub fn hybrid_diff(
+        facts: &AllFacts<Region, Loan, Point, Variable>,
+    ) -> HybridDiff<Region, Loan, Point> {
+        let escalated = !Self::compute(facts, Algorithm::LocationInsensitive, false)
+            .errors
+            .is_empty();
+        let hybrid = Self::compute(facts, Algorithm::Hybrid, true);
+        let opt = Self::compute(facts, Algorithm::DatafrogOpt, true);
+
+        let mut diff = HybridDiff {
+            escalated,
+            errors: diff_by_point(&hybrid.errors, &opt.errors),
+            borrow_live_at: BTreeMap::new(),
+            subset: BTreeMap::new(),
+        };
+        if escalated {
+            let subset_pairs = |output: &Self| -> FxHashMap<Point, Vec<(Region, Region)>> {
+                output
+                    .subset
+                    .iter()
+                    .map(|(&point, subset)| {
+                        let pairs = subset
+                            .iter()
+                            .flat_map(|(&r1, r2s)| r2s.iter().map(move |&r2| (r1, r2)))
+                            .collect();
+                        (point, pairs)
+                    })
+                    .collect()
+            };
+            diff.borrow_live_at = diff_by_point(&hybrid.borrow_live_at, &opt.borrow_live_at);
+            diff.subset = diff_by_point(&subset_pairs(&hybrid), &subset_pairs(&opt));
+        }
+        diff
+    }
+
+    /// Returns an output where all the relations are empty, as if computed from empty facts
+    /// without dumping the intermediate relations.
+    pub fn empty() -> Self {
+        Output::new(&ComputeOptions::default())
+    }
+
+    fn new(options: &ComputeOptions) -> Self {
         Output {
             borrow_live_at: FxHashMap::default(),
             restricts: FxHashMap::default(),
@@ -158,12 +946,157 @@ where
             region_live_at: FxHashMap::default(),
             invalidates: FxHashMap::default(),
             errors: FxHashMap::default(),
+            closure_errors: FxHashMap::default(),
+            potentially_live_loans: BTreeSet::new(),
+            errors_classified: FxHashMap::default(),
             subset: FxHashMap::default(),
             subset_anywhere: FxHashMap::default(),
             var_live_at: FxHashMap::default(),
             var_drop_live_at: FxHashMap::default(),
-            dump_enabled,
+            peak_tuples: None,
+            dump_enabled: options.dump_enabled,
+            truncated: false,
+            drop_liveness_computed: false,
+            max_output_tuples: options.max_output_tuples,
+            universal_region: Vec::new(),
+        }
+    }
+
+    /// Whether materializing `tuples` tuples of the verbose relations would exceed the
+    /// `max_output_tuples` budget. If so, the verbose relations computed so far are dropped, and
+    /// the output is marked as truncated.
+    fn exceeds_output_budget(&mut self, tuples: usize) -> bool {
+        match self.max_output_tuples {
+            Some(max_output_tuples) if tuples > max_output_tuples => {
+                self.borrow_live_at.clear();
+                self.restricts.clear();
+                self.restricts_anywhere.clear();
+                self.region_live_at.clear();
+                self.invalidates.clear();
+                self.subset.clear();
+                self.subset_anywhere.clear();
+                self.var_live_at.clear();
+                self.var_drop_live_at.clear();
+                self.truncated = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    #[cfg(feature = "peak-tuples")]
+    fn record_tuples(&mut self, tuples: usize) {
+        let peak_tuples = self.peak_tuples.get_or_insert(0);
+        *peak_tuples = (*peak_tuples).max(tuples);
+    }
+
+    /// Returns the universal regions of the facts the output was computed from.
+    pub fn universal_regions(&self) -> &[Region] {
+        &self.universal_region
+    }
+
+    /// Returns all the errors, as `(point, loan)` pairs sorted by point and then by loan.
+    pub fn all_errors(&self) -> Vec<(Point, Loan)> {
+        let mut errors: Vec<(Point, Loan)> = self
+            .errors
+            .iter()
+            .flat_map(|(&point, loans)| loans.iter().map(move |&loan| (point, loan)))
+            .collect();
+        errors.sort();
+        errors
+    }
+
+    /// Returns the number of loans in error at each point having errors.
+    pub fn error_counts_per_point(&self) -> BTreeMap<Point, usize> {
+        self.errors
+            .iter()
+            .filter(|(_, loans)| !loans.is_empty())
+            .map(|(&point, loans)| (point, loans.len()))
+            .collect()
+    }
+
+    /// Returns the number of `subset` edges at each point having some: where the region
+    /// constraints accumulate during the fixpoint.
+    pub fn subset_stats(&self) -> BTreeMap<Point, usize> {
+        assert!(self.dump_enabled);
+        self.subset
+            .iter()
+            .map(|(&point, subset)| (point, subset.values().map(BTreeSet::len).sum()))
+            .filter(|&(_, edges)| edges > 0)
+            .collect()
+    }
+
+    /// Returns the number of distinct loans appearing in the `errors`, whatever their points.
+    pub fn erroring_loan_count(&self) -> usize {
+        let loans: FxHashSet<Loan> = self.errors.values().flatten().cloned().collect();
+        loans.len()
+    }
+
+    /// Returns all the errors like `all_errors`, along with the source line of their point in
+    /// `lines`, if any.
+    pub fn errors_with_lines(
+        &self,
+        lines: &FxHashMap<Point, u32>,
+    ) -> Vec<(Point, Loan, Option<u32>)> {
+        self.all_errors()
+            .into_iter()
+            .map(|(point, loan)| (point, loan, lines.get(&point).cloned()))
+            .collect()
+    }
+
+    /// Returns the errors as ranges `(loan, first point, last point)` of consecutive points: the
+    /// points in error for a loan are visited in CFG order (reverse postorder, the unreachable
+    /// points last), and a range goes on while each point is a successor of the previous one.
+    /// The ranges are sorted by loan, and then in CFG order.
+    pub fn error_ranges(
+        &self,
+        facts: &AllFacts<Region, Loan, Point, Variable>,
+    ) -> Vec<(Loan, Point, Point)> {
+        let mut points_by_loan: BTreeMap<Loan, BTreeSet<Point>> = BTreeMap::new();
+        for (point, loan) in self.all_errors() {
+            points_by_loan.entry(loan).or_default().insert(point);
+        }
+        let edges: FxHashSet<(Point, Point)> = facts.cfg_edge.iter().cloned().collect();
+        let error_points = points_by_loan.values().flatten().cloned().collect();
+        let order = cfg::in_cfg_order(&facts.cfg_edge, error_points);
+
+        let mut ranges = Vec::new();
+        for (loan, points) in points_by_loan {
+            let ordered_points: Vec<Point> = order
+                .iter()
+                .cloned()
+                .filter(|p| points.contains(p))
+                .collect();
+
+            let mut range: Option<(Point, Point)> = None;
+            for point in ordered_points {
+                range = match range {
+                    Some((first, last)) if edges.contains(&(last, point)) => Some((first, point)),
+                    Some((first, last)) => {
+                        ranges.push((loan, first, last));
+                        Some((point, point))
+                    }
+                    None => Some((point, point)),
+                };
+            }
+            if let Some((first, last)) = range {
+                ranges.push((loan, first, last));
+            }
+        }
+        ranges
+    }
+
+    /// Returns the points which may be affected by the errors at `point`: the points reachable
+    /// from it in the CFG, including itself. Returns an empty set if there is no error there.
+    pub fn error_fallout(
+        &self,
+        point: Point,
+        facts: &AllFacts<Region, Loan, Point, Variable>,
+    ) -> BTreeSet<Point> {
+        if self.errors_at(point).is_empty() {
+            return BTreeSet::new();
         }
+        cfg::reachable_from(&facts.cfg_edge, point)
     }
 
     pub fn errors_at(&self, location: Point) -> &[Loan] {
@@ -173,6 +1106,28 @@ where
         }
     }
 
+    /// Returns the loans which may be live at some point, when computed by the
+    /// `LocationInsensitive` algorithm. The set is empty with the other algorithms.
+    pub fn potentially_live_loans(&self) -> &BTreeSet<Loan> {
+        &self.potentially_live_loans
+    }
+
+    /// Returns the sorted points of the `errors` which, when computed by the
+    /// `LocationInsensitive` algorithm, are only potential errors: the location-sensitive
+    /// errors can only happen at these points, which are therefore the only ones a
+    /// location-sensitive algorithm needs to check. With the other algorithms, these are the
+    /// points of the actual errors.
+    pub fn insensitive_candidate_points(&self) -> Vec<Point> {
+        let mut points: Vec<Point> = self
+            .errors
+            .iter()
+            .filter(|(_, loans)| !loans.is_empty())
+            .map(|(&point, _)| point)
+            .collect();
+        points.sort();
+        points
+    }
+
     pub fn borrows_in_scope_at(&self, location: Point) -> &[Loan] {
         match self.borrow_live_at.get(&location) {
             Some(p) => p,
@@ -180,6 +1135,80 @@ where
         }
     }
 
+    /// Returns the points where the `loan` is live, but which have a successor in the CFG where
+    /// it isn't: the end of the loan's scope. The points are sorted.
+    pub fn loan_death_points(
+        &self,
+        loan: Loan,
+        facts: &AllFacts<Region, Loan, Point, Variable>,
+    ) -> Vec<Point> {
+        assert!(self.dump_enabled);
+        let is_live = |point: Point| self.borrows_in_scope_at(point).contains(&loan);
+        let death_points: BTreeSet<Point> = facts
+            .cfg_edge
+            .iter()
+            .filter(|&&(p, q)| is_live(p) && !is_live(q))
+            .map(|&(p, _)| p)
+            .collect();
+        death_points.into_iter().collect()
+    }
+
+    /// Returns the points where the `loan` is in `borrow_live_at`: the loan's scope, whose
+    /// boundary is outlined by `loan_death_points`.
+    pub fn loan_scope(&self, loan: Loan) -> BTreeSet<Point> {
+        assert!(self.dump_enabled);
+        self.borrow_live_at
+            .iter()
+            .filter(|(_, loans)| loans.contains(&loan))
+            .map(|(&point, _)| point)
+            .collect()
+    }
+
+    /// Returns the loans which are live at a point where the `facts` invalidate them, from the
+    /// `borrow_live_at` relation, as `(loan, point)` pairs sorted by loan and then by point.
+    /// This is how the location-sensitive algorithms compute the `errors`, which lets them be
+    /// explained, or checked against the liveness in a dump.
+    pub fn self_invalidating_loans(
+        &self,
+        facts: &AllFacts<Region, Loan, Point, Variable>,
+    ) -> Vec<(Loan, Point)> {
+        assert!(self.dump_enabled);
+        let mut loans: Vec<(Loan, Point)> = facts
+            .invalidates
+            .iter()
+            .filter(|&&(point, loan)| self.borrows_in_scope_at(point).contains(&loan))
+            .map(|&(point, loan)| (loan, point))
+            .collect();
+        loans.sort();
+        loans.dedup();
+        loans
+    }
+
+    /// Returns a point where both loans are live, if any: the smallest one, for determinism.
+    pub fn loans_conflict(&self, l1: Loan, l2: Loan) -> Option<Point> {
+        assert!(self.dump_enabled);
+        self.borrow_live_at
+            .iter()
+            .filter(|(_, loans)| loans.contains(&l1) && loans.contains(&l2))
+            .map(|(&point, _)| point)
+            .min()
+    }
+
+    /// Returns the loans introduced by the `borrow_region` facts which are never live: they are in
+    /// `borrow_live_at` at no point, e.g. as their region is dead as soon as they are created.
+    /// They can't cause errors, and usually come from extraction artifacts or dead code.
+    pub fn never_live_loans(&self, facts: &AllFacts<Region, Loan, Point, Variable>) -> Vec<Loan> {
+        assert!(self.dump_enabled);
+        let live_loans: FxHashSet<Loan> = self.borrow_live_at.values().flatten().cloned().collect();
+        let loans: BTreeSet<Loan> = facts
+            .borrow_region
+            .iter()
+            .map(|&(_, loan, _)| loan)
+            .filter(|loan| !live_loans.contains(loan))
+            .collect();
+        loans.into_iter().collect()
+    }
+
     pub fn restricts_at(&self, location: Point) -> Cow<'_, BTreeMap<Region, BTreeSet<Loan>>> {
         assert!(self.dump_enabled);
         match self.restricts.get(&location) {
@@ -196,6 +1225,313 @@ where
         }
     }
 
+    /// Returns the longest chain of regions `'a: 'b: ... : 'u` of the `subset` relation at a
+    /// point, ending at a universal region `'u` of the `facts`, along with this point. The
+    /// regions of a cycle, which are equal, are all part of the chain. The earliest point wins
+    /// ties. Returns `None` if no region outlives a universal region.
+    pub fn longest_outlives_chain(
+        &self,
+        facts: &AllFacts<Region, Loan, Point, Variable>,
+    ) -> Option<(Vec<Region>, Point)> {
+        assert!(self.dump_enabled);
+        let universal_regions: BTreeSet<Region> = facts.universal_region.iter().cloned().collect();
+        let mut points: Vec<Point> = self.subset.keys().cloned().collect();
+        points.sort();
+
+        let mut longest: Option<(Vec<Region>, Point)> = None;
+        for point in points {
+            let edges: Vec<(Region, Region)> = self.subset[&point]
+                .iter()
+                .flat_map(|(&r1, regions)| regions.iter().map(move |&r2| (r1, r2)))
+                .filter(|&(r1, r2)| r1 != r2)
+                .collect();
+            if let Some(chain) = longest_path_to(&edges, &universal_regions) {
+                let is_longer = match longest {
+                    Some((ref longest_chain, _)) => chain.len() > longest_chain.len(),
+                    None => true,
+                };
+                if is_longer {
+                    longest = Some((chain, point));
+                }
+            }
+        }
+        longest
+    }
+
+    /// Returns the first point where each region is live, in CFG order (reverse postorder from
+    /// the entry points), from the `region_live_at` relation. Points which are not reachable in
+    /// the CFG come last. Regions which are never live are absent from the map.
+    pub fn region_first_live(
+        &self,
+        facts: &AllFacts<Region, Loan, Point, Variable>,
+    ) -> FxHashMap<Region, Point> {
+        assert!(self.dump_enabled);
+        let points = self.region_live_at.keys().cloned().collect();
+
+        let mut first_live = FxHashMap::default();
+        for point in cfg::in_cfg_order(&facts.cfg_edge, points) {
+            for &region in self.regions_live_at(point) {
+                first_live.entry(region).or_insert(point);
+            }
+        }
+        first_live
+    }
+
+    /// Returns the points where some regions are live, but no loan is: where the liveness of
+    /// regions doesn't matter to the borrows. The points are sorted.
+    pub fn region_live_no_loans(&self) -> Vec<Point> {
+        assert!(self.dump_enabled);
+        let mut points: Vec<Point> = self
+            .region_live_at
+            .iter()
+            .filter(|(_, regions)| !regions.is_empty())
+            .map(|(&point, _)| point)
+            .filter(|&point| self.borrows_in_scope_at(point).is_empty())
+            .collect();
+        points.sort();
+        points
+    }
+
+    /// Returns, for each point of the CFG in CFG order (reverse postorder from the entry points,
+    /// the unreachable points coming last), the number of regions live there, of loans live
+    /// there, and of errors there: how the pressure on the borrows evolves through the function.
+    pub fn timeline(
+        &self,
+        facts: &AllFacts<Region, Loan, Point, Variable>,
+    ) -> Vec<(Point, usize, usize, usize)> {
+        assert!(self.dump_enabled);
+        let points = facts
+            .cfg_edge
+            .iter()
+            .flat_map(|&(p, q)| vec![p, q])
+            .collect();
+
+        cfg::in_cfg_order(&facts.cfg_edge, points)
+            .into_iter()
+            .map(|point| {
+                (
+                    point,
+                    self.regions_live_at(point).len(),
+                    self.borrows_in_scope_at(point).len(),
+                    self.errors_at(point).len(),
+                )
+            })
+            .collect()
+    }
+
+    /// Returns the `var_live_at` relation restricted to the given `points`, e.g. the points of
+    /// a single block, rather than the whole function.
+    pub fn var_live_at_filtered(
+        &self,
+        points: &BTreeSet<Point>,
+    ) -> FxHashMap<Point, Vec<Variable>> {
+        assert!(self.dump_enabled);
+        self.var_live_at
+            .iter()
+            .filter(|(point, _)| points.contains(point))
+            .map(|(&point, variables)| (point, variables.clone()))
+            .collect()
+    }
+
+    /// Returns the variables which are drop-live at some point where they aren't use-live: the
+    /// variables whose liveness depends on the semantics of their drop there, e.g. whether it
+    /// may dangle.
+    pub fn drop_critical_vars(&self) -> BTreeSet<Variable> {
+        assert!(self.dump_enabled);
+        self.var_drop_live_at
+            .iter()
+            .flat_map(|(point, variables)| {
+                let use_live = self.var_live_at.get(point);
+                variables
+                    .iter()
+                    .filter(move |variable| !use_live.is_some_and(|live| live.contains(variable)))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Checks that the live loans flow along the CFG edges of the `facts` as the rules require:
+    /// a loan live at a point, and not killed there, must still be live at each successor where
+    /// the regions live at the point all are, as they still contain the loan. Otherwise, returns
+    /// the loans dying along an edge `(loan, point, successor)` without a kill, which would be a
+    /// bug of the propagation. The kills include those of the reborrows, as in `compute`.
+    ///
+    /// Only `borrow_live_at` and `region_live_at` are checked, so the check applies to the
+    /// output of each algorithm. It is vacuous for `LocationInsensitive`, which has no
+    /// `borrow_live_at`.
+    pub fn check_live_monotonicity(
+        &self,
+        facts: &AllFacts<Region, Loan, Point, Variable>,
+    ) -> Result<(), Vec<(Loan, Point, Point)>> {
+        assert!(self.dump_enabled);
+        let killed: FxHashSet<(Loan, Point)> =
+            killed_with_reborrows(&facts.killed, &facts.loan_reborrows)
+                .into_iter()
+                .collect();
+        let live_regions = |point| -> BTreeSet<Region> {
+            self.region_live_at
+                .get(&point)
+                .into_iter()
+                .flatten()
+                .cloned()
+                .collect()
+        };
+
+        let mut deaths = BTreeSet::new();
+        for &(p, q) in &facts.cfg_edge {
+            let loans = match self.borrow_live_at.get(&p) {
+                Some(loans) => loans,
+                None => continue,
+            };
+            if !live_regions(p).is_subset(&live_regions(q)) {
+                continue;
+            }
+            let successor_loans = self.borrow_live_at.get(&q);
+            for &loan in loans {
+                let flows = successor_loans.is_some_and(|loans| loans.contains(&loan));
+                if !flows && !killed.contains(&(loan, p)) {
+                    deaths.insert((loan, p, q));
+                }
+            }
+        }
+
+        if deaths.is_empty() {
+            Ok(())
+        } else {
+            Err(deaths.into_iter().collect())
+        }
+    }
+
+    /// Returns the universal regions which are constrained by the computed `subset`, each with
+    /// the other regions which must outlive it: the regions which are a subset of it at some
+    /// point. The universal regions without such a region are omitted.
+    pub fn constrained_universal_regions(&self) -> Vec<(Region, BTreeSet<Region>)> {
+        assert!(self.dump_enabled);
+        self.universal_region
+            .iter()
+            .filter_map(|&universal_region| {
+                let regions: BTreeSet<Region> = self
+                    .subset
+                    .values()
+                    .flat_map(|subset| subset.iter())
+                    .filter(|&(&r1, r2s)| r1 != universal_region && r2s.contains(&universal_region))
+                    .map(|(&r1, _)| r1)
+                    .collect();
+                if regions.is_empty() {
+                    None
+                } else {
+                    Some((universal_region, regions))
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the universal regions reached by each loan: the universal regions which contain
+    /// the loan, or which are a superset of a region containing the loan, at some point. A loan
+    /// reaching a universal region escapes the function.
+    pub fn loan_universal_reach(&self) -> FxHashMap<Loan, BTreeSet<Region>> {
+        assert!(self.dump_enabled);
+        let mut reach: FxHashMap<Loan, BTreeSet<Region>> = FxHashMap::default();
+        for (point, restricts) in &self.restricts {
+            let subset = self.subset.get(point);
+            for (region, loans) in restricts {
+                let supersets = subset.and_then(|subset| subset.get(region));
+                let reached: Vec<Region> = self
+                    .universal_region
+                    .iter()
+                    .filter(|universal_region| {
+                        *universal_region == region
+                            || supersets.into_iter().any(|s| s.contains(universal_region))
+                    })
+                    .cloned()
+                    .collect();
+                if reached.is_empty() {
+                    continue;
+                }
+
+                for &loan in loans {
+                    reach.entry(loan).or_default().extend(&reached);
+                }
+            }
+        }
+        reach
+    }
+
+    /// Partitions the regions appearing in `subset` into classes of equivalent regions: regions
+    /// appearing at the same points, and having the same supersets, themselves included, at
+    /// each of these points. The regions of a class outlive each other wherever they appear,
+    /// and could therefore be merged. The classes are sorted by their smallest region.
+    pub fn region_equivalence_classes(&self) -> Vec<BTreeSet<Region>> {
+        assert!(self.dump_enabled);
+        let mut signatures: BTreeMap<Region, BTreeMap<Point, BTreeSet<Region>>> = BTreeMap::new();
+        for (&point, subset) in &self.subset {
+            let regions: BTreeSet<Region> = subset
+                .iter()
+                .flat_map(|(&r1, supersets)| supersets.iter().cloned().chain(Some(r1)))
+                .collect();
+            for region in regions {
+                let mut supersets = subset.get(&region).cloned().unwrap_or_default();
+                supersets.insert(region);
+                signatures
+                    .entry(region)
+                    .or_default()
+                    .insert(point, supersets);
+            }
+        }
+
+        let mut classes: BTreeMap<BTreeMap<Point, BTreeSet<Region>>, BTreeSet<Region>> =
+            BTreeMap::new();
+        for (region, signature) in signatures {
+            classes.entry(signature).or_default().insert(region);
+        }
+
+        let mut classes: Vec<BTreeSet<Region>> = classes.into_values().collect();
+        classes.sort();
+        classes
+    }
+
+    /// Returns the regions appearing in the `outlives` or `borrow_region` facts but which are
+    /// never live, at any point: candidates for removal before the analysis.
+    pub fn dead_regions(&self, facts: &AllFacts<Region, Loan, Point, Variable>) -> Vec<Region> {
+        assert!(self.dump_enabled);
+        let live_regions: BTreeSet<Region> =
+            self.region_live_at.values().flatten().cloned().collect();
+
+        let regions: BTreeSet<Region> = facts
+            .outlives
+            .iter()
+            .flat_map(|&(r1, r2, _)| vec![r1, r2])
+            .chain(facts.borrow_region.iter().map(|&(r, _, _)| r))
+            .collect();
+        regions.difference(&live_regions).cloned().collect()
+    }
+
+    /// Returns the first `outlives` fact whose removal from the `facts` makes the error of the
+    /// `loan` at the `point` disappear, the facts without it being recomputed with the `Naive`
+    /// algorithm. Returns `None` if there is no such error, or if no single `outlives` fact is
+    /// responsible for it.
+    pub fn critical_outlives(
+        &self,
+        point: Point,
+        loan: Loan,
+        facts: &AllFacts<Region, Loan, Point, Variable>,
+    ) -> Option<(Region, Region, Point)> {
+        if !self.errors_at(point).contains(&loan) {
+            return None;
+        }
+
+        (0..facts.outlives.len()).find_map(|index| {
+            let mut facts = facts.clone();
+            let outlives = facts.outlives.remove(index);
+            let output = Output::compute(&facts, Algorithm::Naive, false);
+            if output.errors_at(point).contains(&loan) {
+                None
+            } else {
+                Some(outlives)
+            }
+        })
+    }
+
     pub fn subsets_at(&self, location: Point) -> Cow<'_, BTreeMap<Region, BTreeSet<Region>>> {
         assert!(self.dump_enabled);
         match self.subset.get(&location) {
@@ -225,6 +1561,79 @@ mod tests {
         diff1
     }
 
+    #[test]
+    fn options_can_be_sent_to_other_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ComputeOptions>();
+    }
+
+    #[cfg(feature = "binary-cache")]
+    #[test]
+    fn test_binary_round_trip() {
+        let facts: AllFacts<usize, usize, usize, usize> = AllFacts {
+            cfg_edge: vec![(0, 1), (1, 2)],
+            universal_region: vec![3],
+            borrow_region: vec![(0, 0, 0)],
+            outlives: vec![(0, 1, 0), (1, 2, 1)],
+            region_live_at: vec![(0, 0), (1, 1), (2, 2)],
+            invalidates: vec![(2, 0)],
+            ..Default::default()
+        };
+
+        for &algorithm in &[Algorithm::Naive, Algorithm::LocationInsensitive] {
+            let output = Output::compute(&facts, algorithm, true);
+            assert_eq!(output.errors.len(), 1);
+            let bytes = output.to_bincode();
+            assert_eq!(Output::from_bincode(&bytes), Ok(output));
+
+            // Truncated, or extended, encodings are rejected.
+            assert!(
+                Output::<usize, usize, usize, usize>::from_bincode(&bytes[..bytes.len() - 1])
+                    .is_err()
+            );
+            let mut extended = bytes.clone();
+            extended.push(0);
+            assert!(Output::<usize, usize, usize, usize>::from_bincode(&extended).is_err());
+        }
+    }
+
+    #[test]
+    fn test_longest_path_to_through_cycle() {
+        let targets: BTreeSet<usize> = vec![3].into_iter().collect();
+        // 1 and 2 are a cycle, and are both on the path, while 4 doesn't reach the target.
+        let edges = [(0, 1), (1, 2), (2, 1), (2, 3), (0, 3), (3, 4)];
+        assert_eq!(longest_path_to(&edges, &targets), Some(vec![0, 1, 2, 3]));
+        assert_eq!(longest_path_to(&[(3, 4)], &targets), None);
+    }
+
+    #[cfg(feature = "petgraph")]
+    #[test]
+    fn test_cfg_graph() {
+        let facts: AllFacts<usize, usize, usize, usize> = AllFacts {
+            cfg_edge: vec![(0, 1), (1, 2), (2, 1), (1, 2), (2, 3)],
+            ..Default::default()
+        };
+        let (graph, nodes) = facts.cfg_graph();
+        assert_eq!(graph.node_count(), 4);
+        assert_eq!(graph.edge_count(), 4);
+        for point in 0..4 {
+            assert_eq!(graph[nodes[&point]], point);
+        }
+
+        // The loop between 1 and 2 is the only non-trivial SCC.
+        let mut sccs: Vec<Vec<usize>> = ::petgraph::algo::kosaraju_scc(&graph)
+            .into_iter()
+            .map(|scc| {
+                let mut points: Vec<usize> = scc.into_iter().map(|node| graph[node]).collect();
+                points.sort();
+                points
+            })
+            .filter(|points| points.len() > 1)
+            .collect();
+        sccs.sort();
+        assert_eq!(sccs, vec![vec![1, 2]]);
+    }
+
     #[test]
     fn test_compare_errors() {
         let empty = FxHashMap::default();
@@ -260,4 +1669,41 @@ mod tests {
         assert_eq!(true, compare(&errors1, &singleton2));
         assert_eq!(true, compare(&errors1, &singleton3));
     }
+
+    #[test]
+    fn test_empty_output() {
+        let output: Output<usize, usize, usize, usize> = Output::empty();
+        assert!(!output.dump_enabled);
+        assert!(output.errors.is_empty());
+        assert!(output.errors_at(0).is_empty());
+        assert!(output.borrows_in_scope_at(0).is_empty());
+    }
+
+    #[test]
+    fn test_all_errors() {
+        let mut output: Output<usize, usize, usize, usize> = Output::empty();
+        output.errors.insert(2, vec![1, 0]);
+        output.errors.insert(1, vec![3]);
+        assert_eq!(output.all_errors(), [(1, 3), (2, 0), (2, 1)]);
+    }
+
+    #[test]
+    fn test_error_counts_per_point() {
+        let mut output: Output<usize, usize, usize, usize> = Output::empty();
+        output.errors.insert(2, vec![1, 0]);
+        output.errors.insert(1, vec![3]);
+        output.errors.insert(3, vec![]);
+        let counts: Vec<_> = output.error_counts_per_point().into_iter().collect();
+        assert_eq!(counts, [(1, 1), (2, 2)]);
+    }
+
+    #[test]
+    fn test_erroring_loan_count() {
+        let mut output: Output<usize, usize, usize, usize> = Output::empty();
+        assert_eq!(output.erroring_loan_count(), 0);
+        output.errors.insert(2, vec![1, 0]);
+        output.errors.insert(1, vec![1]);
+        output.errors.insert(3, vec![]);
+        assert_eq!(output.erroring_loan_count(), 2);
+    }
 }
@@ -14,16 +14,22 @@ use std::collections::{BTreeMap, BTreeSet};
 use std::time::Instant;
 
 use crate::output::liveness;
-use crate::output::Output;
+use crate::output::trace::Tracer;
+#[cfg(feature = "peak-tuples")]
+use crate::output::variable_len;
+use crate::output::{ComputeOptions, Output};
 use facts::{AllFacts, Atom};
 
 use datafrog::{Iteration, Relation, RelationLeaper};
 
 pub(super) fn compute<Region: Atom, Loan: Atom, Point: Atom, Variable: Atom>(
-    dump_enabled: bool,
+    options: ComputeOptions,
     all_facts: AllFacts<Region, Loan, Point, Variable>,
+    on_error: &mut dyn FnMut(Point, Loan),
 ) -> Output<Region, Loan, Point, Variable> {
-    let mut result = Output::new(dump_enabled);
+    let dump_enabled = options.dump_enabled;
+    let mut result = Output::new(&options);
+    let mut tracer = Tracer::new(options.trace, "naive");
 
     let region_live_at = liveness::init_region_live_at(
         all_facts.var_used,
@@ -35,12 +41,13 @@ pub(super) fn compute<Region: Atom, Loan: Atom, Point: Atom, Variable: Atom>(
         &all_facts.cfg_edge,
         all_facts.region_live_at,
         all_facts.universal_region,
+        options.universal_liveness,
         &mut result,
     );
 
     let computation_start = Instant::now();
 
-    let errors = {
+    let (errors, closure_errors) = {
         // Create a new iteration context, ...
         let mut iteration = Iteration::new();
 
@@ -57,6 +64,9 @@ pub(super) fn compute<Region: Atom, Loan: Atom, Point: Atom, Variable: Atom>(
         // `invalidates` facts, stored ready for joins
         let invalidates = iteration.variable::<((Loan, Point), ())>("invalidates");
 
+        // `closure_requires` facts, stored ready for joins
+        let closure_requires = iteration.variable::<((Region, Point), ())>("closure_requires");
+
         // different indices for `subset`.
         let subset_r1p = iteration.variable_indistinct("subset_r1p");
         let subset_r2p = iteration.variable_indistinct("subset_r2p");
@@ -70,6 +80,7 @@ pub(super) fn compute<Region: Atom, Loan: Atom, Point: Atom, Variable: Atom>(
 
         // output
         let errors = iteration.variable("errors");
+        let closure_errors = iteration.variable("closure_errors");
 
         //let compute_region_live_at = all_facts.region_live_at.is_empty();
 
@@ -78,6 +89,12 @@ pub(super) fn compute<Region: Atom, Loan: Atom, Point: Atom, Variable: Atom>(
         requires.insert(all_facts.borrow_region.into());
         invalidates.extend(all_facts.invalidates.iter().map(|&(p, b)| ((b, p), ())));
         region_live_at_var.extend(region_live_at_rel.iter().map(|&(r, p)| ((r, p), ())));
+        closure_requires.extend(
+            all_facts
+                .closure_requires
+                .iter()
+                .map(|&(r, p)| ((r, p), ())),
+        );
 
         // .. and then start iterating rules!
         while iteration.changed() {
@@ -95,6 +112,23 @@ pub(super) fn compute<Region: Atom, Loan: Atom, Point: Atom, Variable: Atom>(
                 .elements
                 .retain(|&(r1, r2, _)| r1 != r2);
 
+            tracer.next_round();
+            tracer.derived("subset", &subset, |&(r1, r2, p)| {
+                vec![r1.index(), r2.index(), p.index()]
+            });
+            tracer.derived("requires", &requires, |&(r, b, p)| {
+                vec![r.index(), b.index(), p.index()]
+            });
+            tracer.derived("borrow_live_at", &borrow_live_at, |&((b, p), ())| {
+                vec![b.index(), p.index()]
+            });
+            tracer.derived("errors", &errors, |&(b, p): &(Loan, Point)| {
+                vec![b.index(), p.index()]
+            });
+            for &(b, p) in errors.recent.borrow().iter() {
+                on_error(p, b);
+            }
+
             // remap fields to re-index by keys.
             subset_r1p.from_map(&subset, |&(r1, r2, p)| ((r1, p), r2));
             subset_r2p.from_map(&subset, |&(r1, r2, p)| ((r2, p), r1));
@@ -156,54 +190,69 @@ pub(super) fn compute<Region: Atom, Loan: Atom, Point: Atom, Variable: Atom>(
 
             // .decl errors(B, P) :- invalidates(B, P), borrow_live_at(B, P).
             errors.from_join(&invalidates, &borrow_live_at, |&(b, p), &(), &()| (b, p));
+
+            // closure_errors(R, P) :- closure_requires(R, P), requires(R, B, P).
+            closure_errors.from_join(&closure_requires, &requires_rp, |&(r, p), &(), &_b| (r, p));
+
+            #[cfg(feature = "peak-tuples")]
+            result.record_tuples(
+                variable_len(&subset) + variable_len(&requires) + variable_len(&borrow_live_at),
+            );
         }
 
         if dump_enabled {
             let subset = subset.complete();
-            assert!(
-                subset.iter().filter(|&(r1, r2, _)| r1 == r2).count() == 0,
-                "unwanted subset symmetries"
-            );
-            for (r1, r2, location) in &subset.elements {
-                result
-                    .subset
-                    .entry(*location)
-                    .or_insert_with(BTreeMap::new)
-                    .entry(*r1)
-                    .or_insert_with(BTreeSet::new)
-                    .insert(*r2);
-            }
-
             let requires = requires.complete();
-            for (region, borrow, location) in &requires.elements {
-                result
-                    .restricts
-                    .entry(*location)
-                    .or_insert_with(BTreeMap::new)
-                    .entry(*region)
-                    .or_insert_with(BTreeSet::new)
-                    .insert(*borrow);
-            }
-
-            for (region, location) in &region_live_at_rel.elements {
-                result
-                    .region_live_at
-                    .entry(*location)
-                    .or_insert_with(Vec::new)
-                    .push(*region);
-            }
-
-            let borrow_live_at = borrow_live_at.complete();
-            for &((loan, location), ()) in &borrow_live_at.elements {
-                result
-                    .borrow_live_at
-                    .entry(location)
-                    .or_insert_with(Vec::new)
-                    .push(loan);
+            if !result.exceeds_output_budget(subset.len() + requires.len()) {
+                assert!(
+                    subset.iter().filter(|&(r1, r2, _)| r1 == r2).count() == 0,
+                    "unwanted subset symmetries"
+                );
+                for (r1, r2, location) in &subset.elements {
+                    result
+                        .subset
+                        .entry(*location)
+                        .or_insert_with(BTreeMap::new)
+                        .entry(*r1)
+                        .or_insert_with(BTreeSet::new)
+                        .insert(*r2);
+                    result
+                        .subset_anywhere
+                        .entry(*r1)
+                        .or_insert_with(BTreeSet::new)
+                        .insert(*r2);
+                }
+
+                for (region, borrow, location) in &requires.elements {
+                    result
+                        .restricts
+                        .entry(*location)
+                        .or_insert_with(BTreeMap::new)
+                        .entry(*region)
+                        .or_insert_with(BTreeSet::new)
+                        .insert(*borrow);
+                }
+
+                for (region, location) in &region_live_at_rel.elements {
+                    result
+                        .region_live_at
+                        .entry(*location)
+                        .or_insert_with(Vec::new)
+                        .push(*region);
+                }
+
+                let borrow_live_at = borrow_live_at.complete();
+                for &((loan, location), ()) in &borrow_live_at.elements {
+                    result
+                        .borrow_live_at
+                        .entry(location)
+                        .or_insert_with(Vec::new)
+                        .push(loan);
+                }
             }
         }
 
-        errors.complete()
+        (errors.complete(), closure_errors.complete())
     };
 
     if dump_enabled {
@@ -222,5 +271,13 @@ pub(super) fn compute<Region: Atom, Loan: Atom, Point: Atom, Variable: Atom>(
             .push(*borrow);
     }
 
+    for (region, location) in &closure_errors.elements {
+        result
+            .closure_errors
+            .entry(*location)
+            .or_insert_with(Vec::new)
+            .push(*region);
+    }
+
     result
 }
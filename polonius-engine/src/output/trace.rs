@@ -0,0 +1,147 @@
+// Copyright 2019 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Tracing the tuples derived at each round of the fixpoint computations.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+/// How the derived tuples are written to the sink of a `Trace`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// One human-readable line per tuple, like `naive round 2: subset(1, 2, 5)`.
+    Text,
+    /// One JSON object per line and per tuple, like
+    /// `{"algorithm":"naive","round":2,"relation":"subset","tuple":[1,2,5]}`.
+    Ndjson,
+}
+
+impl TraceFormat {
+    pub fn variants() -> [&'static str; 2] {
+        ["Text", "Ndjson"]
+    }
+}
+
+impl ::std::str::FromStr for TraceFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_ref() {
+            "text" => Ok(TraceFormat::Text),
+            "ndjson" => Ok(TraceFormat::Ndjson),
+            _ => Err(String::from("valid values: Text, Ndjson")),
+        }
+    }
+}
+
+/// Where, and in which format, the `Naive` and `DatafrogOpt` computations write the tuples of
+/// `subset`, `requires`, `borrow_live_at` and `errors` derived at each round. The tuples are
+/// written in the `Naive` layout, as the indices of their atoms, so that the traces of both
+/// algorithms can be compared. The sink is behind a mutex, so that the `ComputeOptions` holding
+/// a trace can still be sent to other threads.
+#[derive(Clone)]
+pub struct Trace {
+    sink: Arc<Mutex<dyn Write + Send>>,
+    format: TraceFormat,
+}
+
+impl Trace {
+    pub fn new(sink: Arc<Mutex<dyn Write + Send>>, format: TraceFormat) -> Self {
+        Trace { sink, format }
+    }
+}
+
+impl fmt::Debug for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Trace")
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+/// Writes the tuples of a single computation to its optional `Trace`.
+pub(super) struct Tracer {
+    trace: Option<Trace>,
+    algorithm: &'static str,
+    round: usize,
+}
+
+impl Tracer {
+    pub(super) fn new(trace: Option<Trace>, algorithm: &'static str) -> Self {
+        Tracer {
+            trace,
+            algorithm,
+            round: 0,
+        }
+    }
+
+    pub(super) fn next_round(&mut self) {
+        self.round += 1;
+    }
+
+    /// Writes the tuples added to the `variable` at the current round, each one converted to
+    /// the indices of its atoms by `indices`.
+    pub(super) fn derived<Tuple: Ord>(
+        &mut self,
+        relation: &str,
+        variable: &datafrog::Variable<Tuple>,
+        indices: impl Fn(&Tuple) -> Vec<usize>,
+    ) {
+        let result = match self.trace {
+            Some(ref trace) => {
+                let recent = variable.recent.borrow();
+                let tuples = recent.iter().map(indices);
+                self.write(trace, relation, tuples)
+            }
+            None => return,
+        };
+
+        if let Err(e) = result {
+            warn!("failed to write the trace, disabling it: {}", e);
+            self.trace = None;
+        }
+    }
+
+    fn write(
+        &self,
+        trace: &Trace,
+        relation: &str,
+        tuples: impl Iterator<Item = Vec<usize>>,
+    ) -> io::Result<()> {
+        // A poisoned sink is still written to: its tuples are only missing those of the
+        // computation which panicked.
+        let mut sink = trace
+            .sink
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for tuple in tuples {
+            let tuple: Vec<String> = tuple.iter().map(|index| index.to_string()).collect();
+            match trace.format {
+                TraceFormat::Text => writeln!(
+                    sink,
+                    "{} round {}: {}({})",
+                    self.algorithm,
+                    self.round,
+                    relation,
+                    tuple.join(", ")
+                )?,
+                TraceFormat::Ndjson => writeln!(
+                    sink,
+                    "{{\"algorithm\":\"{}\",\"round\":{},\"relation\":\"{}\",\"tuple\":[{}]}}",
+                    self.algorithm,
+                    self.round,
+                    relation,
+                    tuple.join(",")
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright 2019 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Strongly-connected components of a graph, computed with Tarjan's algorithm.
+
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::collections::BTreeMap;
+
+use facts::Atom;
+
+struct Tarjan<'a, Node: Atom> {
+    successors: &'a BTreeMap<Node, Vec<Node>>,
+    index: FxHashMap<Node, usize>,
+    lowlink: FxHashMap<Node, usize>,
+    stack: Vec<Node>,
+    on_stack: FxHashSet<Node>,
+    components: Vec<Vec<Node>>,
+}
+
+impl<'a, Node: Atom> Tarjan<'a, Node> {
+    fn visit(&mut self, node: Node) {
+        let index = self.index.len();
+        self.index.insert(node, index);
+        self.lowlink.insert(node, index);
+        self.stack.push(node);
+        self.on_stack.insert(node);
+
+        let successors = self.successors;
+        for &successor in successors.get(&node).into_iter().flatten() {
+            if !self.index.contains_key(&successor) {
+                self.visit(successor);
+                let lowlink = self.lowlink[&node].min(self.lowlink[&successor]);
+                self.lowlink.insert(node, lowlink);
+            } else if self.on_stack.contains(&successor) {
+                let lowlink = self.lowlink[&node].min(self.index[&successor]);
+                self.lowlink.insert(node, lowlink);
+            }
+        }
+
+        // `node` is the root of a component: the nodes above it on the stack.
+        if self.lowlink[&node] == index {
+            let mut component = Vec::new();
+            loop {
+                let member = self.stack.pop().unwrap();
+                self.on_stack.remove(&member);
+                component.push(member);
+                if member == node {
+                    break;
+                }
+            }
+            self.components.push(component);
+        }
+    }
+}
+
+/// Returns the strongly-connected components of the graph described by `edges`. Each component
+/// is sorted, and the components are sorted by their smallest node.
+pub(crate) fn strongly_connected_components<Node: Atom>(edges: &[(Node, Node)]) -> Vec<Vec<Node>> {
+    let mut successors: BTreeMap<Node, Vec<Node>> = BTreeMap::new();
+    for &(a, b) in edges {
+        successors.entry(a).or_default().push(b);
+        successors.entry(b).or_default();
+    }
+
+    let mut tarjan = Tarjan {
+        successors: &successors,
+        index: FxHashMap::default(),
+        lowlink: FxHashMap::default(),
+        stack: Vec::new(),
+        on_stack: FxHashSet::default(),
+        components: Vec::new(),
+    };
+    for &node in successors.keys() {
+        if !tarjan.index.contains_key(&node) {
+            tarjan.visit(node);
+        }
+    }
+
+    let mut components = tarjan.components;
+    for component in &mut components {
+        component.sort();
+    }
+    components.sort();
+    components
+}
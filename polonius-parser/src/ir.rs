@@ -1,9 +1,13 @@
-#[derive(Debug)]
+use std::collections::BTreeSet;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
 pub struct Input {
     pub universal_regions: Vec<String>,
     pub blocks: Vec<Block>,
     pub var_uses_region: Vec<(String, String)>,
     pub var_drops_region: Vec<(String, String)>,
+    pub assertions: Vec<LivenessAssertion>,
 }
 
 impl Input {
@@ -12,24 +16,26 @@ impl Input {
         var_uses_region: Option<Vec<(String, String)>>,
         var_drops_region: Option<Vec<(String, String)>>,
         blocks: Vec<Block>,
+        assertions: Vec<LivenessAssertion>,
     ) -> Input {
         Input {
             universal_regions,
             var_uses_region: var_uses_region.unwrap_or(Vec::default()),
             var_drops_region: var_drops_region.unwrap_or(Vec::default()),
             blocks,
+            assertions,
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Block {
     pub name: String,
     pub statements: Vec<Statement>,
     pub goto: Vec<String>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Statement {
     /// Effects destined to be emitted at the Statement's Start point
     pub effects_start: Vec<Effect>,
@@ -38,6 +44,22 @@ pub struct Statement {
     pub effects: Vec<Effect>,
 }
 
+/// An assertion that a region or a variable is live at a point, like
+/// `assert_live('a @ Mid(B0[0]))`, to check the liveness computed for the program.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LivenessAssertion {
+    pub live: Liveness,
+
+    /// The point's name, like `Mid(B0[0])`.
+    pub point: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Liveness {
+    Region(String),
+    Variable(String),
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Effect {
     Use { regions: Vec<String> },
@@ -48,6 +70,11 @@ pub enum Effect {
 pub enum Fact {
     Outlives { a: String, b: String },
     BorrowRegionAt { region: String, loan: String },
+    BorrowReservedAt { loan: String },
+    BorrowActivatedAt { loan: String },
+    ClosureRequires { region: String },
+    LoanReborrows { loan: String, parent: String },
+    LoanBorrowsVar { loan: String, variable: String },
     Invalidates { loan: String },
     Kill { loan: String },
     RegionLiveAt { region: String },
@@ -80,4 +107,147 @@ impl Statement {
             effects,
         }
     }
+
+    /// Returns the variables which are both used and defined by the effects of this statement,
+    /// at its start or mid points: their liveness depends on which point each of these effects
+    /// is placed at, and is easily surprising.
+    pub fn variables_used_and_defined(&self) -> BTreeSet<&str> {
+        let mut used = BTreeSet::new();
+        let mut defined = BTreeSet::new();
+        for effect in self.effects_start.iter().chain(&self.effects) {
+            match effect {
+                Effect::Fact(Fact::UseVariable { variable }) => {
+                    used.insert(variable.as_str());
+                }
+                Effect::Fact(Fact::DefineVariable { variable }) => {
+                    defined.insert(variable.as_str());
+                }
+                _ => {}
+            }
+        }
+
+        used.intersection(&defined).cloned().collect()
+    }
+}
+
+/// Renders the program in the syntax of the grammar, in a form which parses back to an equal
+/// `Input`.
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "universal_regions {{ {} }}",
+            self.universal_regions.join(", ")
+        )?;
+
+        let var_region_mappings = [
+            ("var_uses_region", &self.var_uses_region),
+            ("var_drops_region", &self.var_drops_region),
+        ];
+        for (name, mappings) in &var_region_mappings {
+            if !mappings.is_empty() {
+                let mappings: Vec<_> = mappings
+                    .iter()
+                    .map(|(variable, region)| format!("({}, {})", variable, region))
+                    .collect();
+                writeln!(f, "{} {{ {} }}", name, mappings.join(", "))?;
+            }
+        }
+
+        for block in &self.blocks {
+            write!(f, "\n{}", block)?;
+        }
+
+        if !self.assertions.is_empty() {
+            writeln!(f)?;
+            for assertion in &self.assertions {
+                writeln!(f, "{}", assertion)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Block {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "block {} {{", self.name)?;
+        for statement in &self.statements {
+            writeln!(f, "    {}", statement)?;
+        }
+        if !self.goto.is_empty() {
+            writeln!(f, "    goto {};", self.goto.join(", "))?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn effects(effects: &[Effect]) -> String {
+            effects
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
+
+        // The start effects only need to be explicit when they differ from the ones
+        // `Statement::new` would derive from the mid effects.
+        if *self == Statement::new(self.effects.clone()) {
+            write!(f, "{};", effects(&self.effects))
+        } else if self.effects_start.is_empty() {
+            write!(f, "/ {};", effects(&self.effects))
+        } else {
+            write!(
+                f,
+                "{} / {};",
+                effects(&self.effects_start),
+                effects(&self.effects)
+            )
+        }
+    }
+}
+
+impl fmt::Display for LivenessAssertion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let live = match &self.live {
+            Liveness::Region(region) => region,
+            Liveness::Variable(variable) => variable,
+        };
+        write!(f, "assert_live({} @ {})", live, self.point)
+    }
+}
+
+impl fmt::Display for Effect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Effect::Use { regions } => write!(f, "use({})", regions.join(", ")),
+            Effect::Fact(fact) => write!(f, "{}", fact),
+        }
+    }
+}
+
+impl fmt::Display for Fact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fact::Outlives { a, b } => write!(f, "outlives({}: {})", a, b),
+            Fact::BorrowRegionAt { region, loan } => {
+                write!(f, "borrow_region_at({}, {})", region, loan)
+            }
+            Fact::BorrowReservedAt { loan } => write!(f, "borrow_reserved_at({})", loan),
+            Fact::BorrowActivatedAt { loan } => write!(f, "borrow_activated_at({})", loan),
+            Fact::ClosureRequires { region } => write!(f, "closure_requires({})", region),
+            Fact::LoanReborrows { loan, parent } => {
+                write!(f, "loan_reborrows({}, {})", loan, parent)
+            }
+            Fact::LoanBorrowsVar { loan, variable } => {
+                write!(f, "loan_borrows_var({}, {})", loan, variable)
+            }
+            Fact::Invalidates { loan } => write!(f, "invalidates({})", loan),
+            Fact::Kill { loan } => write!(f, "kill({})", loan),
+            Fact::RegionLiveAt { region } => write!(f, "region_live_at({})", region),
+            Fact::DefineVariable { variable } => write!(f, "var_defined({})", variable),
+            Fact::UseVariable { variable } => write!(f, "var_used({})", variable),
+        }
+    }
 }
@@ -7,7 +7,33 @@ lalrpop_mod!(#[rustfmt::skip] parser); // synthetized by LALRPOP
 mod tests;
 
 pub fn parse_input(text: &str) -> Result<ir::Input, String> {
-    parser::InputParser::new()
-        .parse(text)
-        .map_err(|e| format!("Polonius parse error: {:?}", e))
+    let mut errors = Vec::new();
+    let input = parser::InputParser::new()
+        .parse(&mut errors, text)
+        .map_err(|e| format!("Polonius parse error: {:?}", e))?;
+
+    // Statements with errors are recovered from, to be able to report them all in
+    // `parse_all_errors`, but they still make the parse fail.
+    match errors.into_iter().next() {
+        Some(recovery) => Err(format!("Polonius parse error: {:?}", recovery.error)),
+        None => Ok(input),
+    }
+}
+
+/// Parses the program and returns all its parse errors, instead of stopping at the first one
+/// like `parse_input`: parsing resumes after the end of a statement with an error. An error
+/// outside of statements, or too close to the end of the program, can't be recovered from, and
+/// is then the last one returned.
+pub fn parse_all_errors(text: &str) -> Vec<String> {
+    let mut errors = Vec::new();
+    let result = parser::InputParser::new().parse(&mut errors, text);
+
+    let mut messages: Vec<String> = errors
+        .into_iter()
+        .map(|recovery| format!("Polonius parse error: {:?}", recovery.error))
+        .collect();
+    if let Err(e) = result {
+        messages.push(format!("Polonius parse error: {:?}", e));
+    }
+    messages
 }
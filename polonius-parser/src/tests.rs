@@ -1,7 +1,7 @@
 #![cfg(test)]
 
-use crate::ir::{Effect, Fact};
-use crate::parse_input;
+use crate::ir::{Effect, Fact, Liveness, LivenessAssertion, Statement};
+use crate::{parse_all_errors, parse_input};
 
 #[test]
 fn universal_regions() {
@@ -177,6 +177,44 @@ fn effects_start() {
     );
 }
 
+#[test]
+fn explicit_start_and_mid_effects() {
+    let program = r"
+        universal_regions { 'a }
+        block B0 {
+            start { invalidates(L0) } mid { region_live_at('a), kill(L0) };
+            start { } mid { invalidates(L1) };
+        }
+    ";
+    let input = parse_input(program).unwrap();
+    let statements = &input.blocks[0].statements;
+
+    // The effects are placed as written: region liveness isn't copied to the start point
+    assert_eq!(
+        statements[0],
+        Statement::with_start_effects(
+            vec![Effect::Fact(Fact::Invalidates {
+                loan: "L0".to_string()
+            })],
+            vec![
+                Effect::Fact(Fact::RegionLiveAt {
+                    region: "'a".to_string()
+                }),
+                Effect::Fact(Fact::Kill {
+                    loan: "L0".to_string()
+                }),
+            ]
+        )
+    );
+    assert!(statements[1].effects_start.is_empty());
+    assert_eq!(
+        statements[1].effects,
+        [Effect::Fact(Fact::Invalidates {
+            loan: "L1".to_string()
+        })]
+    );
+}
+
 #[test]
 fn complete_example() {
     let program = r"
@@ -298,3 +336,161 @@ fn var_drops_region() {
         [("V1".to_string(), "'a".to_string())]
     );
 }
+
+#[test]
+fn display_round_trip() {
+    let program = r"
+        universal_regions { 'a, 'b }
+        var_uses_region { (V1, 'a) }
+        var_drops_region { (V2, 'b) }
+
+        block B0 {
+            borrow_region_at('a, L0), outlives('a: 'b), region_live_at('b);
+            invalidates(L0) / kill(L0), use('a, 'b);
+            var_defined(V1), var_used(V2);
+            ;
+            goto B1, B2;
+        }
+
+        block B1 {
+        }
+
+        block B2 {
+            / region_live_at('a);
+            goto B1;
+        }
+    ";
+    let input = parse_input(program).unwrap();
+
+    let printed = input.to_string();
+    assert_eq!(
+        printed,
+        "\
+universal_regions { 'a, 'b }
+var_uses_region { (V1, 'a) }
+var_drops_region { (V2, 'b) }
+
+block B0 {
+    borrow_region_at('a, L0), outlives('a: 'b), region_live_at('b);
+    invalidates(L0) / kill(L0), use('a, 'b);
+    var_defined(V1), var_used(V2);
+    ;
+    goto B1, B2;
+}
+
+block B1 {
+}
+
+block B2 {
+    / region_live_at('a);
+    goto B1;
+}
+"
+    );
+    assert_eq!(parse_input(&printed).unwrap(), input);
+}
+
+#[test]
+fn all_parse_errors() {
+    let program = r"
+        universal_regions { 'a }
+        block B0 {
+            borrow_region_at('a, L0);
+            outlives('a 'b);
+            invalidates(L0);
+            kill(0);
+        }
+    ";
+    assert!(parse_input(program).is_err());
+
+    let errors = parse_all_errors(program);
+    assert_eq!(errors.len(), 2);
+    assert!(errors[0].contains("'b"));
+    assert!(errors[1].contains("InvalidToken"));
+
+    let program = r"
+        universal_regions { 'a }
+        block B0 {
+            kill(L0);
+        }
+    ";
+    assert!(parse_all_errors(program).is_empty());
+}
+
+#[test]
+fn two_phase_borrows() {
+    let program = r"
+        universal_regions { 'a }
+        block B0 {
+            borrow_region_at('a, L0), borrow_reserved_at(L0);
+            borrow_activated_at(L0);
+        }
+    ";
+    let input = parse_input(program).unwrap();
+    let statements = &input.blocks[0].statements;
+    assert_eq!(
+        statements[0].effects[1],
+        Effect::Fact(Fact::BorrowReservedAt {
+            loan: "L0".to_string()
+        })
+    );
+    assert_eq!(
+        statements[1].effects[0],
+        Effect::Fact(Fact::BorrowActivatedAt {
+            loan: "L0".to_string()
+        })
+    );
+}
+
+#[test]
+fn variables_used_and_defined() {
+    let program = r"
+        universal_regions { }
+        block B0 {
+            var_used(V0), var_defined(V0), var_defined(V1);
+            var_defined(V1) / var_used(V1), var_used(V2);
+            var_used(V0);
+        }
+    ";
+    let input = parse_input(program).unwrap();
+    let statements = &input.blocks[0].statements;
+    let variables: Vec<Vec<&str>> = statements
+        .iter()
+        .map(|statement| statement.variables_used_and_defined().into_iter().collect())
+        .collect();
+    assert_eq!(variables, [vec!["V0"], vec!["V1"], vec![]]);
+}
+
+#[test]
+fn liveness_assertions() {
+    let program = r"
+        universal_regions { 'a }
+
+        block B0 {
+            var_used(V0), region_live_at('a);
+        }
+
+        assert_live('a @ Start(B0[0]))
+        assert_live(V0 @ Start(B0[0]))
+    ";
+    let input = parse_input(program).unwrap();
+    assert_eq!(
+        input.assertions,
+        [
+            LivenessAssertion {
+                live: Liveness::Region("'a".to_string()),
+                point: "Start(B0[0])".to_string(),
+            },
+            LivenessAssertion {
+                live: Liveness::Variable("V0".to_string()),
+                point: "Start(B0[0])".to_string(),
+            },
+        ]
+    );
+
+    let printed = input.to_string();
+    assert!(printed.ends_with("\nassert_live('a @ Start(B0[0]))\nassert_live(V0 @ Start(B0[0]))\n"));
+    assert_eq!(parse_input(&printed).unwrap(), input);
+
+    assert!(parse_input(&program.replace("Start(B0[0])", "End(B0[0])")).is_err());
+}
@@ -0,0 +1,227 @@
+use crate::facts::{AllFacts, Loan, Point, Region, Variable};
+use crate::intern::InternerTables;
+use crate::lsp::Span;
+use log::warn;
+use polonius_engine::Output;
+use rustc_hash::FxHashMap;
+use std::fmt::Write;
+
+/// Renders the errors of the `output` like rustc does: for each error, the lines of the `source`
+/// where the loan is invalidated, and where it was borrowed, with their spans underlined. Errors
+/// at points without a span are skipped with a warning. The loans whose borrowed variable is
+/// known from the `loan_borrows_var` facts are described by their variable.
+pub(crate) fn render_annotated_source(
+    output: &Output<Region, Loan, Point, Variable>,
+    source: &str,
+    spans: &FxHashMap<Point, Span>,
+    facts: &AllFacts,
+    tables: &InternerTables,
+) -> String {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut rendered = String::new();
+    for (point, loan) in output.all_errors() {
+        let span = match spans.get(&point) {
+            Some(span) => span,
+            None => {
+                warn!(
+                    "no source span for point {}, skipping its error",
+                    tables.points.untern(point)
+                );
+                continue;
+            }
+        };
+
+        let (borrow, borrowed) = match facts.loan_variable(loan) {
+            Some(variable) => {
+                let variable = tables.variables.untern(variable);
+                (
+                    format!("borrow of `{}`", variable),
+                    format!("`{}`", variable),
+                )
+            }
+            None => {
+                let loan = tables.loans.untern(loan);
+                (format!("borrow {}", loan), loan.to_string())
+            }
+        };
+        let mut labels = vec![(span, '^', format!("{} is invalidated here", borrowed))];
+        let borrow_span = facts
+            .loan_origin(loan)
+            .and_then(|(_, borrow_point)| spans.get(&borrow_point));
+        if let Some(borrow_span) = borrow_span {
+            labels.push((borrow_span, '-', format!("{} is borrowed here", borrowed)));
+        }
+        labels.sort_by_key(|&(span, ..)| (span.start.line, span.start.character));
+
+        let gutter = labels
+            .iter()
+            .map(|(span, ..)| (span.start.line + 1).to_string().len())
+            .max()
+            .unwrap();
+
+        writeln!(
+            rendered,
+            "error: {} is invalidated while still live",
+            borrow
+        )
+        .unwrap();
+        writeln!(
+            rendered,
+            "{:gutter$}--> {}:{}:{}",
+            "",
+            span.uri,
+            span.start.line + 1,
+            span.start.character + 1,
+            gutter = gutter
+        )
+        .unwrap();
+        writeln!(rendered, "{:gutter$} |", "", gutter = gutter).unwrap();
+
+        let mut previous_line = None;
+        for (span, marker, message) in labels {
+            let line_index = span.start.line as usize;
+            let line = lines.get(line_index).cloned().unwrap_or("");
+            if previous_line != Some(line_index) {
+                writeln!(
+                    rendered,
+                    "{:>gutter$} | {}",
+                    line_index + 1,
+                    line,
+                    gutter = gutter
+                )
+                .unwrap();
+                previous_line = Some(line_index);
+            }
+
+            // Spans covering several lines are underlined until the end of their first line.
+            let start = span.start.character as usize;
+            let end = if span.end.line == span.start.line {
+                span.end.character as usize
+            } else {
+                line.chars().count()
+            };
+            let underline: String = (0..end.saturating_sub(start).max(1))
+                .map(|_| marker)
+                .collect();
+            writeln!(
+                rendered,
+                "{:gutter$} | {:start$}{} {}",
+                "",
+                "",
+                underline,
+                message,
+                gutter = gutter,
+                start = start
+            )
+            .unwrap();
+        }
+        writeln!(rendered, "{:gutter$} |", "", gutter = gutter).unwrap();
+        rendered.push('\n');
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lsp::Position;
+    use crate::program;
+    use polonius_engine::Algorithm;
+
+    const PROGRAM: &str = r"
+        universal_regions { }
+
+        block B0 {
+            borrow_region_at('a, L0), region_live_at('a);
+            invalidates(L0), region_live_at('a);
+        }
+    ";
+
+    const SOURCE: &str = "\
+let mut v = vec![];
+let r = &v;
+v.push(1);
+r.len();
+";
+
+    fn span(line: u32, start: u32, end: u32) -> Span {
+        Span {
+            uri: "src/main.rs".to_string(),
+            start: Position {
+                line,
+                character: start,
+            },
+            end: Position {
+                line,
+                character: end,
+            },
+        }
+    }
+
+    #[test]
+    fn annotated_errors() {
+        let mut tables = InternerTables::new();
+        let facts = program::parse_from_program(PROGRAM, &mut tables).unwrap();
+        let output = Output::compute(&facts, Algorithm::Naive, false);
+        let mut spans = FxHashMap::default();
+        spans.insert(tables.points.intern("\"Mid(B0[0])\""), span(1, 8, 10));
+        spans.insert(tables.points.intern("\"Mid(B0[1])\""), span(2, 0, 9));
+
+        assert_eq!(
+            render_annotated_source(&output, SOURCE, &spans, &facts, &tables),
+            "\
+error: borrow L0 is invalidated while still live
+ --> src/main.rs:3:1
+  |
+2 | let r = &v;
+  |         -- L0 is borrowed here
+3 | v.push(1);
+  | ^^^^^^^^^ L0 is invalidated here
+  |
+
+"
+        );
+    }
+
+    #[test]
+    fn annotated_errors_with_borrowed_variables() {
+        let mut tables = InternerTables::new();
+        let program = PROGRAM.replace(
+            "borrow_region_at('a, L0),",
+            "borrow_region_at('a, L0), loan_borrows_var(L0, V0),",
+        );
+        let facts = program::parse_from_program(&program, &mut tables).unwrap();
+        assert_eq!(
+            facts.loan_variable(tables.loans.intern("L0")),
+            Some(tables.variables.intern("V0"))
+        );
+        let output = Output::compute(&facts, Algorithm::Naive, false);
+        let mut spans = FxHashMap::default();
+        spans.insert(tables.points.intern("\"Mid(B0[1])\""), span(2, 0, 9));
+
+        assert_eq!(
+            render_annotated_source(&output, SOURCE, &spans, &facts, &tables),
+            "\
+error: borrow of `V0` is invalidated while still live
+ --> src/main.rs:3:1
+  |
+3 | v.push(1);
+  | ^^^^^^^^^ `V0` is invalidated here
+  |
+
+"
+        );
+    }
+
+    #[test]
+    fn errors_without_spans_are_skipped() {
+        let mut tables = InternerTables::new();
+        let facts = program::parse_from_program(PROGRAM, &mut tables).unwrap();
+        let output = Output::compute(&facts, Algorithm::Naive, false);
+        assert!(!output.errors.is_empty());
+        assert_eq!(
+            render_annotated_source(&output, SOURCE, &FxHashMap::default(), &facts, &tables),
+            ""
+        );
+    }
+}
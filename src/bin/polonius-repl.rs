@@ -0,0 +1,8 @@
+extern crate env_logger;
+extern crate failure;
+extern crate polonius;
+
+pub fn main() -> Result<(), failure::Error> {
+    env_logger::init();
+    polonius::repl::main()
+}
@@ -1,11 +1,18 @@
+use crate::annotate;
 use crate::dump;
 use crate::facts::{Loan, Point, Region, Variable};
 use crate::intern;
+use crate::lsp;
+use crate::program;
 use crate::tab_delim;
+use crate::validate;
 use failure::Error;
-use log::error;
-use polonius_engine::{Algorithm, AllFacts, Output};
+use log::{error, info, warn};
+use polonius_engine::{Algorithm, AllFacts, ComputeOptions, JoinOrder, Output, Trace, TraceFormat};
+use std::fs;
+use std::io::{self, BufRead};
 use std::path::Path;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use structopt::StructOpt;
 
@@ -13,8 +20,14 @@ type PoloniusFacts = AllFacts<Region, Loan, Point, Variable>;
 type PoloniusOutput = Output<Region, Loan, Point, Variable>;
 
 #[derive(StructOpt, Debug)]
-#[structopt(name = "borrow-check")]
+#[structopt(
+    name = "borrow-check",
+    raw(setting = "structopt::clap::AppSettings::SubcommandsNegateReqs")
+)]
 pub struct Opt {
+    #[structopt(subcommand)]
+    command: Option<Command>,
+
     #[structopt(
         short = "a",
         env = "POLONIUS_ALGORITHM",
@@ -24,6 +37,21 @@ pub struct Opt {
     algorithm: Algorithm,
     #[structopt(long = "show-tuples", help = "Show output tuples on stdout")]
     show_tuples: bool,
+    #[structopt(
+        long = "show-errors",
+        help = "Show the errors on stdout, at the lines of the optional `point_line.facts` file"
+    )]
+    show_errors: bool,
+    #[structopt(
+        long = "digest",
+        help = "Show a digest of the errors and liveness, which changes if and only if they do"
+    )]
+    digest: bool,
+    #[structopt(
+        long = "algorithm-report",
+        help = "Show, as JSON, the errors of each algorithm and whether they agree with naive"
+    )]
+    algorithm_report: bool,
     #[structopt(long = "skip-timing", help = "Do not display timing results")]
     skip_timing: bool,
     #[structopt(
@@ -50,12 +78,125 @@ pub struct Opt {
         help = "Generate a graphviz file to visualize the liveness information"
     )]
     liveness_graph_file: Option<String>,
+    #[structopt(
+        long = "dump-cfg-graph",
+        help = "Generate a graphviz file of the CFG, annotated with the errors and live loans"
+    )]
+    cfg_graph_file: Option<String>,
+    #[structopt(
+        long = "dump-subset-graph",
+        help = "Show the subset relation at this point on stdout, as a graphviz digraph"
+    )]
+    subset_graph_point: Option<String>,
+    #[structopt(
+        long = "block-live-in",
+        help = "Show the variables live on entry to this block on stdout"
+    )]
+    live_in_block: Option<String>,
+    #[structopt(
+        long = "timeline-csv",
+        help = "Write the number of live regions, live loans and errors at each point, in CFG \
+                order, to this CSV file"
+    )]
+    timeline_csv_file: Option<String>,
+
+    #[structopt(
+        long = "lsp-diagnostics",
+        help = "Write the errors as LSP diagnostics in a JSON file, using the points' source spans"
+    )]
+    lsp_diagnostics_file: Option<String>,
+
+    #[structopt(
+        long = "annotate-source",
+        help = "Show the errors on stdout, like rustc, as annotations of this source file at the \
+                points' source spans"
+    )]
+    annotated_source_file: Option<String>,
+
+    #[structopt(
+        long = "tar-archive",
+        help = "Load the facts from a tar archive, the fact directories being the functions' names"
+    )]
+    tar_archive: Option<String>,
+
+    #[structopt(
+        long = "combined",
+        help = "Load the facts from combined files, where each line starts with a relation name, \
+                or from stdin as they arrive if the facts directory is `-`"
+    )]
+    combined: bool,
+
+    #[structopt(
+        long = "program",
+        help = "Load the facts from program files, in the syntax of the tests, instead of \
+                facts directories"
+    )]
+    program: bool,
+
+    #[structopt(
+        long = "show-program",
+        help = "With `--program`, show the effects of the program at each point, along with the \
+                errors there, and the liveness assertions which fail"
+    )]
+    show_program: bool,
+
+    #[structopt(
+        long = "strict",
+        help = "Check the facts for inconsistencies which usually indicate a bug in their \
+                extraction, like loans without a `borrow_region` fact or facts at points outside \
+                the CFG, and warn about each one"
+    )]
+    strict: bool,
 
     #[structopt(
         long = "ignore-region-live-at",
         help = "ignore any provided region-live-at and let Polonius perform the calculation"
     )]
     ignore_region_live_at: bool,
+
+    #[structopt(
+        long = "canonical-region-names",
+        help = "Rename the regions like `'_#1r` to `'1`, to compare the outputs of different \
+                extractions"
+    )]
+    canonical_region_names: bool,
+
+    #[structopt(
+        long = "trace",
+        raw(
+            possible_values = "&TraceFormat::variants()",
+            case_insensitive = "true"
+        ),
+        help = "Write the tuples derived at each round of the Naive and DatafrogOpt fixpoints \
+                on stderr, as text or NDJSON"
+    )]
+    trace: Option<TraceFormat>,
+
+    #[structopt(
+        long = "join-order",
+        raw(possible_values = "&JoinOrder::variants()", case_insensitive = "true"),
+        help = "How DatafrogOpt joins the premises of its rules carrying subset and requires \
+                along the CFG edges, to benchmark alternatives (default: Leapjoin)"
+    )]
+    join_order: Option<JoinOrder>,
+}
+
+#[derive(StructOpt, Debug)]
+enum Command {
+    #[structopt(
+        name = "normalize",
+        about = "Write the facts of a directory back in a canonical order, without duplicates"
+    )]
+    Normalize {
+        facts_dir: String,
+        output_directory: String,
+    },
+    #[structopt(
+        name = "count-lines",
+        about = "Show the number of lines of each relation's file in a directory, without \
+                 loading the facts"
+    )]
+    CountLines { facts_dir: String },
 }
 
 macro_rules! attempt {
@@ -65,6 +206,20 @@ macro_rules! attempt {
 }
 
 pub fn main(opt: Opt) -> Result<(), Error> {
+    match opt.command {
+        Some(Command::Normalize {
+            ref facts_dir,
+            ref output_directory,
+        }) => return normalize(Path::new(facts_dir), Path::new(output_directory)),
+        Some(Command::CountLines { ref facts_dir }) => {
+            for (relation, count) in tab_delim::count_lines(Path::new(facts_dir))? {
+                println!("{}\t{}", relation, count);
+            }
+            return Ok(());
+        }
+        None => {}
+    }
+
     let output_directory = opt
         .output_directory
         .as_ref()
@@ -74,20 +229,61 @@ pub fn main(opt: Opt) -> Result<(), Error> {
         .liveness_graph_file
         .as_ref()
         .map(|x| Path::new(x).to_owned());
+    let cfg_graph_file = opt.cfg_graph_file.as_ref().map(|x| Path::new(x).to_owned());
     for facts_dir in &opt.fact_dirs {
         let tables = &mut intern::InternerTables::new();
 
         let result: Result<(Duration, PoloniusFacts, PoloniusOutput), Error> = attempt! {
             let verbose = opt.verbose;
-            let mut all_facts =
-                tab_delim::load_tab_delimited_facts(tables, &Path::new(&facts_dir))?;
+            let mut all_facts = match opt.tar_archive {
+                Some(ref tar_archive) => {
+                    let archive = fs::File::open(tar_archive)?;
+                    tab_delim::load_from_tar(tables, io::BufReader::new(archive), facts_dir)?
+                }
+                None if opt.combined && facts_dir == "-" => load_combined_from_stdin(tables)?,
+                None if opt.combined => tab_delim::load_combined(tables, Path::new(&facts_dir))?,
+                None if opt.program => program::parse_from_file(Path::new(&facts_dir), tables)?,
+                None => {
+                    let (all_facts, missing) = tab_delim::load_tab_delimited_facts_with_missing(
+                        tables,
+                        &Path::new(&facts_dir),
+                    )?;
+                    for relation in missing {
+                        info!("`{}`: no `{}` facts", facts_dir, relation);
+                    }
+                    all_facts
+                }
+            };
+            if opt.canonical_region_names {
+                tables.canonicalize_regions(canonical_region_name);
+            }
+            if opt.strict {
+                for problem in validate::check_facts(&all_facts, tables) {
+                    warn!("`{}`: {}", facts_dir, problem);
+                }
+            }
             if opt.ignore_region_live_at {
                 all_facts.region_live_at = Vec::default();
             }
             let algorithm = opt.algorithm;
-            let graphviz_output = graphviz_file.is_some() || liveness_graph_file.is_some();
+            let graphviz_output = graphviz_file.is_some()
+                || liveness_graph_file.is_some()
+                || cfg_graph_file.is_some()
+                || opt.subset_graph_point.is_some()
+                || opt.live_in_block.is_some()
+                || opt.timeline_csv_file.is_some()
+                || opt.show_program
+                || opt.digest;
+            let options = ComputeOptions {
+                dump_enabled: verbose || graphviz_output,
+                trace: opt
+                    .trace
+                    .map(|format| Trace::new(Arc::new(Mutex::new(io::stderr())), format)),
+                join_order: opt.join_order.unwrap_or_default(),
+                ..ComputeOptions::default()
+            };
             let (duration, output) =
-                timed(|| Output::compute(&all_facts, algorithm, verbose || graphviz_output));
+                timed(|| Output::compute_with_options(&all_facts, algorithm, options));
             (duration, all_facts, output)
         };
 
@@ -100,6 +296,65 @@ pub fn main(opt: Opt) -> Result<(), Error> {
                     let millis = f64::from(duration.subsec_nanos()) * 0.000_000_001_f64;
                     println!("Time: {:0.3}s", seconds + millis);
                 }
+                if opt.digest {
+                    println!("Digest: {:016x}", dump::digest(&output, tables));
+                }
+                if opt.algorithm_report {
+                    let report = Output::algorithm_report(&all_facts, !opt.skip_timing);
+                    println!("Algorithm report: {}", report.to_json());
+                }
+                if opt.verbose {
+                    let mut subset_stats: Vec<(Point, usize)> =
+                        output.subset_stats().into_iter().collect();
+                    subset_stats.sort_by_key(|&(_, edges)| std::cmp::Reverse(edges));
+                    println!("Subset edges per point:");
+                    for (point, edges) in subset_stats {
+                        println!("  {}: {}", tables.points.untern(point), edges);
+                    }
+                }
+                if let Some(peak_tuples) = output.peak_tuples {
+                    println!("Peak tuples: {}", peak_tuples);
+                }
+                if opt.show_errors {
+                    // Programs have no source lines, their errors are shown at their points.
+                    let lines = if opt.program {
+                        Default::default()
+                    } else {
+                        tab_delim::load_point_lines(tables, Path::new(&facts_dir))
+                            .expect("Failed to load point lines")
+                    };
+                    for (point, loan, line) in output.errors_with_lines(&lines) {
+                        let location = match line {
+                            Some(line) => format!("line {}", line),
+                            None => tables.points.untern(point).to_string(),
+                        };
+                        println!("error at {}: loan {}", location, tables.loans.untern(loan));
+                    }
+                }
+                if opt.show_program && opt.program {
+                    let program = fs::read_to_string(facts_dir).expect("Failed to read program");
+                    let program = program::parse_program(&program, tables, Default::default())
+                        .expect("Failed to parse program");
+                    for (point, effects) in program.point_effects() {
+                        let effects: Vec<_> = effects.iter().map(ToString::to_string).collect();
+                        let errors: Vec<_> = output
+                            .errors
+                            .get(&point)
+                            .into_iter()
+                            .flatten()
+                            .map(|&loan| tables.loans.untern(loan))
+                            .collect();
+                        print!("{}: {}", tables.points.untern(point), effects.join(", "));
+                        if errors.is_empty() {
+                            println!();
+                        } else {
+                            println!(" // error: {}", errors.join(", "));
+                        }
+                    }
+                    for assertion in program.failed_assertions(&output, tables) {
+                        println!("failed assertion: {}", assertion);
+                    }
+                }
                 if opt.show_tuples {
                     dump::dump_output(&output, &output_directory, tables)
                         .expect("Failed to write output");
@@ -112,6 +367,44 @@ pub fn main(opt: Opt) -> Result<(), Error> {
                     dump::liveness_graph(&output, &all_facts, liveness_graph_file, tables)
                         .expect("Failed to write liveness graph");
                 }
+                if let Some(ref cfg_graph_file) = cfg_graph_file {
+                    dump::cfg_graphviz(&all_facts, Some(&output), cfg_graph_file, tables)
+                        .expect("Failed to write CFG graph");
+                }
+                if let Some(ref lsp_diagnostics_file) = opt.lsp_diagnostics_file {
+                    let spans = tab_delim::load_point_spans(tables, Path::new(&facts_dir))
+                        .expect("Failed to load point spans");
+                    let diagnostics = lsp::to_lsp_diagnostics(&output, &spans, tables);
+                    fs::write(lsp_diagnostics_file, diagnostics)
+                        .expect("Failed to write LSP diagnostics");
+                }
+                if let Some(ref subset_graph_point) = opt.subset_graph_point {
+                    let point = tables.points.intern(subset_graph_point);
+                    println!("{}", dump::subset_to_dot(&output, point, tables));
+                }
+                if let Some(ref block) = opt.live_in_block {
+                    let variables = dump::block_live_in(&output, block, &all_facts, tables);
+                    let variables: Vec<String> = variables.into_iter().collect();
+                    println!("Live in {}: {}", block, variables.join(", "));
+                }
+                if let Some(ref timeline_csv_file) = opt.timeline_csv_file {
+                    let mut file =
+                        fs::File::create(timeline_csv_file).expect("Failed to create timeline CSV");
+                    dump::timeline_csv(&mut file, &output, &all_facts, tables)
+                        .expect("Failed to write timeline CSV");
+                }
+                if let Some(ref annotated_source_file) = opt.annotated_source_file {
+                    let source = fs::read_to_string(annotated_source_file)
+                        .expect("Failed to read the source file");
+                    let spans = tab_delim::load_point_spans(tables, Path::new(&facts_dir))
+                        .expect("Failed to load point spans");
+                    print!(
+                        "{}",
+                        annotate::render_annotated_source(
+                            &output, &source, &spans, &all_facts, tables
+                        )
+                    );
+                }
             }
 
             Err(error) => {
@@ -123,6 +416,50 @@ pub fn main(opt: Opt) -> Result<(), Error> {
     Ok(())
 }
 
+fn normalize(facts_dir: &Path, output_directory: &Path) -> Result<(), Error> {
+    let tables = &mut intern::InternerTables::new();
+    let mut all_facts = tab_delim::load_tab_delimited_facts(tables, facts_dir)?;
+    tab_delim::normalize_facts(&mut all_facts, tables);
+    tab_delim::write_tab_delimited_facts(&all_facts, tables, output_directory)?;
+    Ok(())
+}
+
+/// Removes the decorations of rustc's region names: `'_#1r`, or `"\'_#1r"` when quoted, becomes
+/// `'1`, or `"\'1"`. Other names are unchanged.
+fn canonical_region_name(name: &str) -> String {
+    let (unquoted, quote) = if name.len() > 1 && name.starts_with('"') && name.ends_with('"') {
+        (&name[1..name.len() - 1], "\"")
+    } else {
+        (name, "")
+    };
+    match unquoted.find("_#") {
+        Some(index) if unquoted.ends_with('r') => format!(
+            "{}{}{}{}",
+            quote,
+            &unquoted[..index],
+            &unquoted[index + 2..unquoted.len() - 1],
+            quote
+        ),
+        _ => name.to_string(),
+    }
+}
+
+/// Loads combined facts from stdin, interning each tuple as soon as its line is read.
+fn load_combined_from_stdin(tables: &mut intern::InternerTables) -> io::Result<PoloniusFacts> {
+    let mut sink = tables.fact_sink();
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        let mut columns = line.split('\t');
+        let relation = columns.next().unwrap();
+        sink.push(relation, &columns.collect::<Vec<_>>())?;
+    }
+    Ok(sink.finish())
+}
+
 fn timed<T>(op: impl FnOnce() -> T) -> (Duration, T) {
     let start = Instant::now();
     let output = op();
@@ -24,6 +24,12 @@ pub(crate) fn dump_output(
         &output.errors,
     )?;
 
+    dump_rows(
+        &mut writer_for(output_dir, "closure_errors")?,
+        intern,
+        &output.closure_errors,
+    )?;
+
     if output.dump_enabled {
         dump_rows(
             &mut writer_for(output_dir, "restricts")?,
@@ -79,6 +85,39 @@ pub(crate) fn dump_output(
     }
 }
 
+/// Computes a digest of the results of the `output`: its `errors`, and, if it was computed
+/// with the dump enabled, its `borrow_live_at`, `var_live_at` and `region_live_at` relations.
+/// The tuples are hashed by the names of their atoms, in sorted order, using FNV-1a: equal
+/// outputs have equal digests, whatever the order of their maps, on every run and platform.
+pub(crate) fn digest(
+    output: &Output<Region, Loan, Point, Variable>,
+    intern: &InternerTables,
+) -> u64 {
+    let mut relations: Vec<(&str, &dyn OutputDump)> = vec![("errors", &output.errors)];
+    if output.dump_enabled {
+        relations.push(("borrow_live_at", &output.borrow_live_at));
+        relations.push(("var_live_at", &output.var_live_at));
+        relations.push(("region_live_at", &output.region_live_at));
+    }
+
+    let mut lines = Vec::new();
+    for (name, value) in relations {
+        let mut rows = Vec::new();
+        value.push_all(intern, &mut vec![name], &mut rows);
+        lines.extend(rows.into_iter().map(|row| row.join("\t")));
+    }
+    lines.sort();
+
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for line in &lines {
+        for byte in line.bytes().chain(Some(b'\n')) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
 trait OutputDump {
     fn push_all<'a>(
         &'a self,
@@ -261,7 +300,8 @@ fn preserve<'a>(s: &mut Vec<&'a str>, op: impl FnOnce(&mut Vec<&'a str>)) {
     s.truncate(len);
 }
 
-pub(crate) trait Atom: Copy + From<usize> + Into<usize> {
+/// An atom which can be dumped, using the table it was interned into.
+pub(crate) trait Atom: PoloniusEngineAtom {
     fn table(intern: &InternerTables) -> &Interner<Self>;
 }
 
@@ -364,6 +404,27 @@ fn build_inputs_by_point_for_visualization(
             1,
             intern,
         ),
+        facts_by_point(
+            all_facts.borrow_reserved_at.iter().cloned(),
+            |(l, p)| (p, (l,)),
+            "borrow_reserved_at".to_string(),
+            1,
+            intern,
+        ),
+        facts_by_point(
+            all_facts.borrow_activated_at.iter().cloned(),
+            |(l, p)| (p, (l,)),
+            "borrow_activated_at".to_string(),
+            1,
+            intern,
+        ),
+        facts_by_point(
+            all_facts.closure_requires.iter().cloned(),
+            |(r, p)| (p, (r,)),
+            "closure_requires".to_string(),
+            1,
+            intern,
+        ),
         facts_by_point(
             all_facts.invalidates.iter().cloned(),
             |(p, l)| (p, (l,)),
@@ -823,3 +884,168 @@ pub(crate) fn liveness_graph(
     file.write_all(&output_bytes)?;
     Ok(())
 }
+
+/// Renders the CFG as a graphviz digraph. When an `output` is given, each point is annotated
+/// with its number of errors and of live loans, and colored accordingly: red points have errors,
+/// and yellow points have live loans. The live loans are only known if the output was computed
+/// with the dump enabled.
+pub(crate) fn render_cfg_graphviz(
+    all_facts: &AllFacts,
+    output: Option<&Output<Region, Loan, Point, Variable>>,
+    intern: &InternerTables,
+) -> String {
+    let mut output_fragments: Vec<String> = Vec::new();
+    let mut seen_points = BTreeSet::new();
+    let error_counts = output
+        .map(Output::error_counts_per_point)
+        .unwrap_or_default();
+
+    output_fragments.push("digraph g {\n  graph [\n  rankdir = \"TD\"\n];\n".to_string());
+    for &(p1, p2) in &all_facts.cfg_edge {
+        for &pt in &[p1, p2] {
+            if !seen_points.insert(pt) {
+                continue;
+            }
+
+            let point = escape_for_graphviz(Point::table(intern).untern(pt));
+            output_fragments.push(match output {
+                None => format!("\"node{}\" [label = \"{}\"];\n", pt.index(), point),
+                Some(output) => {
+                    let error_count = error_counts.get(&pt).cloned().unwrap_or(0);
+                    let live_loan_count = output
+                        .borrow_live_at
+                        .get(&pt)
+                        .map_or(0, |loans| loans.len());
+                    let color = if error_count > 0 {
+                        "#FD6467"
+                    } else if live_loan_count > 0 {
+                        "#FDDDA0"
+                    } else {
+                        "#FFFFFF"
+                    };
+                    format!(
+                        "\"node{}\" [label = \"{}\\lerrors: {}\\llive loans: {}\\l\" \
+                         style = \"filled\" fillcolor = \"{}\"];\n",
+                        pt.index(),
+                        point,
+                        error_count,
+                        live_loan_count,
+                        color
+                    )
+                }
+            });
+        }
+
+        output_fragments.push(format!(
+            "\"node{}\" -> \"node{}\";\n",
+            p1.index(),
+            p2.index()
+        ));
+    }
+    output_fragments.push("}".to_string()); // close digraph
+    output_fragments.join("")
+}
+
+pub(crate) fn cfg_graphviz(
+    all_facts: &AllFacts,
+    output: Option<&Output<Region, Loan, Point, Variable>>,
+    output_file: &PathBuf,
+    intern: &InternerTables,
+) -> io::Result<()> {
+    let mut file = File::create(output_file)?;
+    file.write_all(render_cfg_graphviz(all_facts, output, intern).as_bytes())
+}
+
+/// Renders the `subset` relation at `point` as a graphviz digraph, with an `'a -> 'b` edge for
+/// each `subset('a, 'b, point)` tuple. The self-loops, which the computations should never
+/// produce, are highlighted in red. The graph is empty if there are no subsets at this point,
+/// or if the output was computed without the dump enabled.
+pub(crate) fn subset_to_dot(
+    output: &Output<Region, Loan, Point, Variable>,
+    point: Point,
+    intern: &InternerTables,
+) -> String {
+    let mut output_fragments: Vec<String> = Vec::new();
+    output_fragments.push("digraph g {\n".to_string());
+    if let Some(subsets) = output.subset.get(&point) {
+        let regions: BTreeSet<Region> = subsets
+            .iter()
+            .flat_map(|(&r1, rs)| rs.iter().cloned().chain(Some(r1)))
+            .collect();
+        for r in regions {
+            output_fragments.push(format!(
+                "\"region{}\" [label = \"{}\"];\n",
+                r.index(),
+                escape_for_graphviz(Region::table(intern).untern(r))
+            ));
+        }
+
+        for (&r1, rs) in subsets {
+            for &r2 in rs {
+                let attributes = if r1 == r2 {
+                    " [color = \"#FD6467\" penwidth = 3]"
+                } else {
+                    ""
+                };
+                output_fragments.push(format!(
+                    "\"region{}\" -> \"region{}\"{};\n",
+                    r1.index(),
+                    r2.index(),
+                    attributes
+                ));
+            }
+        }
+    }
+    output_fragments.push("}".to_string()); // close digraph
+    output_fragments.join("")
+}
+
+/// Returns the names of the variables live on entry to the `block`, e.g. `B0` or `bb0`: at its
+/// first point, named `"Start(<block>[0])"` among the points of `all_facts`' CFG. The set is
+/// empty if there is no such point, or if the output was computed without the dump enabled.
+pub(crate) fn block_live_in(
+    output: &Output<Region, Loan, Point, Variable>,
+    block: &str,
+    all_facts: &AllFacts,
+    intern: &InternerTables,
+) -> BTreeSet<String> {
+    let entry_name = format!("\"Start({}[0])\"", block);
+    let entry = all_facts
+        .cfg_edge
+        .iter()
+        .flat_map(|&(p, q)| vec![p, q])
+        .find(|&point| intern.points.untern(point) == entry_name);
+
+    entry
+        .and_then(|entry| output.var_live_at.get(&entry))
+        .into_iter()
+        .flatten()
+        .map(|&variable| intern.variables.untern(variable).to_string())
+        .collect()
+}
+
+/// Writes the `timeline` of the output as CSV, for spreadsheets: a header, then one row per
+/// point of the CFG, in CFG order, with the number of regions live, of loans live, and of
+/// errors there. The points are named without their quotes.
+pub(crate) fn timeline_csv<W: Write>(
+    writer: &mut W,
+    output: &Output<Region, Loan, Point, Variable>,
+    all_facts: &AllFacts,
+    intern: &InternerTables,
+) -> io::Result<()> {
+    writeln!(writer, "point,live_regions,live_loans,errors")?;
+    for (point, live_regions, live_loans, errors) in output.timeline(all_facts) {
+        let name = intern.points.untern(point).replace("\"", "");
+        let name = if name.contains(',') {
+            format!("\"{}\"", name)
+        } else {
+            name
+        };
+        writeln!(
+            writer,
+            "{},{},{},{}",
+            name, live_regions, live_loans, errors
+        )?;
+    }
+    Ok(())
+}
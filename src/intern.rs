@@ -1,4 +1,5 @@
 use crate::facts::*;
+use crate::tab_delim::FactSink;
 use std::collections::HashMap;
 
 /// When we load facts out of the table, they are essentially random
@@ -29,6 +30,17 @@ where
         data.into_iter().map(|d| self.untern(*d)).collect()
     }
 
+    /// Renames every interned string with `f`, keeping their atoms. Panics if two strings are
+    /// renamed to the same one.
+    fn rename(&mut self, f: impl Fn(&str) -> String) {
+        self.strings.clear();
+        for (index, string) in self.rev_strings.iter_mut().enumerate() {
+            *string = f(string);
+            let previous = self.strings.insert(string.clone(), TargetType::from(index));
+            assert!(previous.is_none(), "`{}` is the name of two atoms", string);
+        }
+    }
+
     pub(crate) fn intern(&mut self, data: &str) -> TargetType {
         if let Some(&interned) = self.strings.get(data) {
             return interned;
@@ -56,33 +68,78 @@ impl InternerTables {
             variables: Interner::new(),
         }
     }
+
+    /// Renames the regions with `f`, e.g. to give the same names to the regions of different
+    /// extractions of the facts, like `'0` and `'_#0r`, before comparing their outputs.
+    ///
+    /// The atoms don't change, but the new names replace the old ones in the table: this must
+    /// be called after all the facts are loaded and before computing the output, or facts using
+    /// the old names would intern new, distinct, regions. Panics if two regions are renamed to
+    /// the same name.
+    pub(crate) fn canonicalize_regions(&mut self, f: impl Fn(&str) -> String) {
+        self.regions.rename(f);
+    }
+
+    /// Returns a sink interning the tuples pushed into it with these tables, to build facts
+    /// incrementally.
+    pub(crate) fn fact_sink(&mut self) -> FactSink<'_> {
+        FactSink::new(self)
+    }
+}
+
+/// Maps the names of atoms to atoms, when loading facts. `InternerTables` is the default
+/// implementation, but the loaders accept any other one, e.g. to share a name space with the
+/// atoms of other tools.
+pub(crate) trait AtomInterner {
+    fn intern_region(&mut self, name: &str) -> Region;
+    fn intern_loan(&mut self, name: &str) -> Loan;
+    fn intern_point(&mut self, name: &str) -> Point;
+    fn intern_variable(&mut self, name: &str) -> Variable;
+}
+
+impl AtomInterner for InternerTables {
+    fn intern_region(&mut self, name: &str) -> Region {
+        self.regions.intern(name)
+    }
+
+    fn intern_loan(&mut self, name: &str) -> Loan {
+        self.loans.intern(name)
+    }
+
+    fn intern_point(&mut self, name: &str) -> Point {
+        self.points.intern(name)
+    }
+
+    fn intern_variable(&mut self, name: &str) -> Variable {
+        self.variables.intern(name)
+    }
 }
 
 pub(crate) trait InternTo<To> {
-    fn intern(tables: &mut InternerTables, input: Self) -> To;
+    fn intern(tables: &mut dyn AtomInterner, input: Self) -> To;
 }
 
 macro_rules! intern_impl {
-    ($t:ident, $field:ident) => {
+    ($t:ident, $intern:ident) => {
         impl InternTo<$t> for &str {
-            fn intern(tables: &mut InternerTables, input: &str) -> $t {
-                tables.$field.intern(input)
+            fn intern(tables: &mut dyn AtomInterner, input: &str) -> $t {
+                tables.$intern(input)
             }
         }
     };
 }
 
-intern_impl!(Region, regions);
-intern_impl!(Loan, loans);
-intern_impl!(Point, points);
-intern_impl!(Variable, variables);
+intern_impl!(Region, intern_region);
+intern_impl!(Loan, intern_loan);
+intern_impl!(Point, intern_point);
+intern_impl!(Variable, intern_variable);
 
 impl<A, FromA, B, FromB> InternTo<(A, B)> for (FromA, FromB)
 where
     FromA: InternTo<A>,
     FromB: InternTo<B>,
 {
-    fn intern(tables: &mut InternerTables, input: (FromA, FromB)) -> (A, B) {
+    fn intern(tables: &mut dyn AtomInterner, input: (FromA, FromB)) -> (A, B) {
         let (from_a, from_b) = input;
         (FromA::intern(tables, from_a), FromB::intern(tables, from_b))
     }
@@ -94,7 +151,7 @@ where
     FromB: InternTo<B>,
     FromC: InternTo<C>,
 {
-    fn intern(tables: &mut InternerTables, input: (FromA, FromB, FromC)) -> (A, B, C) {
+    fn intern(tables: &mut dyn AtomInterner, input: (FromA, FromB, FromC)) -> (A, B, C) {
         let (from_a, from_b, from_c) = input;
         (
             FromA::intern(tables, from_a),
@@ -111,7 +168,7 @@ where
     FromC: InternTo<C>,
     FromD: InternTo<D>,
 {
-    fn intern(tables: &mut InternerTables, input: (FromA, FromB, FromC, FromD)) -> (A, B, C, D) {
+    fn intern(tables: &mut dyn AtomInterner, input: (FromA, FromB, FromC, FromD)) -> (A, B, C, D) {
         let (from_a, from_b, from_c, from_d) = input;
         (
             FromA::intern(tables, from_a),
@@ -4,15 +4,19 @@ extern crate polonius_engine;
 extern crate rustc_hash;
 extern crate structopt;
 
+mod annotate;
 mod dump;
 mod facts;
 mod intern;
+mod lsp;
 mod program;
 mod tab_delim;
 mod test;
 mod test_util;
+mod validate;
 
 pub mod cli;
+pub mod repl;
 
 extern crate log;
 extern crate petgraph;
@@ -0,0 +1,138 @@
+use crate::facts::{Loan, Point, Region, Variable};
+use crate::intern::InternerTables;
+use log::warn;
+use polonius_engine::Output;
+use rustc_hash::FxHashMap;
+
+/// A position in a source file, following the LSP conventions: lines and characters are
+/// zero-based.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Position {
+    pub(crate) line: u32,
+    pub(crate) character: u32,
+}
+
+/// The source span a point corresponds to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Span {
+    pub(crate) uri: String,
+    pub(crate) start: Position,
+    pub(crate) end: Position,
+}
+
+/// Joins the errors of the `output` with the source spans of their points, and formats them as
+/// a JSON array of LSP-compatible diagnostics: `{uri, range, message}` objects. Errors at points
+/// without a span are skipped with a warning.
+pub(crate) fn to_lsp_diagnostics(
+    output: &Output<Region, Loan, Point, Variable>,
+    spans: &FxHashMap<Point, Span>,
+    tables: &InternerTables,
+) -> String {
+    let mut diagnostics = Vec::new();
+    for (point, loan) in output.all_errors() {
+        let span = match spans.get(&point) {
+            Some(span) => span,
+            None => {
+                warn!(
+                    "no source span for point {}, skipping its error",
+                    tables.points.untern(point)
+                );
+                continue;
+            }
+        };
+
+        let message = format!(
+            "borrow {} is invalidated at {} while still live",
+            tables.loans.untern(loan),
+            tables.points.untern(point)
+        );
+        diagnostics.push(format!(
+            "{{\"uri\": {}, \"range\": {{\"start\": {}, \"end\": {}}}, \"message\": {}}}",
+            json_string(&span.uri),
+            json_position(span.start),
+            json_position(span.end),
+            json_string(&message)
+        ));
+    }
+
+    format!("[{}]", diagnostics.join(", "))
+}
+
+fn json_position(position: Position) -> String {
+    format!(
+        "{{\"line\": {}, \"character\": {}}}",
+        position.line, position.character
+    )
+}
+
+fn json_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::program;
+    use polonius_engine::Algorithm;
+
+    const PROGRAM: &str = r"
+        universal_regions { }
+
+        block B0 {
+            borrow_region_at('a, L0);
+            invalidates(L0), region_live_at('a);
+        }
+    ";
+
+    #[test]
+    fn errors_as_diagnostics() {
+        let (output, mut tables) = program::run(PROGRAM, Algorithm::Naive).unwrap();
+        let mut spans = FxHashMap::default();
+        spans.insert(
+            tables.points.intern("\"Mid(B0[1])\""),
+            Span {
+                uri: "file:///src/main.rs".to_string(),
+                start: Position {
+                    line: 2,
+                    character: 4,
+                },
+                end: Position {
+                    line: 2,
+                    character: 10,
+                },
+            },
+        );
+
+        assert_eq!(
+            to_lsp_diagnostics(&output, &spans, &tables),
+            "[{\"uri\": \"file:///src/main.rs\", \"range\": {\
+             \"start\": {\"line\": 2, \"character\": 4}, \
+             \"end\": {\"line\": 2, \"character\": 10}}, \
+             \"message\": \"borrow L0 is invalidated at \\\"Mid(B0[1])\\\" while still live\"}]"
+        );
+    }
+
+    #[test]
+    fn errors_without_spans_are_skipped() {
+        let (output, tables) = program::run(PROGRAM, Algorithm::Naive).unwrap();
+        assert!(!output.errors.is_empty());
+        assert_eq!(
+            to_lsp_diagnostics(&output, &FxHashMap::default(), &tables),
+            "[]"
+        );
+    }
+}
@@ -1,15 +1,21 @@
-#![cfg(test)]
-
 use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
 
 use polonius_parser::{
-    ir::{Effect, Fact},
+    ir::{Effect, Fact, Input, Liveness, LivenessAssertion},
     parse_input,
 };
 
+use failure::{err_msg, Error};
+use log::warn;
+use polonius_engine::{Algorithm, Output};
+
 use crate::facts::{AllFacts, Loan, Point, Region, Variable};
 use crate::intern::InternerTables;
 
+type PoloniusOutput = Output<Region, Loan, Point, Variable>;
+
 /// A structure to hold and deduplicate facts
 #[derive(Default)]
 struct Facts {
@@ -26,6 +32,11 @@ struct Facts {
     var_uses_region: BTreeSet<(Variable, Region)>,
     var_drops_region: BTreeSet<(Variable, Region)>,
     var_initialized_on_exit: BTreeSet<(Variable, Point)>,
+    borrow_reserved_at: BTreeSet<(Loan, Point)>,
+    borrow_activated_at: BTreeSet<(Loan, Point)>,
+    closure_requires: BTreeSet<(Region, Point)>,
+    loan_reborrows: BTreeSet<(Loan, Loan)>,
+    loan_borrows_var: BTreeSet<(Loan, Variable)>,
 }
 
 impl From<Facts> for AllFacts {
@@ -44,17 +55,161 @@ impl From<Facts> for AllFacts {
             var_uses_region: facts.var_uses_region.into_iter().collect(),
             var_drops_region: facts.var_drops_region.into_iter().collect(),
             var_initialized_on_exit: facts.var_initialized_on_exit.into_iter().collect(),
+            borrow_reserved_at: facts.borrow_reserved_at.into_iter().collect(),
+            borrow_activated_at: facts.borrow_activated_at.into_iter().collect(),
+            closure_requires: facts.closure_requires.into_iter().collect(),
+            loan_reborrows: facts.loan_reborrows.into_iter().collect(),
+            loan_borrows_var: facts.loan_borrows_var.into_iter().collect(),
         }
     }
 }
 
+/// Options controlling how the effects of a program are lowered to facts.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct LoweringOptions {
+    /// Whether every use of a variable also counts as a drop-use: each `var_used` fact is then
+    /// mirrored into a `var_drop_used` fact, at the same point.
+    pub(crate) use_implies_drop_use: bool,
+
+    /// Whether every loan is invalidated when the function returns: an `invalidates` fact is
+    /// then emitted, at each point without a successor, for each loan of the program. Whether a
+    /// loan is live at these points is only known once computed, so all loans are invalidated
+    /// there, and only the ones still live at the exit will be errors.
+    pub(crate) invalidate_loans_at_exit: bool,
+}
+
+/// An input program parsed by `parse_program`: its facts, and the effects of its statements
+/// they were lowered from.
+pub(crate) struct ParsedProgram {
+    pub(crate) facts: AllFacts,
+    input: Input,
+    points: Vec<(Point, Point)>,
+}
+
+impl ParsedProgram {
+    /// Returns the start and mid point of each statement, in the order of the program, with the
+    /// effects of the statement at that point.
+    pub(crate) fn point_effects(&self) -> impl Iterator<Item = (Point, &[Effect])> {
+        let statements = self
+            .input
+            .blocks
+            .iter()
+            .flat_map(|block| block.statements.iter());
+        self.points
+            .iter()
+            .zip(statements)
+            .flat_map(|(&(start, mid), statement)| {
+                vec![
+                    (start, &statement.effects_start[..]),
+                    (mid, &statement.effects[..]),
+                ]
+            })
+    }
+
+    /// Returns the liveness assertions of the program which don't hold in the `output`, computed
+    /// from its facts with the dump enabled: the regions not in `region_live_at` at the asserted
+    /// point, and the variables not in `var_live_at` there.
+    pub(crate) fn failed_assertions(
+        &self,
+        output: &PoloniusOutput,
+        tables: &mut InternerTables,
+    ) -> Vec<&LivenessAssertion> {
+        self.input
+            .assertions
+            .iter()
+            .filter(|assertion| {
+                let point = tables.points.intern(&format!("\"{}\"", assertion.point));
+                let live = match assertion.live {
+                    Liveness::Region(ref region) => {
+                        let region = tables.regions.intern(region);
+                        output.regions_live_at(point).contains(&region)
+                    }
+                    Liveness::Variable(ref variable) => {
+                        let variable = tables.variables.intern(variable);
+                        output
+                            .var_live_at
+                            .get(&point)
+                            .is_some_and(|variables| variables.contains(&variable))
+                    }
+                };
+                !live
+            })
+            .collect()
+    }
+}
+
 /// Parses an input program into a set of its facts, into the same format `rustc` outputs.
 pub(crate) fn parse_from_program(
     program: &str,
     tables: &mut InternerTables,
 ) -> Result<AllFacts, String> {
+    parse_from_program_with_options(program, tables, LoweringOptions::default())
+}
+
+/// Parses an input program into a set of its facts like `parse_from_program_with_options`,
+/// keeping the effects of its statements alongside them.
+pub(crate) fn parse_program(
+    program: &str,
+    tables: &mut InternerTables,
+    options: LoweringOptions,
+) -> Result<ParsedProgram, String> {
     let input = parse_input(program)?;
+    let facts = lower(&input, tables, options);
+    let points = input
+        .blocks
+        .iter()
+        .flat_map(|block| {
+            (0..block.statements.len()).map(move |statement_idx| (&block.name, statement_idx))
+        })
+        .map(|(block_name, statement_idx)| statement_points(block_name, statement_idx, tables))
+        .collect();
+    Ok(ParsedProgram {
+        facts,
+        input,
+        points,
+    })
+}
+
+/// Reads the input program in the file at `path`, and parses it into a set of its facts like
+/// `parse_from_program`.
+pub(crate) fn parse_from_file(path: &Path, tables: &mut InternerTables) -> Result<AllFacts, Error> {
+    let program = fs::read_to_string(path)
+        .map_err(|e| err_msg(format!("failed to read `{}`: {}", path.display(), e)))?;
+    parse_from_program(&program, tables)
+        .map_err(|e| err_msg(format!("failed to parse `{}`: {}", path.display(), e)))
+}
+
+/// Parses an input program into a set of its facts, like `parse_from_program`, but lowering
+/// its effects according to the given `options`.
+pub(crate) fn parse_from_program_with_options(
+    program: &str,
+    tables: &mut InternerTables,
+    options: LoweringOptions,
+) -> Result<AllFacts, String> {
+    parse_program(program, tables, options).map(|program| program.facts)
+}
+
+/// Interns the start and mid point of the statement `statement_idx` of the block `block_name`.
+fn statement_points(
+    block_name: &str,
+    statement_idx: usize,
+    tables: &mut InternerTables,
+) -> (Point, Point) {
+    let start = format!(
+        "\"Start({block}[{statement}])\"",
+        block = block_name,
+        statement = statement_idx
+    );
+    let mid = format!(
+        "\"Mid({block}[{statement}])\"",
+        block = block_name,
+        statement = statement_idx
+    );
+    (tables.points.intern(&start), tables.points.intern(&mid))
+}
 
+/// Lowers the effects of a parsed input program to its facts, according to the `options`.
+fn lower(input: &Input, tables: &mut InternerTables, options: LoweringOptions) -> AllFacts {
     let mut facts: Facts = Default::default();
 
     // facts: universal_region(Region)
@@ -87,19 +242,15 @@ pub(crate) fn parse_from_program(
         let block_name = &block.name;
 
         for (statement_idx, statement) in block.statements.iter().enumerate() {
-            let start = format!(
-                "\"Start({block}[{statement}])\"",
-                block = block_name,
-                statement = statement_idx
-            );
-            let mid = format!(
-                "\"Mid({block}[{statement}])\"",
-                block = block_name,
-                statement = statement_idx
-            );
-
-            let start = tables.points.intern(&start);
-            let mid = tables.points.intern(&mid);
+            for variable in statement.variables_used_and_defined() {
+                warn!(
+                    "variable {} is both used and defined by statement {} of block {}, its \
+                     liveness depends on whether each effect is at the start or mid point",
+                    variable, statement_idx, block_name
+                );
+            }
+
+            let (start, mid) = statement_points(block_name, statement_idx, tables);
 
             // facts: cfg_edge(Point, Point)
             {
@@ -157,7 +308,7 @@ pub(crate) fn parse_from_program(
 
                     Effect::Fact(ref fact) => {
                         // Manually specified facts
-                        emit_fact(&mut facts, fact, mid, tables)
+                        emit_fact(&mut facts, fact, mid, tables, options)
                     }
                 };
             }
@@ -165,16 +316,55 @@ pub(crate) fn parse_from_program(
             // commonly used to emit manual `invalidates` at Start points, like some rustc features do
             for effect in &statement.effects_start {
                 if let Effect::Fact(ref fact) = effect {
-                    emit_fact(&mut facts, fact, start, tables);
+                    emit_fact(&mut facts, fact, start, tables, options);
                 }
             }
         }
     }
 
-    Ok(facts.into())
+    if options.invalidate_loans_at_exit {
+        // Each statement has an edge between its Start and Mid points, so all the points
+        // appear in `cfg_edge`.
+        let exits: BTreeSet<Point> = facts
+            .cfg_edge
+            .iter()
+            .map(|&(_, q)| q)
+            .filter(|&point| !facts.cfg_edge.iter().any(|&(p, _)| p == point))
+            .collect();
+        let loans: BTreeSet<Loan> = facts
+            .borrow_region
+            .iter()
+            .map(|&(_, loan, _)| loan)
+            .collect();
+        for &exit in &exits {
+            facts
+                .invalidates
+                .extend(loans.iter().map(|&loan| (exit, loan)));
+        }
+    }
+
+    facts.into()
+}
+
+/// Parses an input program, and computes its `Output` with the given algorithm, in verbose
+/// mode. Returns the `InternerTables` used to intern the facts, to map the output back to names.
+pub(crate) fn run(
+    program: &str,
+    algorithm: Algorithm,
+) -> Result<(PoloniusOutput, InternerTables), Error> {
+    let mut tables = InternerTables::new();
+    let facts = parse_from_program(program, &mut tables).map_err(err_msg)?;
+    let output = Output::compute(&facts, algorithm, true);
+    Ok((output, tables))
 }
 
-fn emit_fact(facts: &mut Facts, fact: &Fact, point: Point, tables: &mut InternerTables) {
+fn emit_fact(
+    facts: &mut Facts,
+    fact: &Fact,
+    point: Point,
+    tables: &mut InternerTables,
+    options: LoweringOptions,
+) {
     match fact {
         // facts: borrow_region(Region, Loan, Point)
         Fact::BorrowRegionAt {
@@ -204,6 +394,46 @@ fn emit_fact(facts: &mut Facts, fact: &Fact, point: Point, tables: &mut Interner
             facts.killed.insert((loan, point));
         }
 
+        // facts: borrow_reserved_at(Loan, Point)
+        Fact::BorrowReservedAt { ref loan } => {
+            let loan = tables.loans.intern(loan);
+            facts.borrow_reserved_at.insert((loan, point));
+        }
+
+        // facts: borrow_activated_at(Loan, Point)
+        Fact::BorrowActivatedAt { ref loan } => {
+            let loan = tables.loans.intern(loan);
+            facts.borrow_activated_at.insert((loan, point));
+        }
+
+        // facts: closure_requires(Region, Point)
+        Fact::ClosureRequires { ref region } => {
+            let region = tables.regions.intern(region);
+            facts.closure_requires.insert((region, point));
+        }
+
+        // facts: loan_reborrows(Loan, Loan)
+        Fact::LoanReborrows {
+            ref loan,
+            ref parent,
+        } => {
+            // loan_reborrows: the point where the reborrow is declared doesn't matter
+            let loan = tables.loans.intern(loan);
+            let parent = tables.loans.intern(parent);
+            facts.loan_reborrows.insert((loan, parent));
+        }
+
+        // facts: loan_borrows_var(Loan, Variable)
+        Fact::LoanBorrowsVar {
+            ref loan,
+            ref variable,
+        } => {
+            // loan_borrows_var: like loan_reborrows, the point doesn't matter
+            let loan = tables.loans.intern(loan);
+            let variable = tables.variables.intern(variable);
+            facts.loan_borrows_var.insert((loan, variable));
+        }
+
         // facts: invalidates(Point, Loan)
         Fact::Invalidates { ref loan } => {
             let loan = tables.loans.intern(loan);
@@ -231,6 +461,11 @@ fn emit_fact(facts: &mut Facts, fact: &Fact, point: Point, tables: &mut Interner
             // var_used: a variable is used here
             let variable = tables.variables.intern(variable);
             facts.var_used.insert((variable, point));
+
+            // var_drop_used: with this policy, the use is also a drop-use
+            if options.use_implies_drop_use {
+                facts.var_drop_used.insert((variable, point));
+            }
         }
     };
 }
@@ -387,4 +622,44 @@ mod tests {
             .cfg_edge
             .contains(&make_edge("\"Mid(B0[1])\"", "\"Start(B1[0])\"")));
     }
+
+    #[test]
+    fn statement_point_effects() {
+        let program = r"
+            universal_regions { }
+
+            block B0 {
+                invalidates(L0) / kill(L0);
+                goto B1;
+            }
+
+            block B1 {
+                use('a), var_used(V0);
+            }
+        ";
+
+        let mut tables = InternerTables::new();
+        let parsed = parse_program(program, &mut tables, LoweringOptions::default())
+            .expect("Parsing failure");
+        assert_eq!(parsed.facts.killed.len(), 1);
+
+        let effects: Vec<_> = parsed
+            .point_effects()
+            .map(|(point, effects)| {
+                let effects: Vec<_> = effects.iter().map(ToString::to_string).collect();
+                (tables.points.untern(point).to_string(), effects.join(", "))
+            })
+            .collect();
+        let expected = [
+            ("\"Start(B0[0])\"", "invalidates(L0)"),
+            ("\"Mid(B0[0])\"", "kill(L0)"),
+            ("\"Start(B1[0])\"", ""),
+            ("\"Mid(B1[0])\"", "use('a), var_used(V0)"),
+        ];
+        let expected: Vec<_> = expected
+            .iter()
+            .map(|&(point, effects)| (point.to_string(), effects.to_string()))
+            .collect();
+        assert_eq!(effects, expected);
+    }
 }
@@ -0,0 +1,120 @@
+use crate::facts::Point;
+use crate::intern::InternerTables;
+use crate::program;
+use failure::Error;
+use polonius_engine::Algorithm;
+use rustc_hash::FxHashMap;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, Write};
+
+/// Reads programs from stdin, each ended by a blank line or EOF, and prints the errors and
+/// liveness computed for each of them.
+pub fn main() -> Result<(), Error> {
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    loop {
+        print!("> ");
+        io::stdout().flush()?;
+
+        let mut program = String::new();
+        let mut eof = true;
+        for line in &mut lines {
+            let line = line?;
+            if line.trim().is_empty() {
+                if program.trim().is_empty() {
+                    continue;
+                }
+                eof = false;
+                break;
+            }
+            program.push_str(&line);
+            program.push('\n');
+        }
+
+        if !program.trim().is_empty() {
+            print!("{}", evaluate(&program));
+        }
+        if eof {
+            return Ok(());
+        }
+    }
+}
+
+/// Computes the output of the program, and renders its errors and the regions live at each
+/// point with their names.
+fn evaluate(program: &str) -> String {
+    let (output, tables) = match program::run(program, Algorithm::Naive) {
+        Ok(result) => result,
+        Err(e) => return format!("{}\n", e),
+    };
+
+    let mut rendered = String::new();
+    render_by_point(&mut rendered, "errors", &output.errors, &tables, |loan| {
+        tables.loans.untern(loan)
+    });
+    render_by_point(
+        &mut rendered,
+        "live regions",
+        &output.region_live_at,
+        &tables,
+        |region| tables.regions.untern(region),
+    );
+    rendered
+}
+
+fn render_by_point<'a, T: Copy + Ord>(
+    rendered: &mut String,
+    title: &str,
+    by_point: &FxHashMap<Point, Vec<T>>,
+    tables: &'a InternerTables,
+    untern: impl Fn(T) -> &'a str,
+) {
+    writeln!(rendered, "{}:", title).unwrap();
+    if by_point.is_empty() {
+        writeln!(rendered, "  none").unwrap();
+    }
+
+    let mut points: Vec<&Point> = by_point.keys().collect();
+    points.sort();
+    for point in points {
+        let mut values = by_point[point].clone();
+        values.sort();
+        let values: Vec<&str> = values.into_iter().map(&untern).collect();
+        writeln!(
+            rendered,
+            "  {}: {}",
+            tables.points.untern(*point),
+            values.join(", ")
+        )
+        .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evaluate_program() {
+        let program = r"
+            universal_regions { }
+
+            block B0 {
+                borrow_region_at('a, L0);
+                invalidates(L0), region_live_at('a);
+            }
+        ";
+
+        assert_eq!(
+            evaluate(program),
+            "errors:\n  \"Mid(B0[1])\": L0\n\
+             live regions:\n  \"Start(B0[1])\": 'a\n  \"Mid(B0[1])\": 'a\n"
+        );
+    }
+
+    #[test]
+    fn evaluate_invalid_program() {
+        assert!(evaluate("block B0 {").starts_with("Polonius parse error"));
+    }
+}
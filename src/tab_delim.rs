@@ -1,83 +1,541 @@
-use crate::facts::AllFacts;
-use crate::intern::{InternTo, InternerTables};
+use crate::dump::Atom;
+use crate::facts::{AllFacts, Point};
+use crate::intern::{AtomInterner, InternTo, InternerTables};
+use crate::lsp::{Position, Span};
 use log::{error, warn};
-use std::fs::File;
+use polonius_engine::{for_each_relation, FACTS_SCHEMA_VERSION};
+use rustc_hash::FxHashMap;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs::{self, File};
 use std::io::{self, prelude::*, ErrorKind};
 use std::path::Path;
 use std::process;
+use std::str;
 
 trait FromTabDelimited<'input>: Sized {
     fn parse(
-        tables: &mut InternerTables,
+        tables: &mut dyn AtomInterner,
         inputs: &mut dyn Iterator<Item = &'input str>,
     ) -> Option<Self>;
 }
 
+/// The names of the relations of `AllFacts`, like their fact files without the `.facts` extension.
+const RELATIONS: &[&str] = &{
+    macro_rules! relation_names {
+        ($($t:ident,)*) => {
+            [$(stringify!($t),)*]
+        };
+    }
+    for_each_relation!(relation_names!())
+};
+
+/// The relations whose fact file may be missing, which are then loaded as empty. Two-phase
+/// borrows, closure requirements, reborrows and borrowed variables are not described by every
+/// version of rustc, and some datasets have no drop facts: the drop-liveness is then skipped.
+/// Without `region_live_at` facts, the region liveness is computed from the variables.
+const OPTIONAL_RELATIONS: &[&str] = &[
+    "region_live_at",
+    "borrow_reserved_at",
+    "borrow_activated_at",
+    "closure_requires",
+    "loan_reborrows",
+    "loan_borrows_var",
+    "var_drop_used",
+    "var_drops_region",
+];
+
+/// Loads each relation of `AllFacts` with `$load`, an expression evaluating to the
+/// `io::Result` of loading the relation from the file `$filename`.
+///
+/// The names of the `OPTIONAL_RELATIONS` whose file is missing are pushed to the `Vec`
+/// `$missing`, if given, and these relations are empty.
+macro_rules! load_facts {
+    (|$filename:ident| $load:expr) => {{
+        let mut missing = Vec::new();
+        load_facts!(|$filename| $load; missing)
+    }};
+    (|$filename:ident| $load:expr; $missing:ident) => {
+        for_each_relation!(load_facts!(@relations |$filename| $load; $missing;))
+    };
+    (@relations |$filename:ident| $load:expr; $missing:ident; $($t:ident,)*) => {
+        Ok(AllFacts {
+            $(
+                $t: {
+                    let $filename = format!("{}.facts", stringify!($t));
+                    match $load {
+                        Ok(facts) => facts,
+                        Err(e) => match (e.kind(), stringify!($t)) {
+                            (ErrorKind::NotFound, "region_live_at") => {
+                                warn!("couldn't find fact file {:?}: {}", $filename, e);
+                                $missing.push(stringify!($t));
+                                Vec::default()
+                            }
+                            (ErrorKind::NotFound, relation)
+                                if OPTIONAL_RELATIONS.contains(&relation) =>
+                            {
+                                $missing.push(stringify!($t));
+                                Vec::default()
+                            }
+                            _ => return Err(e),
+                        },
+                    }
+                },
+            )*
+        })
+    };
+}
+
 pub(crate) fn load_tab_delimited_facts(
-    tables: &mut InternerTables,
+    tables: &mut dyn AtomInterner,
     facts_dir: &Path,
 ) -> io::Result<AllFacts> {
-    macro_rules! load_facts {
-        (from ($tables:expr, $facts_dir:expr) load AllFacts { $($t:ident,)* }) => {
-            Ok(AllFacts {
-                $(
-                    $t: {
-                        let filename = format!("{}.facts", stringify!($t));
-                        let facts_file = $facts_dir.join(&filename);
-
-                        match load_tab_delimited_file($tables, &facts_file) {
-                            Ok(facts) => facts,
-                            Err(e) => {
-                                match (e.kind(), filename.as_ref()) {
-                                    (ErrorKind::NotFound, "region_live_at.facts") => {
-
-                                        warn!("couldn't find fact file {:?}", facts_file);
-                                        Vec::default()},
-                                    _ => return Err(e)
-}
-
-                                },
+    load_tab_delimited_facts_with_prefix(tables, facts_dir, "")
+}
+
+/// Loads the facts in `facts_dir` like `load_tab_delimited_facts`, but prefixes every atom name
+/// with `prefix` before interning it. Atoms are interned as they are written in the fact files,
+/// quotes included, so the prefix goes outside of the quotes: with the prefix `fnA:`, the region
+/// `"\'_#2r"` becomes `fnA:"\'_#2r"`. Loading the facts of multiple functions, each with its own
+/// prefix, into the same `InternerTables` guarantees that their atoms never collide.
+///
+/// Note that all atoms are prefixed, including universal regions: a universal region shared
+/// between functions, like `'static`, becomes a distinct region per function.
+pub(crate) fn load_tab_delimited_facts_with_prefix(
+    tables: &mut dyn AtomInterner,
+    facts_dir: &Path,
+    prefix: &str,
+) -> io::Result<AllFacts> {
+    warn_on_schema_mismatch(facts_dir);
+    load_facts!(|filename| load_tab_delimited_file(tables, &facts_dir.join(&filename), prefix))
+}
+
+/// Loads the facts in `facts_dir` like `load_tab_delimited_facts`, and also returns the names
+/// of the optional relations whose file is missing, like `var_drop_used`: these relations are
+/// empty, and the phases of the computation depending on them are skipped.
+pub(crate) fn load_tab_delimited_facts_with_missing(
+    tables: &mut dyn AtomInterner,
+    facts_dir: &Path,
+) -> io::Result<(AllFacts, Vec<&'static str>)> {
+    warn_on_schema_mismatch(facts_dir);
+    let mut missing = Vec::new();
+    let facts: io::Result<AllFacts> = load_facts!(|filename| load_tab_delimited_file(
+        tables,
+        &facts_dir.join(&filename),
+        ""
+    ); missing);
+    Ok((facts?, missing))
+}
+
+/// The file of a facts directory holding the `FACTS_SCHEMA_VERSION` its facts were written for.
+const SCHEMA_FILE: &str = "schema.txt";
+
+/// Reads the schema version of the facts in `facts_dir`, from its optional `schema.txt` file.
+/// Returns `None` if there is no such file, like in the facts extracted by rustc.
+pub(crate) fn schema_version(facts_dir: &Path) -> io::Result<Option<u32>> {
+    match fs::read_to_string(facts_dir.join(SCHEMA_FILE)) {
+        Ok(contents) => contents.trim().parse().map(Some).map_err(|e| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid schema version {:?}: {}", contents.trim(), e),
+            )
+        }),
+        Err(ref e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Warns if the facts in `facts_dir` were written for another schema version than the engine's:
+/// the relations they lack are still loaded as empty, which can give surprising results.
+fn warn_on_schema_mismatch(facts_dir: &Path) {
+    match schema_version(facts_dir) {
+        Ok(Some(version)) if version != FACTS_SCHEMA_VERSION => {
+            warn!(
+                "the facts in {:?} are for schema version {}, but version {} is expected",
+                facts_dir, version, FACTS_SCHEMA_VERSION
+            );
+        }
+        Ok(_) => {}
+        Err(e) => {
+            warn!("couldn't read the schema version of {:?}: {}", facts_dir, e);
+        }
+    }
+}
+
+/// Writes the facts as tab-delimited files in `facts_dir`, in the format read by
+/// `load_tab_delimited_facts`, along with the schema version they are written for.
+pub(crate) fn write_tab_delimited_facts(
+    facts: &AllFacts,
+    tables: &InternerTables,
+    facts_dir: &Path,
+) -> io::Result<()> {
+    fs::create_dir_all(facts_dir)?;
+    fs::write(
+        facts_dir.join(SCHEMA_FILE),
+        format!("{}\n", FACTS_SCHEMA_VERSION),
+    )?;
+
+    macro_rules! write_facts {
+        ($($t:ident,)*) => {
+            $(
+                let path = facts_dir.join(format!("{}.facts", stringify!($t)));
+                let mut file = File::create(path)?;
+                for row in &facts.$t {
+                    writeln!(file, "{}", row.to_row(tables).join("\t"))?;
+                }
+            )*
+        };
+    }
+
+    for_each_relation!(write_facts!());
+
+    Ok(())
+}
+
+/// Counts the lines of each relation's file in `facts_dir`, without parsing or interning them,
+/// to estimate the size of its facts. The relations whose file is optional when loading, like
+/// `region_live_at`, have no lines if their file is missing.
+pub(crate) fn count_lines(facts_dir: &Path) -> io::Result<BTreeMap<String, usize>> {
+    let mut counts = BTreeMap::new();
+    for &relation in RELATIONS {
+        let path = facts_dir.join(format!("{}.facts", relation));
+        let count = match fs::read(&path) {
+            // A last line without a trailing newline is still a line.
+            Ok(contents) => {
+                let newlines = contents.iter().filter(|&&byte| byte == b'\n').count();
+                newlines + (!contents.is_empty() && !contents.ends_with(b"\n")) as usize
+            }
+            Err(ref e)
+                if e.kind() == ErrorKind::NotFound && OPTIONAL_RELATIONS.contains(&relation) =>
+            {
+                0
+            }
+            Err(e) => return Err(e),
+        };
+        counts.insert(relation.to_string(), count);
+    }
+    Ok(counts)
+}
+
+/// Puts the facts in a canonical form: the tuples of each relation are deduplicated, and sorted
+/// by the names of their atoms, so that the order of the input lines and of the atoms' interning
+/// doesn't matter.
+pub(crate) fn normalize_facts(facts: &mut AllFacts, tables: &InternerTables) {
+    fn normalize<Row: ToTabDelimited + PartialEq>(rows: &mut Vec<Row>, tables: &InternerTables) {
+        rows.sort_by_cached_key(|row| row.to_row(tables));
+        rows.dedup();
+    }
+
+    macro_rules! normalize_facts {
+        ($($t:ident,)*) => {
+            $(
+                normalize(&mut facts.$t, tables);
+            )*
+        };
+    }
+
+    for_each_relation!(normalize_facts!());
+}
+
+/// Loads the facts of `function` from a tar archive of `nll-facts` directories, without
+/// extracting it to disk: the facts are read from the `<function>/<relation>.facts` entries,
+/// wherever the `<function>` directory is in the archive. Only the ustar format is supported,
+/// so paths cannot be longer than 255 bytes.
+pub(crate) fn load_from_tar(
+    tables: &mut dyn AtomInterner,
+    reader: impl Read,
+    function: &str,
+) -> io::Result<AllFacts> {
+    let files = read_tar_files(reader, function)?;
+    load_facts!(|filename| match files.get(&filename) {
+        Some(contents) => parse_tab_delimited(tables, &contents[..], &filename, ""),
+        None => Err(io::Error::new(
+            ErrorKind::NotFound,
+            format!("no `{}` entry for `{}` in the archive", filename, function),
+        )),
+    })
+}
+
+/// Loads the facts from a single combined file, where each line is the name of a relation
+/// followed by the columns of one of its tuples, e.g. `killed\tbw0\t"Mid(bb0[1])"`. The
+/// relations without tuples can be omitted.
+pub(crate) fn load_combined(tables: &mut dyn AtomInterner, path: &Path) -> io::Result<AllFacts> {
+    let file = io::BufReader::new(File::open(path)?);
+    let mut relations: FxHashMap<String, Vec<u8>> = FxHashMap::default();
+    for line in file.lines() {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        let mut columns = line.splitn(2, '\t');
+        let relation = columns.next().unwrap();
+        let contents = relations.entry(format!("{}.facts", relation)).or_default();
+        contents.extend_from_slice(columns.next().unwrap_or("").as_bytes());
+        contents.push(b'\n');
+    }
+
+    let all_facts: io::Result<AllFacts> = load_facts!(|filename| {
+        let contents = relations.remove(&filename).unwrap_or_default();
+        let source = format!("{} (`{}` tuples)", path.display(), filename);
+        parse_tab_delimited(tables, &contents[..], source, "")
+    });
+
+    match relations.keys().next() {
+        Some(filename) => Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "unknown relation `{}` in `{}`",
+                filename.trim_end_matches(".facts"),
+                path.display()
+            ),
+        )),
+        None => all_facts,
+    }
+}
+
+/// Builds facts incrementally, from tuples arriving one at a time, e.g. from a socket: each tuple
+/// is interned as soon as it is pushed, and appended to the facts returned by `finish`. Created
+/// by `InternerTables::fact_sink`.
+///
+/// The sink borrows its tables mutably, for as long as the facts are being built: as the tables
+/// must be `Send`, it can be moved to the thread receiving the tuples, but not shared between
+/// threads. Producers on other threads should send their tuples to this single consumer, e.g.
+/// over a channel.
+pub(crate) struct FactSink<'tables> {
+    tables: &'tables mut (dyn AtomInterner + Send),
+    facts: AllFacts,
+}
+
+impl<'tables> FactSink<'tables> {
+    pub(crate) fn new(tables: &'tables mut (dyn AtomInterner + Send)) -> Self {
+        FactSink {
+            tables,
+            facts: AllFacts::default(),
+        }
+    }
+
+    /// Interns the `columns` of a tuple of `relation`, named like its fact file without the
+    /// `.facts` extension, and appends it to the facts. The atoms are interned in the order the
+    /// tuples arrive.
+    pub(crate) fn push(&mut self, relation: &str, columns: &[&str]) -> io::Result<()> {
+        macro_rules! push_fact {
+            ($($t:ident,)*) => {
+                match relation {
+                    $(
+                        stringify!($t) => {
+                            let mut inputs = columns.iter().cloned();
+                            let row = FromTabDelimited::parse(self.tables, &mut inputs);
+                            match row {
+                                Some(row) if inputs.next().is_none() => {
+                                    self.facts.$t.push(row);
+                                    Ok(())
+                                }
+                                _ => Err(io::Error::new(
+                                    ErrorKind::InvalidData,
+                                    format!(
+                                        "invalid `{}` tuple: {}",
+                                        relation,
+                                        columns.join("\t")
+                                    ),
+                                )),
+                            }
                         }
-                    },
-                )*
-            })
+                    )*
+                    _ => Err(io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!("unknown relation `{}`", relation),
+                    )),
+                }
+            };
         }
+
+        for_each_relation!(push_fact!())
     }
 
-    load_facts! {
-        from (tables, facts_dir) load AllFacts {
-            borrow_region,
-            universal_region,
-            cfg_edge,
-            killed,
-            outlives,
-            region_live_at,
-            invalidates,
-            var_defined,
-            var_used,
-            var_drop_used,
-            var_uses_region,
-            var_drops_region,
-            var_initialized_on_exit,
+    /// Returns the facts pushed so far.
+    pub(crate) fn finish(self) -> AllFacts {
+        self.facts
+    }
+}
+
+/// Reads the regular files of a tar archive which are directly in a `dir_name` directory, and
+/// returns their contents by file name.
+fn read_tar_files(mut reader: impl Read, dir_name: &str) -> io::Result<FxHashMap<String, Vec<u8>>> {
+    fn field(bytes: &[u8]) -> io::Result<&str> {
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        str::from_utf8(&bytes[..end])
+            .map_err(|_| io::Error::new(ErrorKind::InvalidData, "invalid tar header"))
+    }
+
+    let mut files = FxHashMap::default();
+    loop {
+        let mut header = [0; 512];
+        reader.read_exact(&mut header)?;
+
+        // The archive ends with zero-filled blocks.
+        if header.iter().all(|&b| b == 0) {
+            return Ok(files);
+        }
+
+        let name = field(&header[0..100])?;
+        let path = match field(&header[345..500])? {
+            "" => name.to_string(),
+            prefix => format!("{}/{}", prefix, name),
+        };
+        let size = usize::from_str_radix(field(&header[124..136])?.trim(), 8)
+            .map_err(|_| io::Error::new(ErrorKind::InvalidData, "invalid tar entry size"))?;
+
+        // The contents are padded to a multiple of 512 bytes.
+        let mut contents = vec![0; size.div_ceil(512) * 512];
+        reader.read_exact(&mut contents)?;
+        contents.truncate(size);
+
+        let is_regular_file = header[156] == b'0' || header[156] == 0;
+        let path = Path::new(&path);
+        if is_regular_file && path.parent().and_then(Path::file_name) == Some(dir_name.as_ref()) {
+            let file_name = path.file_name().unwrap().to_string_lossy().into_owned();
+            files.insert(file_name, contents);
         }
     }
 }
 
-fn load_tab_delimited_file<Row>(tables: &mut InternerTables, path: &Path) -> io::Result<Vec<Row>>
+/// Loads the source lines of the points from the optional `point_line.facts` file in
+/// `facts_dir`. Each line contains a point and its line number. If the file is missing, there are
+/// no lines.
+pub(crate) fn load_point_lines(
+    tables: &mut dyn AtomInterner,
+    facts_dir: &Path,
+) -> io::Result<FxHashMap<Point, u32>> {
+    let path = facts_dir.join("point_line.facts");
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(FxHashMap::default()),
+        Err(e) => return Err(e),
+    };
+
+    let mut lines = FxHashMap::default();
+    for (index, line) in io::BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        let columns: Vec<&str> = line.split('\t').collect();
+        match columns[..] {
+            [point, number] => match number.parse::<u32>() {
+                Ok(number) => {
+                    lines.insert(tables.intern_point(point), number);
+                }
+                Err(_) => {
+                    error!("error parsing line {} of `{}`", index + 1, path.display());
+                    process::exit(1);
+                }
+            },
+            _ => {
+                error!("error parsing line {} of `{}`", index + 1, path.display());
+                process::exit(1);
+            }
+        }
+    }
+
+    Ok(lines)
+}
+
+/// Loads the source spans of the points from the optional `point_spans.facts` file in
+/// `facts_dir`. Each line contains a point, the URI of its source file, and the zero-based line
+/// and character of the start and end of its span. If the file is missing, there are no spans.
+pub(crate) fn load_point_spans(
+    tables: &mut dyn AtomInterner,
+    facts_dir: &Path,
+) -> io::Result<FxHashMap<Point, Span>> {
+    let path = facts_dir.join("point_spans.facts");
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(FxHashMap::default()),
+        Err(e) => return Err(e),
+    };
+
+    let mut spans = FxHashMap::default();
+    for (index, line) in io::BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        let columns: Vec<&str> = line.split('\t').collect();
+        let parse_u32 = |column: &str| column.parse::<u32>().ok();
+        let span = match columns[..] {
+            [point, uri, start_line, start_character, end_line, end_character] => {
+                match (
+                    parse_u32(start_line),
+                    parse_u32(start_character),
+                    parse_u32(end_line),
+                    parse_u32(end_character),
+                ) {
+                    (
+                        Some(start_line),
+                        Some(start_character),
+                        Some(end_line),
+                        Some(end_character),
+                    ) => {
+                        let span = Span {
+                            uri: uri.to_string(),
+                            start: Position {
+                                line: start_line,
+                                character: start_character,
+                            },
+                            end: Position {
+                                line: end_line,
+                                character: end_character,
+                            },
+                        };
+                        Some((tables.intern_point(point), span))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+
+        match span {
+            Some((point, span)) => {
+                spans.insert(point, span);
+            }
+            None => {
+                error!("error parsing line {} of `{}`", index + 1, path.display());
+                process::exit(1);
+            }
+        }
+    }
+
+    Ok(spans)
+}
+
+fn load_tab_delimited_file<Row>(
+    tables: &mut dyn AtomInterner,
+    path: &Path,
+    prefix: &str,
+) -> io::Result<Vec<Row>>
 where
     Row: for<'input> FromTabDelimited<'input>,
 {
     let file = File::open(path)?;
+    parse_tab_delimited(tables, io::BufReader::new(file), path.display(), prefix)
+}
 
-    io::BufReader::new(file)
+fn parse_tab_delimited<Row>(
+    tables: &mut dyn AtomInterner,
+    reader: impl BufRead,
+    source: impl Display,
+    prefix: &str,
+) -> io::Result<Vec<Row>>
+where
+    Row: for<'input> FromTabDelimited<'input>,
+{
+    reader
         .lines()
         .enumerate()
         .map(|(index, line)| {
-            let line = line?;
+            let mut line = line?;
+            if !prefix.is_empty() {
+                line = format!("{}{}", prefix, line.replace('\t', &format!("\t{}", prefix)));
+            }
             let mut columns = line.split('\t');
             let row = match FromTabDelimited::parse(tables, &mut columns) {
                 None => {
-                    error!("error parsing line {} of `{}`", index + 1, path.display());
+                    error!("error parsing line {} of `{}`", index + 1, source);
                     process::exit(1);
                 }
 
@@ -85,7 +543,7 @@ where
             };
 
             if columns.next().is_some() {
-                error!("extra data on line {} of `{}`", index + 1, path.display());
+                error!("extra data on line {} of `{}`", index + 1, source);
                 process::exit(1);
             }
 
@@ -94,12 +552,41 @@ where
         .collect()
 }
 
+trait ToTabDelimited {
+    fn to_row<'a>(&self, tables: &'a InternerTables) -> Vec<&'a str>;
+}
+
+impl<A: Atom> ToTabDelimited for A {
+    fn to_row<'a>(&self, tables: &'a InternerTables) -> Vec<&'a str> {
+        vec![A::table(tables).untern(*self)]
+    }
+}
+
+impl<A: Atom, B: Atom> ToTabDelimited for (A, B) {
+    fn to_row<'a>(&self, tables: &'a InternerTables) -> Vec<&'a str> {
+        vec![
+            A::table(tables).untern(self.0),
+            B::table(tables).untern(self.1),
+        ]
+    }
+}
+
+impl<A: Atom, B: Atom, C: Atom> ToTabDelimited for (A, B, C) {
+    fn to_row<'a>(&self, tables: &'a InternerTables) -> Vec<&'a str> {
+        vec![
+            A::table(tables).untern(self.0),
+            B::table(tables).untern(self.1),
+            C::table(tables).untern(self.2),
+        ]
+    }
+}
+
 impl<'input, T> FromTabDelimited<'input> for T
 where
     &'input str: InternTo<T>,
 {
     fn parse(
-        tables: &mut InternerTables,
+        tables: &mut dyn AtomInterner,
         inputs: &mut dyn Iterator<Item = &'input str>,
     ) -> Option<Self> {
         let input = inputs.next()?;
@@ -113,7 +600,7 @@ where
     B: FromTabDelimited<'input>,
 {
     fn parse(
-        tables: &mut InternerTables,
+        tables: &mut dyn AtomInterner,
         inputs: &mut dyn Iterator<Item = &'input str>,
     ) -> Option<Self> {
         let a = A::parse(tables, inputs)?;
@@ -129,7 +616,7 @@ where
     C: FromTabDelimited<'input>,
 {
     fn parse(
-        tables: &mut InternerTables,
+        tables: &mut dyn AtomInterner,
         inputs: &mut dyn Iterator<Item = &'input str>,
     ) -> Option<Self> {
         let a = A::parse(tables, inputs)?;
@@ -147,7 +634,7 @@ where
     D: FromTabDelimited<'input>,
 {
     fn parse(
-        tables: &mut InternerTables,
+        tables: &mut dyn AtomInterner,
         inputs: &mut dyn Iterator<Item = &'input str>,
     ) -> Option<Self> {
         let a = A::parse(tables, inputs)?;
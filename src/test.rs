@@ -1,15 +1,27 @@
 #![cfg(test)]
 
+use crate::dump;
 use crate::facts::{AllFacts, Loan, Point, Region, Variable};
 use crate::intern;
-use crate::program::parse_from_program;
+use crate::program::{self, parse_from_program, parse_from_program_with_options, LoweringOptions};
 use crate::tab_delim;
-use crate::test_util::assert_equal;
+use crate::test_util::{
+    assert_algorithm_errors, assert_cfg, assert_equal, assert_errors,
+    assert_opt_matches_naive_or_dump, assert_opt_subset_matches_naive, check_random_facts,
+    fixture_dir, load_fixture, FactsSpec,
+};
 use failure::Error;
-use polonius_engine::{Algorithm, Output};
+use polonius_engine::{
+    Algorithm, Atom, ComputeOptions, CostEstimate, ErrorKind, JoinOrder, Output, RegionMapping,
+    Trace, TraceFormat, UniversalLivenessPolicy, FACTS_SCHEMA_VERSION,
+};
 use rustc_hash::FxHashMap;
+use std::cell::Cell;
 use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::panic;
 use std::path::Path;
+use std::sync::{Arc, Mutex};
 
 fn test_facts(all_facts: &AllFacts, algorithms: &[Algorithm]) {
     let naive = Output::compute(all_facts, Algorithm::Naive, true);
@@ -55,16 +67,47 @@ fn test_facts(all_facts: &AllFacts, algorithms: &[Algorithm]) {
     assert_equal(&naive.errors, &opt.errors);
 }
 
-fn test_fn(dir_name: &str, fn_name: &str, algorithm: Algorithm) -> Result<(), Error> {
-    let facts_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
-        .join("inputs")
-        .join(dir_name)
-        .join("nll-facts")
-        .join(fn_name);
-    println!("facts_dir = {:?}", facts_dir);
-    let tables = &mut intern::InternerTables::new();
-    let all_facts = tab_delim::load_tab_delimited_facts(tables, &facts_dir)?;
-    Ok(test_facts(&all_facts, &[algorithm]))
+/// Tests a known divergence between the naive and optimized variants: they must still agree on
+/// `borrow_live_at` and `errors`, but the optimized variants only compute a part of the
+/// transitive closure of `restricts`. At each point, the optimized `restricts` must then be
+/// contained in the naive one, and strictly smaller somewhere.
+///
+/// If this starts failing because the `restricts` relations are equal, the divergence has been
+/// fixed, and the test can use `test_facts` instead.
+fn test_facts_expect_divergence(all_facts: &AllFacts, algorithms: &[Algorithm]) {
+    test_facts(all_facts, algorithms);
+
+    let naive = Output::compute(all_facts, Algorithm::Naive, true);
+    for &optimized_algorithm in algorithms {
+        println!("Algorithm {:?}", optimized_algorithm);
+        let opt = Output::compute(all_facts, optimized_algorithm, true);
+
+        for (point, opt_restricts) in &opt.restricts {
+            let naive_restricts = naive.restricts_at(*point);
+            for (region, opt_loans) in opt_restricts {
+                let naive_loans = naive_restricts.get(region).cloned().unwrap_or_default();
+                assert!(
+                    opt_loans.is_subset(&naive_loans),
+                    "{:?} had `restricts({:?}, {:?}, {:?})` which naive did not",
+                    optimized_algorithm,
+                    region,
+                    opt_loans,
+                    point,
+                );
+            }
+        }
+
+        assert!(
+            naive.restricts != opt.restricts,
+            "{:?} computed the same `restricts` as naive: the divergence has been fixed",
+            optimized_algorithm,
+        );
+    }
+}
+
+fn test_fn(dir_name: &str, fn_name: &str, algorithm: Algorithm) {
+    let (all_facts, _) = load_fixture(dir_name, fn_name);
+    test_facts(&all_facts, &[algorithm]);
 }
 
 macro_rules! tests {
@@ -74,8 +117,8 @@ macro_rules! tests {
                 use super::*;
 
                 #[test]
-                fn datafrog_opt() -> Result<(), Error> {
-                    test_fn($dir, $fn, Algorithm::DatafrogOpt)
+                fn datafrog_opt() {
+                    test_fn($dir, $fn, Algorithm::DatafrogOpt);
                 }
             }
         )*
@@ -89,50 +132,88 @@ tests! {
     vec_push_ref_foo3("vec-push-ref", "foo3"),
 }
 
+/// The fixtures which are not checked by `all_inputs`: either a whole `inputs/<dir>`, or a
+/// single function's `<dir>/<fn>`, because of known divergences between the algorithms
+/// or of outdated facts.
+const ALL_INPUTS_IGNORED: &[&str] = &[
+    // These fixtures predate the `var_initialized_on_exit` relation.
+    "blog-exmple",
+    "polonius",
+];
+
+/// Checks every fixture in `inputs/`, except the ones in `ALL_INPUTS_IGNORED`, so that new
+/// fixtures are tested without having to be added to the `tests!` macro.
 #[test]
-fn test_insensitive_errors() -> Result<(), Error> {
-    let facts_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
-        .join("inputs")
-        .join("issue-47680")
-        .join("nll-facts")
-        .join("main");
-    println!("facts_dir = {:?}", facts_dir);
-    let tables = &mut intern::InternerTables::new();
-    let all_facts = tab_delim::load_tab_delimited_facts(tables, &facts_dir)?;
-    let insensitive = Output::compute(&all_facts, Algorithm::LocationInsensitive, false);
+fn all_inputs() -> Result<(), Error> {
+    let inputs_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("inputs");
+
+    let mut facts_dirs = Vec::new();
+    for dir in fs::read_dir(&inputs_dir)? {
+        let nll_facts_dir = dir?.path().join("nll-facts");
+        if !nll_facts_dir.is_dir() {
+            continue;
+        }
 
-    let mut expected = FxHashMap::default();
-    expected.insert(Point::from(22), vec![Loan::from(1)]);
-    expected.insert(Point::from(46), vec![Loan::from(2)]);
+        for facts_dir in fs::read_dir(&nll_facts_dir)? {
+            facts_dirs.push(facts_dir?.path());
+        }
+    }
+    facts_dirs.sort();
+
+    let mut failures = Vec::new();
+    for facts_dir in &facts_dirs {
+        let name = facts_dir.strip_prefix(&inputs_dir)?.display().to_string();
+        let name = name.replace("/nll-facts/", "/");
+        let ignored = ALL_INPUTS_IGNORED
+            .iter()
+            .any(|&ignored| name == ignored || name.starts_with(&format!("{}/", ignored)));
+        if ignored {
+            println!("ignoring {}", name);
+            continue;
+        }
 
-    assert_equal(&insensitive.errors, &expected);
+        println!("facts_dir = {:?}", facts_dir);
+        let tables = &mut intern::InternerTables::new();
+        let all_facts = tab_delim::load_tab_delimited_facts(tables, facts_dir)?;
+        if panic::catch_unwind(|| test_facts(&all_facts, Algorithm::OPTIMIZED)).is_err() {
+            failures.push(name);
+        }
+    }
+
+    assert!(failures.is_empty(), "failing inputs: {:#?}", failures);
     Ok(())
 }
 
 #[test]
-fn test_sensitive_passes_issue_47680() -> Result<(), Error> {
-    let facts_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
-        .join("inputs")
-        .join("issue-47680")
-        .join("nll-facts")
-        .join("main");
-    let tables = &mut intern::InternerTables::new();
-    let all_facts = tab_delim::load_tab_delimited_facts(tables, &facts_dir)?;
-    let sensitive = Output::compute(&all_facts, Algorithm::DatafrogOpt, false);
+fn test_insensitive_errors() {
+    assert_algorithm_errors(
+        "issue-47680",
+        "main",
+        Algorithm::LocationInsensitive,
+        &[(22, 1), (46, 2)],
+    );
+}
 
-    assert!(sensitive.errors.is_empty());
-    Ok(())
+#[test]
+fn potentially_live_loans_issue_47680() {
+    let (all_facts, _) = load_fixture("issue-47680", "main");
+    let insensitive = Output::compute(&all_facts, Algorithm::LocationInsensitive, false);
+    let naive = Output::compute(&all_facts, Algorithm::Naive, true);
+
+    let live_loans: BTreeSet<Loan> = naive.borrow_live_at.values().flatten().cloned().collect();
+    assert!(!live_loans.is_empty());
+    assert_equal(insensitive.potentially_live_loans(), &live_loans);
+    assert!(naive.potentially_live_loans().is_empty());
 }
 
 #[test]
-fn no_subset_symmetries_exist() -> Result<(), Error> {
-    let facts_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
-        .join("inputs")
-        .join("issue-47680")
-        .join("nll-facts")
-        .join("main");
-    let tables = &mut intern::InternerTables::new();
-    let all_facts = tab_delim::load_tab_delimited_facts(tables, &facts_dir)?;
+fn test_sensitive_passes_issue_47680() {
+    assert_errors("issue-47680", "main", &[]);
+}
+
+#[test]
+fn no_subset_symmetries_exist() {
+    let (all_facts, _) = load_fixture("issue-47680", "main");
 
     let subset_symmetries_exist = |output: &Output<Region, Loan, Point, Variable>| {
         for (_, subsets) in &output.subset {
@@ -152,17 +233,19 @@ fn no_subset_symmetries_exist() -> Result<(), Error> {
     // produce subset symmetries for it. It does for clap, and it was used to manually verify
     // that the assert in verbose  mode didn't trigger. Therefore, switch to this dataset
     // whenever it's fast enough to be enabled in tests, or somehow create a test facts program
-    // or reduce it from clap.
+    // or reduce it from clap: `assert_opt_subset_matches_naive` writes reduced facts where the
+    // DatafrogOpt subsets differ from Naive's.
     let opt = Output::compute(&all_facts, Algorithm::DatafrogOpt, true);
     assert!(!subset_symmetries_exist(&opt));
-    Ok(())
 }
 
 // The following 3 tests, `send_is_not_static_std_sync`, `escape_upvar_nested`, and `issue_31567`
-// are extracted from rustc's test suite, and fail because of differences between the Naive
+// are extracted from rustc's test suite, and show differences between the Naive
 // and DatafrogOpt variants, on the computation of the transitive closure.
 // They are part of the same pattern that the optimized variant misses, and only differ in
 // the length of the `outlives` chain reaching a live region at a specific point.
+// The divergence is limited to `restricts`, and checked by `test_facts_expect_divergence`.
+// `outlives_chains_of_increasing_length` checks the same pattern with longer chains.
 
 #[test]
 fn send_is_not_static_std_sync() {
@@ -177,7 +260,7 @@ fn send_is_not_static_std_sync() {
 
     let mut tables = intern::InternerTables::new();
     let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
-    test_facts(&facts, Algorithm::OPTIMIZED);
+    test_facts_expect_divergence(&facts, Algorithm::OPTIMIZED);
 }
 
 #[test]
@@ -195,7 +278,7 @@ fn escape_upvar_nested() {
 
     let mut tables = intern::InternerTables::new();
     let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
-    test_facts(&facts, Algorithm::OPTIMIZED);
+    test_facts_expect_divergence(&facts, Algorithm::OPTIMIZED);
 }
 
 #[test]
@@ -218,7 +301,100 @@ fn issue_31567() {
 
     let mut tables = intern::InternerTables::new();
     let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
-    test_facts(&facts, Algorithm::OPTIMIZED);
+    test_facts_expect_divergence(&facts, Algorithm::OPTIMIZED);
+}
+
+#[test]
+fn issue_31567_longest_outlives_chain() {
+    // The program of `issue_31567`, where the chain ends at the live region `'d` rather than at
+    // a universal region: there is then no chain, unless `'d` is universal.
+    let program = |universal_regions| {
+        format!(
+            "universal_regions {{ {} }}
+            block B0 {{
+                borrow_region_at('a, L0),
+                outlives('a: 'b),
+                outlives('b: 'c),
+                outlives('c: 'd),
+                region_live_at('d);
+            }}",
+            universal_regions
+        )
+    };
+
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(&program(""), &mut tables).expect("Parsing failure");
+    let output = Output::compute(&facts, Algorithm::Naive, true);
+    assert_eq!(output.longest_outlives_chain(&facts), None);
+
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(&program("'d"), &mut tables).expect("Parsing failure");
+    let output = Output::compute(&facts, Algorithm::Naive, true);
+    let (chain, point) = output.longest_outlives_chain(&facts).expect("no chain");
+    assert_eq!(tables.regions.untern_vec(&chain), ["'a", "'b", "'c", "'d"]);
+    assert_eq!(tables.points.untern(point), "\"Mid(B0[0])\"");
+}
+
+#[test]
+fn outlives_chains_of_increasing_length() {
+    // The pattern of the previous 3 tests, with `outlives` chains of length 0 to 8, from the
+    // borrow region `'r0` to the live region: the divergence appears as soon as there is a chain,
+    // whatever its length, and is still limited to `restricts`.
+    let mut diverging_lengths = Vec::new();
+    for length in 0..=8 {
+        let mut effects = vec!["borrow_region_at('r0, L0)".to_string()];
+        effects.extend((0..length).map(|i| format!("outlives('r{}: 'r{})", i, i + 1)));
+        effects.push(format!("region_live_at('r{})", length));
+        let program = format!(
+            "universal_regions {{ }} block B0 {{ {}; }}",
+            effects.join(", ")
+        );
+
+        let mut tables = intern::InternerTables::new();
+        let facts = parse_from_program(&program, &mut tables).expect("Parsing failure");
+        test_facts(&facts, Algorithm::OPTIMIZED);
+
+        let naive = Output::compute(&facts, Algorithm::Naive, true);
+        let opt = Output::compute(&facts, Algorithm::DatafrogOpt, true);
+        if naive.restricts != opt.restricts {
+            diverging_lengths.push(length);
+        }
+    }
+
+    assert_eq!(diverging_lengths, (1..=8).collect::<Vec<_>>());
+}
+
+#[test]
+fn issue_31567_critical_outlives() {
+    // The program of `issue_31567`, with the loan invalidated where it is live.
+    let program = r"
+        universal_regions { }
+        block B0 {
+            borrow_region_at('a, L0),
+            outlives('a: 'b),
+            outlives('b: 'c),
+            outlives('c: 'd),
+            region_live_at('d),
+            invalidates(L0);
+        }
+    ";
+
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    let output = Output::compute(&facts, Algorithm::Naive, false);
+
+    let point = tables.points.intern("\"Mid(B0[0])\"");
+    let loan = tables.loans.intern("L0");
+    let (r1, r2, p) = output
+        .critical_outlives(point, loan, &facts)
+        .expect("no critical outlives edge");
+    assert_eq!(tables.regions.untern(r1), "'a");
+    assert_eq!(tables.regions.untern(r2), "'b");
+    assert_eq!(p, point);
+
+    // Without the loan's error, no edge is critical.
+    let other_loan = tables.loans.intern("L1");
+    assert_eq!(output.critical_outlives(point, other_loan, &facts), None);
 }
 
 #[test]
@@ -245,6 +421,279 @@ fn borrowed_local_error() {
     let mut tables = intern::InternerTables::new();
     let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
     test_facts(&facts, Algorithm::OPTIMIZED);
+
+    // `'a` and `'b` must outlive `'c`, the only universal region.
+    let output = Output::compute(&facts, Algorithm::Naive, true);
+    let constrained: Vec<_> = output
+        .constrained_universal_regions()
+        .into_iter()
+        .map(|(universal_region, regions)| {
+            let regions: Vec<_> = regions
+                .into_iter()
+                .map(|region| tables.regions.untern(region))
+                .collect();
+            (tables.regions.untern(universal_region), regions)
+        })
+        .collect();
+    assert_eq!(constrained, [("'c", vec!["'a", "'b"])]);
+}
+
+#[test]
+fn borrowed_local_error_invalidated_at_exit() {
+    // The program of `borrowed_local_error`: `L0` flows into the universal region `'c`, and is
+    // still live when the function returns.
+    let program = r"
+        universal_regions { 'c }
+        block B0 {
+            borrow_region_at('a, L0), outlives('a: 'b), outlives('b: 'c);
+        }
+    ";
+
+    let compute = |invalidate_loans_at_exit| {
+        let mut tables = intern::InternerTables::new();
+        let options = LoweringOptions {
+            invalidate_loans_at_exit,
+            ..LoweringOptions::default()
+        };
+        let facts = parse_from_program_with_options(program, &mut tables, options)
+            .expect("Parsing failure");
+        (facts, tables)
+    };
+
+    let (facts, _) = compute(false);
+    assert!(facts.invalidates.is_empty());
+    for &algorithm in &[Algorithm::Naive, Algorithm::DatafrogOpt] {
+        let output = Output::compute(&facts, algorithm, false);
+        assert!(output.errors.is_empty(), "{:?}", algorithm);
+    }
+
+    let (facts, mut tables) = compute(true);
+    let exit = tables.points.intern("\"Mid(B0[0])\"");
+    let l0 = tables.loans.intern("L0");
+    assert_eq!(facts.invalidates, [(exit, l0)]);
+    for &algorithm in &[Algorithm::Naive, Algorithm::DatafrogOpt] {
+        let output = Output::compute(&facts, algorithm, false);
+        let mut expected = FxHashMap::default();
+        expected.insert(exit, vec![l0]);
+        assert_equal(&expected, &output.errors);
+    }
+}
+
+#[test]
+fn reborrow_killed_with_its_parent() {
+    // `L1` reborrows `L0`, and is invalidated while `'b` is live: killing `L0` ends `L1` too.
+    let program = |reborrow| {
+        format!(
+            r"
+            universal_regions {{ }}
+            block B0 {{
+                borrow_region_at('a, L0), region_live_at('a);
+                borrow_region_at('b, L1), {}region_live_at('b);
+                kill(L0), region_live_at('b);
+                invalidates(L1), region_live_at('b);
+            }}
+            ",
+            if reborrow {
+                "loan_reborrows(L1, L0), "
+            } else {
+                ""
+            }
+        )
+    };
+
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(&program(false), &mut tables).expect("Parsing failure");
+    let point = tables.points.intern("\"Mid(B0[3])\"");
+    let l1 = tables.loans.intern("L1");
+    for &algorithm in &[Algorithm::Naive, Algorithm::DatafrogOpt, Algorithm::Hybrid] {
+        let output = Output::compute(&facts, algorithm, false);
+        let mut expected = FxHashMap::default();
+        expected.insert(point, vec![l1]);
+        assert_equal(&expected, &output.errors);
+    }
+
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(&program(true), &mut tables).expect("Parsing failure");
+    let l0 = tables.loans.intern("L0");
+    let l1 = tables.loans.intern("L1");
+    assert_eq!(facts.loan_reborrows, [(l1, l0)]);
+    for &algorithm in &[Algorithm::Naive, Algorithm::DatafrogOpt, Algorithm::Hybrid] {
+        let output = Output::compute(&facts, algorithm, false);
+        assert!(output.errors.is_empty(), "{:?}", algorithm);
+    }
+}
+
+#[test]
+fn subset_edges_per_point() {
+    let program = r"
+        universal_regions { }
+        block B0 {
+            outlives('a: 'b), outlives('b: 'c), region_live_at('a), region_live_at('b), region_live_at('c);
+            outlives('c: 'd), region_live_at('a), region_live_at('b), region_live_at('c), region_live_at('d);
+        }
+    ";
+
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    let output = Output::compute(&facts, Algorithm::Naive, true);
+    let stats: Vec<(&str, usize)> = output
+        .subset_stats()
+        .into_iter()
+        .map(|(point, edges)| (tables.points.untern(point), edges))
+        .collect();
+    // The transitive closure of the `outlives` facts: 3 edges, then 6 with `'c: 'd`.
+    assert_eq!(
+        stats,
+        [
+            ("\"Mid(B0[0])\"", 3),
+            ("\"Start(B0[1])\"", 3),
+            ("\"Mid(B0[1])\"", 6),
+        ]
+    );
+}
+
+#[test]
+fn borrowed_local_error_universal_liveness_policies() {
+    // The program of `borrowed_local_error`: two points and an edge.
+    let program = r"
+        universal_regions { 'c }
+        block B0 {
+            borrow_region_at('a, L0), outlives('a: 'b), outlives('b: 'c);
+        }
+    ";
+
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    let c = tables.regions.intern("'c");
+    let start = tables.points.intern("\"Start(B0[0])\"");
+    let mid = tables.points.intern("\"Mid(B0[0])\"");
+
+    let policies: &[(UniversalLivenessPolicy, &[Point])] = &[
+        (UniversalLivenessPolicy::Everywhere, &[start, mid]),
+        (UniversalLivenessPolicy::ExitOnly, &[mid]),
+        (UniversalLivenessPolicy::Explicit, &[]),
+    ];
+    assert_eq!(
+        ComputeOptions::default().universal_liveness,
+        UniversalLivenessPolicy::Everywhere
+    );
+
+    for &(policy, live_points) in policies {
+        for &algorithm in &[Algorithm::Naive, Algorithm::DatafrogOpt] {
+            let options = ComputeOptions {
+                dump_enabled: true,
+                universal_liveness: policy,
+                ..ComputeOptions::default()
+            };
+            let output = Output::compute_with_options(&facts, algorithm, options);
+            let c_live_at: Vec<Point> = output
+                .region_live_at
+                .iter()
+                .filter(|(_, regions)| regions.contains(&c))
+                .map(|(&point, _)| point)
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect();
+            assert_eq!(c_live_at, live_points, "{:?}", policy);
+
+            // `L0` flows into `'c` at the exit point, where it is live unless `'c` isn't.
+            let l0_live = output
+                .borrow_live_at
+                .get(&mid)
+                .map_or(0, |loans| loans.len());
+            let expected = if live_points.is_empty() { 0 } else { 1 };
+            assert_eq!(l0_live, expected, "{:?}", policy);
+        }
+    }
+}
+
+#[test]
+fn borrowed_local_error_universal_reach() {
+    // The program of `borrowed_local_error`: `L0` escapes through the universal region `'c`.
+    let program = r"
+        universal_regions { 'c, 'd }
+        block B0 {
+            borrow_region_at('a, L0), outlives('a: 'b), outlives('b: 'c);
+            borrow_region_at('e, L1);
+        }
+    ";
+
+    for &algorithm in &[Algorithm::Naive, Algorithm::DatafrogOpt] {
+        let (output, mut tables) = program::run(program, algorithm).unwrap();
+        let reach = output.loan_universal_reach();
+        let reached: Vec<_> = reach[&tables.loans.intern("L0")]
+            .iter()
+            .map(|&r| tables.regions.untern(r))
+            .collect();
+        assert_eq!(reached, ["'c"]);
+        assert!(!reach.contains_key(&tables.loans.intern("L1")));
+    }
+}
+
+#[test]
+fn sensitive_errors_are_at_insensitive_candidate_points() {
+    for test_fn in &["return_ref_to_local", "use_while_mut", "use_while_mut_fr"] {
+        let (facts, _) = load_fixture("smoke-test", test_fn);
+
+        let insensitive = Output::compute(&facts, Algorithm::LocationInsensitive, false);
+        let candidates: BTreeSet<Point> = insensitive
+            .insensitive_candidate_points()
+            .into_iter()
+            .collect();
+        let sensitive = Output::compute(&facts, Algorithm::Naive, false);
+        assert!(!sensitive.errors.is_empty());
+        for (point, _) in sensitive.all_errors() {
+            assert!(candidates.contains(&point), "{}", test_fn);
+        }
+    }
+}
+
+#[test]
+fn datafrog_opt_join_orders_agree() {
+    for test_fn in &[
+        "foo",
+        "return_ref_to_local",
+        "use_while_mut",
+        "use_while_mut_fr",
+    ] {
+        let (facts, _) = load_fixture("smoke-test", test_fn);
+
+        let leapjoin = Output::compute(&facts, Algorithm::DatafrogOpt, true);
+        let options = ComputeOptions {
+            dump_enabled: true,
+            join_order: JoinOrder::Pairwise,
+            ..ComputeOptions::default()
+        };
+        let pairwise = Output::compute_with_options(&facts, Algorithm::DatafrogOpt, options);
+        assert!(!pairwise.subset.is_empty(), "{}", test_fn);
+        assert_eq!(pairwise, leapjoin, "{}", test_fn);
+    }
+}
+
+#[test]
+fn errors_reported_as_discovered() {
+    let (facts, _) = load_fixture("smoke-test", "use_while_mut");
+
+    for &algorithm in &[
+        Algorithm::Naive,
+        Algorithm::DatafrogOpt,
+        Algorithm::LocationInsensitive,
+        Algorithm::Compare,
+        Algorithm::Hybrid,
+    ] {
+        let mut reported = Vec::new();
+        let output = Output::compute_with_error_callback(
+            &facts,
+            algorithm,
+            ComputeOptions::default(),
+            |point, loan| reported.push((point, loan)),
+        );
+        assert!(!reported.is_empty(), "{:?}", algorithm);
+
+        // Each error is reported once, in no particular order.
+        reported.sort();
+        assert_eq!(reported, output.all_errors(), "{:?}", algorithm);
+    }
 }
 
 #[test]
@@ -257,14 +706,7 @@ fn smoke_test_errors() {
     ];
 
     for test_fn in &failures {
-        let facts_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
-            .join("inputs")
-            .join("smoke-test")
-            .join("nll-facts")
-            .join(test_fn);
-        println!("facts_dir = {:?}", facts_dir);
-        let tables = &mut intern::InternerTables::new();
-        let facts = tab_delim::load_tab_delimited_facts(tables, &facts_dir).expect("facts");
+        let (facts, _) = load_fixture("smoke-test", test_fn);
 
         let location_insensitive = Output::compute(&facts, Algorithm::LocationInsensitive, true);
         assert!(
@@ -288,38 +730,129 @@ fn smoke_test_errors() {
 
 #[test]
 fn smoke_test_success_1() {
-    let facts_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
-        .join("inputs")
-        .join("smoke-test")
-        .join("nll-facts")
-        .join("position_dependent_outlives");
-    println!("facts_dir = {:?}", facts_dir);
-    let tables = &mut intern::InternerTables::new();
-    let facts = tab_delim::load_tab_delimited_facts(tables, &facts_dir).expect("facts");
+    let (facts, _) = load_fixture("smoke-test", "position_dependent_outlives");
 
     let location_insensitive = Output::compute(&facts, Algorithm::LocationInsensitive, true);
     assert!(!location_insensitive.errors.is_empty());
 
+    for &algorithm in &[Algorithm::Naive, Algorithm::DatafrogOpt, Algorithm::Hybrid] {
+        let output = Output::compute(&facts, algorithm, true);
+        assert_eq!(output.check_live_monotonicity(&facts), Ok(()));
+    }
+
     test_facts(&facts, Algorithm::OPTIMIZED);
 }
 
 #[test]
 fn smoke_test_success_2() {
-    let facts_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
-        .join("inputs")
-        .join("smoke-test")
-        .join("nll-facts")
-        .join("foo");
-    println!("facts_dir = {:?}", facts_dir);
-    let tables = &mut intern::InternerTables::new();
-    let facts = tab_delim::load_tab_delimited_facts(tables, &facts_dir).expect("facts");
+    let (facts, _) = load_fixture("smoke-test", "foo");
 
     let location_insensitive = Output::compute(&facts, Algorithm::LocationInsensitive, true);
     assert!(location_insensitive.errors.is_empty());
 
+    for &algorithm in &[Algorithm::Naive, Algorithm::DatafrogOpt, Algorithm::Hybrid] {
+        let output = Output::compute(&facts, algorithm, true);
+        assert_eq!(output.check_live_monotonicity(&facts), Ok(()));
+    }
+
     test_facts(&facts, Algorithm::OPTIMIZED);
 }
 
+#[test]
+fn live_monotonicity_violation() {
+    let program = r"
+        universal_regions { }
+
+        block B0 {
+            borrow_region_at('a, L0), region_live_at('a);
+            region_live_at('a);
+            kill(L0), region_live_at('a);
+            region_live_at('a);
+        }
+    ";
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    let mut output = Output::compute(&facts, Algorithm::Naive, true);
+    assert_eq!(output.check_live_monotonicity(&facts), Ok(()));
+
+    // Only the kill at `Mid(B0[2])` ends `L0`: removing it earlier is a death without a kill.
+    let l0 = tables.loans.intern("L0");
+    let start = tables.points.intern("\"Start(B0[1])\"");
+    let mid = tables.points.intern("\"Mid(B0[1])\"");
+    output
+        .borrow_live_at
+        .get_mut(&mid)
+        .unwrap()
+        .retain(|&loan| loan != l0);
+    assert_eq!(
+        output.check_live_monotonicity(&facts),
+        Err(vec![(l0, start, mid)])
+    );
+}
+
+#[test]
+fn hybrid_agrees_with_datafrog_opt() {
+    // `foo` has no potential errors, whereas `use_while_mut` has errors, which `Hybrid` finds
+    // with the location-sensitive analysis.
+    for &(test_fn, escalated) in &[("foo", false), ("use_while_mut", true)] {
+        let (facts, _) = load_fixture("smoke-test", test_fn);
+
+        let diff = Output::hybrid_diff(&facts);
+        assert_eq!(diff.escalated, escalated, "{}", test_fn);
+        assert!(diff.is_empty(), "{}: {:?}", test_fn, diff);
+    }
+}
+
+#[test]
+fn subsets_computed_without_errors() {
+    let (facts, _) = load_fixture("smoke-test", "foo");
+
+    for &algorithm in &[
+        Algorithm::Naive,
+        Algorithm::DatafrogOpt,
+        Algorithm::LocationInsensitive,
+        Algorithm::Hybrid,
+    ] {
+        let output = Output::compute(&facts, algorithm, true);
+        assert!(output.errors.is_empty());
+        assert!(
+            !output.subset_anywhere.is_empty(),
+            "{:?} didn't compute `subset_anywhere`",
+            algorithm
+        );
+
+        match algorithm {
+            Algorithm::Naive | Algorithm::DatafrogOpt => {
+                assert!(
+                    !output.subset.is_empty(),
+                    "{:?} didn't compute `subset`",
+                    algorithm
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn peak_tuples() {
+    let (facts, _) = load_fixture("smoke-test", "foo");
+
+    for &algorithm in &[
+        Algorithm::Naive,
+        Algorithm::DatafrogOpt,
+        Algorithm::LocationInsensitive,
+    ] {
+        let output = Output::compute(&facts, algorithm, false);
+        if cfg!(feature = "peak-tuples") {
+            let peak_tuples = output.peak_tuples.expect("peak tuples were not tracked");
+            assert!(peak_tuples > 0, "{:?} didn't track peak tuples", algorithm);
+        } else {
+            assert_eq!(output.peak_tuples, None);
+        }
+    }
+}
+
 #[test]
 // V used in P => V live upon entry into P
 fn var_live_in_single_block() {
@@ -332,10 +865,9 @@ fn var_live_in_single_block() {
         }
     ";
 
-    let mut tables = intern::InternerTables::new();
-    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    let (output, _) = program::run(program, Algorithm::Naive).expect("Parsing failure");
 
-    let liveness = Output::compute(&facts, Algorithm::Naive, true).var_live_at;
+    let liveness = output.var_live_at;
     println!("Registered liveness data: {:?}", liveness);
     for (point, variables) in liveness.iter() {
         println!("{:?} has live variables: {:?}", point, variables);
@@ -345,41 +877,236 @@ fn var_live_in_single_block() {
 }
 
 #[test]
-// P GOTO Q, V used in Q => V live in P
-fn var_live_in_successor_propagates_to_predecessor() {
+fn var_live_in_blocks() {
+    // The program of `var_live_in_single_block`, with a use of `V2` in the next block.
     let program = r"
         universal_regions {  }
 
         block B0 {
-            invalidates(L0); // generate a point
+            var_used(V1);
             goto B1;
         }
 
         block B1 {
-            invalidates(L0);
-            goto B2;
-        }
-
-        block B2 {
-            invalidates(L0);
-            var_used(V1);
+            var_defined(V1);
+            var_used(V2);
         }
     ";
 
     let mut tables = intern::InternerTables::new();
     let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    let output = Output::compute(&facts, Algorithm::Naive, true);
+    let live_in = |block| -> Vec<String> {
+        dump::block_live_in(&output, block, &facts, &tables)
+            .into_iter()
+            .collect()
+    };
+    assert_eq!(live_in("B0"), ["V1", "V2"]);
+    assert_eq!(live_in("B1"), ["V2"]);
+    assert!(live_in("B2").is_empty());
+}
 
-    let liveness = Output::compute(&facts, Algorithm::Naive, true).var_live_at;
-    println!("Registered liveness data: {:?}", liveness);
-    println!("CFG: {:?}", facts.cfg_edge);
-    for (point, variables) in liveness.iter() {
-        println!("{:?} has live variables: {:?}", point, variables);
-        assert_eq!(variables.len(), 1);
+#[test]
+fn timeline_as_csv() {
+    let program = r"
+        universal_regions { }
+
+        block B0 {
+            borrow_region_at('a, L0), region_live_at('a);
+            invalidates(L0), region_live_at('a);
+        }
+    ";
+
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    let output = Output::compute(&facts, Algorithm::Naive, true);
+    let mut csv = Vec::new();
+    dump::timeline_csv(&mut csv, &output, &facts, &tables).unwrap();
+    assert_eq!(
+        String::from_utf8(csv).unwrap(),
+        "\
+point,live_regions,live_loans,errors
+Start(B0[0]),1,0,0
+Mid(B0[0]),1,1,0
+Start(B0[1]),1,1,0
+Mid(B0[1]),1,1,1
+"
+    );
+}
+
+#[test]
+fn var_live_at_filtered_to_a_block() {
+    let program = r"
+        universal_regions {  }
+
+        block B0 {
+            var_used(V1);
+            goto B1;
+        }
+
+        block B1 {
+            var_used(V2);
+        }
+    ";
+
+    let (output, mut tables) = program::run(program, Algorithm::Naive).expect("Parsing failure");
+    let b1_points: BTreeSet<Point> = ["\"Start(B1[0])\"", "\"Mid(B1[0])\""]
+        .iter()
+        .map(|point| tables.points.intern(point))
+        .collect();
+
+    let liveness = output.var_live_at_filtered(&b1_points);
+    assert_eq!(liveness.len(), 2);
+    for point in &b1_points {
+        assert_eq!(tables.variables.untern_vec(&liveness[point]), ["V2"]);
+    }
+    assert!(output.var_live_at.len() > liveness.len());
+}
+
+#[test]
+// P GOTO Q, V used in Q => V live in P
+fn var_live_in_successor_propagates_to_predecessor() {
+    let program = r"
+        universal_regions {  }
+
+        block B0 {
+            invalidates(L0); // generate a point
+            goto B1;
+        }
+
+        block B1 {
+            invalidates(L0);
+            goto B2;
+        }
+
+        block B2 {
+            invalidates(L0);
+            var_used(V1);
+        }
+    ";
+
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+
+    assert_cfg(
+        &facts,
+        &tables,
+        &[
+            ("\"Start(B0[0])\"", "\"Mid(B0[0])\""),
+            ("\"Mid(B0[0])\"", "\"Start(B1[0])\""),
+            ("\"Start(B1[0])\"", "\"Mid(B1[0])\""),
+            ("\"Mid(B1[0])\"", "\"Start(B2[0])\""),
+            ("\"Start(B2[0])\"", "\"Mid(B2[0])\""),
+            ("\"Mid(B2[0])\"", "\"Start(B2[1])\""),
+            ("\"Start(B2[1])\"", "\"Mid(B2[1])\""),
+        ],
+    );
+
+    let liveness = Output::compute(&facts, Algorithm::Naive, true).var_live_at;
+    println!("Registered liveness data: {:?}", liveness);
+    for (point, variables) in liveness.iter() {
+        println!("{:?} has live variables: {:?}", point, variables);
+        assert_eq!(variables.len(), 1);
     }
 
     assert!(!liveness.get(&0.into()).unwrap().is_empty());
 }
 
+#[test]
+fn var_liveness_iterations() {
+    // The program of `var_live_in_successor_propagates_to_predecessor`: `V1` becomes live one
+    // more point backwards at each round, from its use to the entry point.
+    let program = r"
+        universal_regions {  }
+
+        block B0 {
+            invalidates(L0); // generate a point
+            goto B1;
+        }
+
+        block B1 {
+            invalidates(L0);
+            goto B2;
+        }
+
+        block B2 {
+            invalidates(L0);
+            var_used(V1);
+        }
+    ";
+
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    let iterations = Output::liveness_iterations(&facts);
+
+    let points = [
+        "\"Mid(B2[1])\"",
+        "\"Start(B2[1])\"",
+        "\"Mid(B2[0])\"",
+        "\"Start(B2[0])\"",
+        "\"Mid(B1[0])\"",
+        "\"Start(B1[0])\"",
+        "\"Mid(B0[0])\"",
+        "\"Start(B0[0])\"",
+    ];
+    assert_eq!(iterations.len(), points.len());
+    let v1 = tables.variables.intern("V1");
+    for (round, var_live_at) in iterations.iter().enumerate() {
+        let mut expected = FxHashMap::default();
+        for point in &points[..=round] {
+            expected.insert(tables.points.intern(point), vec![v1]);
+        }
+        assert_equal(&expected, var_live_at);
+    }
+
+    let output = Output::compute(&facts, Algorithm::Naive, true);
+    assert_equal(&output.var_live_at, iterations.last().unwrap());
+}
+
+#[test]
+// V defined before a loop and used after it => V live throughout the loop
+fn var_live_across_loop() {
+    let program = r"
+        universal_regions {  }
+
+        block B0 {
+            var_defined(V1);
+            goto B1;
+        }
+
+        block B1 {
+            invalidates(L0);
+            invalidates(L0);
+            // the back edge of the loop
+            goto B1, B2;
+        }
+
+        block B2 {
+            var_used(V1);
+        }
+    ";
+
+    let (output, tables) = program::run(program, Algorithm::Naive).expect("Parsing failure");
+    let live_points: BTreeSet<&str> = output
+        .var_live_at
+        .iter()
+        .filter(|(_, variables)| !variables.is_empty())
+        .map(|(&point, _)| tables.points.untern(point))
+        .collect();
+    let expected: BTreeSet<&str> = [
+        "\"Start(B1[0])\"",
+        "\"Mid(B1[0])\"",
+        "\"Start(B1[1])\"",
+        "\"Mid(B1[1])\"",
+        "\"Start(B2[0])\"",
+        "\"Mid(B2[0])\"",
+    ]
+    .iter()
+    .cloned()
+    .collect();
+    assert_equal(&expected, &live_points);
+}
+
 #[test]
 // P GOTO Q, V used in Q, V defined in P => V not live in P
 fn var_live_in_successor_killed_by_reassignment() {
@@ -502,6 +1229,47 @@ fn var_drop_used_simple() {
     );
 }
 
+#[test]
+fn use_implies_drop_use_policy() {
+    let program = r"
+        universal_regions {  }
+
+        block B0 {
+            var_defined(V1);
+            goto B1;
+        }
+
+        block B1 {
+            invalidates(L0);
+            var_used(V1);
+        }
+    ";
+
+    let compute = |use_implies_drop_use| {
+        let mut tables = intern::InternerTables::new();
+        let options = LoweringOptions {
+            use_implies_drop_use,
+            ..LoweringOptions::default()
+        };
+        let facts = parse_from_program_with_options(program, &mut tables, options)
+            .expect("Parsing failure");
+        let output = Output::compute(&facts, Algorithm::Naive, true);
+        (output, tables)
+    };
+
+    let (default_output, _) = compute(false);
+    assert!(default_output.var_drop_live_at.is_empty());
+
+    let (output, mut tables) = compute(true);
+    assert_equal(&default_output.var_live_at, &output.var_live_at);
+
+    let used_at = tables.points.intern("\"Mid(B1[1])\"");
+    let v1 = tables.variables.intern("V1");
+    let mut expected = FxHashMap::default();
+    expected.insert(used_at, vec![v1]);
+    assert_equal(&expected, &output.var_drop_live_at);
+}
+
 fn untern_region_live_at(
     region_live_at: FxHashMap<Point, Vec<Region>>,
     tables: &intern::InternerTables,
@@ -517,6 +1285,21 @@ fn untern_region_live_at(
     result
 }
 
+#[test]
+fn drop_critical_variables() {
+    let (mut facts, tables) = load_fixture("maybe-initialized-drop", "main");
+    facts.region_live_at = Vec::default();
+
+    let output = Output::compute(&facts, Algorithm::Naive, true);
+    let variables: Vec<&str> = output
+        .drop_critical_vars()
+        .into_iter()
+        .map(|variable| tables.variables.untern(variable))
+        .collect();
+    // `wrap` is never used, only dropped at the end of `main`.
+    assert_eq!(variables, ["\"_2\""]);
+}
+
 fn compare_region_live_at(dir_name: &str, fn_name: &str) {
     let facts_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
         .join("inputs")
@@ -563,6 +1346,53 @@ fn compare_region_live_at(dir_name: &str, fn_name: &str) {
     assert_equal(&input_region_live_at, &output_region_live_at);
 }
 
+#[test]
+fn ignored_drop_liveness_same_as_without_drops() {
+    // Unlike in `drop-may-dangle`, the drop-uses of this function make regions live.
+    let (mut all_facts, _) = load_fixture("drop-no-may-dangle", "main");
+    all_facts.region_live_at = Vec::default();
+    assert!(!all_facts.var_drop_used.is_empty());
+
+    let facts_without_drops = AllFacts {
+        var_drop_used: Vec::new(),
+        ..all_facts.clone()
+    };
+    let options = ComputeOptions {
+        dump_enabled: true,
+        ignore_drop_liveness: true,
+        ..ComputeOptions::default()
+    };
+    for &algorithm in &[Algorithm::Naive, Algorithm::DatafrogOpt] {
+        let with_drops = Output::compute(&all_facts, algorithm, true);
+        let relaxed = Output::compute_with_options(&all_facts, algorithm, options.clone());
+        let without_drops = Output::compute(&facts_without_drops, algorithm, true);
+        assert_equal(&without_drops.errors, &relaxed.errors);
+        assert_equal(&without_drops.region_live_at, &relaxed.region_live_at);
+        assert!(relaxed.var_drop_live_at.is_empty());
+        assert_ne!(with_drops.region_live_at, relaxed.region_live_at);
+    }
+}
+
+#[test]
+fn precomputed_liveness_same_as_computed() {
+    let (mut all_facts, _) = load_fixture("drop-liveness", "main");
+    all_facts.region_live_at = Vec::default();
+
+    let region_live_at = Output::compute(&all_facts, Algorithm::Naive, true).region_live_at;
+    for &algorithm in &[
+        Algorithm::Naive,
+        Algorithm::DatafrogOpt,
+        Algorithm::LocationInsensitive,
+        Algorithm::Hybrid,
+    ] {
+        let computed = Output::compute(&all_facts, algorithm, true);
+        let precomputed =
+            Output::compute_with_liveness(&all_facts, algorithm, &region_live_at, true);
+        assert_equal(&computed.errors, &precomputed.errors);
+        assert_equal(&computed.borrow_live_at, &precomputed.borrow_live_at);
+    }
+}
+
 macro_rules! region_live_at_tests {
     ($($name:ident($dir:expr, $fn:expr),)*) => {
         $(
@@ -592,3 +1422,2000 @@ region_live_at_tests! {
     maybe_initialized_drop_with_fragment_main("maybe-initialized-drop-with-fragment", "main"),
     maybe_initialized_drop_with_uninitialized_fragments_main("maybe-initialized-drop-with-uninitialized-fragments", "main"),
 }
+
+#[test]
+fn dominators() {
+    let program = r"
+        universal_regions { }
+
+        block B0 {
+            invalidates(L0);
+            goto B1, B2;
+        }
+
+        block B1 {
+            invalidates(L0);
+            goto B3;
+        }
+
+        block B2 {
+            invalidates(L0);
+            goto B3;
+        }
+
+        block B3 {
+            invalidates(L0);
+        }
+
+        block B4 {
+            invalidates(L0); // a second entry point
+            goto B5;
+        }
+
+        block B5 {
+            invalidates(L0);
+        }
+    ";
+
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    assert_cfg(
+        &facts,
+        &tables,
+        &[
+            ("\"Start(B0[0])\"", "\"Mid(B0[0])\""),
+            ("\"Mid(B0[0])\"", "\"Start(B1[0])\""),
+            ("\"Mid(B0[0])\"", "\"Start(B2[0])\""),
+            ("\"Start(B1[0])\"", "\"Mid(B1[0])\""),
+            ("\"Mid(B1[0])\"", "\"Start(B3[0])\""),
+            ("\"Start(B2[0])\"", "\"Mid(B2[0])\""),
+            ("\"Mid(B2[0])\"", "\"Start(B3[0])\""),
+            ("\"Start(B3[0])\"", "\"Mid(B3[0])\""),
+            ("\"Start(B4[0])\"", "\"Mid(B4[0])\""),
+            ("\"Mid(B4[0])\"", "\"Start(B5[0])\""),
+            ("\"Start(B5[0])\"", "\"Mid(B5[0])\""),
+        ],
+    );
+
+    let dominators: BTreeMap<&str, &str> = facts
+        .dominators()
+        .into_iter()
+        .map(|(p, dominator)| (tables.points.untern(p), tables.points.untern(dominator)))
+        .collect();
+
+    let mut expected = BTreeMap::new();
+    expected.insert("\"Start(B0[0])\"", "\"Start(B0[0])\"");
+    expected.insert("\"Mid(B0[0])\"", "\"Start(B0[0])\"");
+    expected.insert("\"Start(B1[0])\"", "\"Mid(B0[0])\"");
+    expected.insert("\"Mid(B1[0])\"", "\"Start(B1[0])\"");
+    expected.insert("\"Start(B2[0])\"", "\"Mid(B0[0])\"");
+    expected.insert("\"Mid(B2[0])\"", "\"Start(B2[0])\"");
+    expected.insert("\"Start(B3[0])\"", "\"Mid(B0[0])\"");
+    expected.insert("\"Mid(B3[0])\"", "\"Start(B3[0])\"");
+    expected.insert("\"Start(B4[0])\"", "\"Start(B4[0])\"");
+    expected.insert("\"Mid(B4[0])\"", "\"Start(B4[0])\"");
+    expected.insert("\"Start(B5[0])\"", "\"Mid(B4[0])\"");
+    expected.insert("\"Mid(B5[0])\"", "\"Start(B5[0])\"");
+    assert_equal(&expected, &dominators);
+}
+
+#[test]
+fn dominators_multiple_entries() {
+    // `B2` can be reached from two entry points, so only the virtual root dominates it.
+    let program = r"
+        universal_regions { }
+
+        block B0 {
+            invalidates(L0);
+            goto B2;
+        }
+
+        block B1 {
+            invalidates(L0);
+            goto B2;
+        }
+
+        block B2 {
+            invalidates(L0);
+        }
+    ";
+
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    let dominators = facts.dominators();
+
+    let mut point = |name: &str| tables.points.intern(name);
+    let start_b2 = point("\"Start(B2[0])\"");
+    assert_eq!(dominators[&start_b2], start_b2);
+    assert_eq!(dominators[&point("\"Mid(B2[0])\"")], start_b2);
+    assert_eq!(dominators.len(), 6);
+}
+
+#[test]
+fn dominators_with_a_loop_at_the_entry() {
+    // The first point is a loop header: it has a predecessor, but no point outside of the loop
+    // reaches it, so it is still the entry point.
+    let program = r"
+        universal_regions { }
+
+        block B0 {
+            invalidates(L0);
+            goto B0, B1;
+        }
+
+        block B1 {
+            invalidates(L0);
+        }
+    ";
+
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    let dominators: BTreeMap<&str, &str> = facts
+        .dominators()
+        .into_iter()
+        .map(|(p, dominator)| (tables.points.untern(p), tables.points.untern(dominator)))
+        .collect();
+
+    let mut expected = BTreeMap::new();
+    expected.insert("\"Start(B0[0])\"", "\"Start(B0[0])\"");
+    expected.insert("\"Mid(B0[0])\"", "\"Start(B0[0])\"");
+    expected.insert("\"Start(B1[0])\"", "\"Mid(B0[0])\"");
+    expected.insert("\"Mid(B1[0])\"", "\"Start(B1[0])\"");
+    assert_eq!(dominators, expected);
+}
+
+#[test]
+fn prefixed_facts_do_not_collide() -> Result<(), Error> {
+    let facts_dir = |fn_name| fixture_dir("smoke-test", fn_name);
+    let tables = &mut intern::InternerTables::new();
+    let foo = tab_delim::load_tab_delimited_facts_with_prefix(tables, &facts_dir("foo"), "foo:")?;
+    let main =
+        tab_delim::load_tab_delimited_facts_with_prefix(tables, &facts_dir("main"), "main:")?;
+
+    // Both functions have a `'_#0r` universal region, and a `Start(bb0[0])` point.
+    let foo_regions: BTreeSet<_> = foo.universal_region.iter().cloned().collect();
+    assert!(main
+        .universal_region
+        .iter()
+        .all(|region| !foo_regions.contains(region)));
+    assert_eq!(
+        tables.regions.untern(foo.universal_region[0]),
+        "foo:\"\\'_#0r\""
+    );
+    assert_eq!(
+        tables.regions.untern(main.universal_region[0]),
+        "main:\"\\'_#0r\""
+    );
+
+    let foo_points: BTreeSet<_> = foo.cfg_edge.iter().map(|&(p, _)| p).collect();
+    assert!(main.cfg_edge.iter().all(|(p, _)| !foo_points.contains(p)));
+    assert_eq!(
+        tables.points.untern(main.cfg_edge[0].0),
+        "main:\"Start(bb0[0])\""
+    );
+    Ok(())
+}
+
+#[test]
+fn loans_never_live() {
+    // The region of `L1` is never live, unlike the region of `L0`.
+    let program = r"
+        universal_regions { }
+
+        block B0 {
+            borrow_region_at('a, L0), borrow_region_at('b, L1), region_live_at('a);
+            region_live_at('a);
+        }
+    ";
+
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    for &algorithm in &[Algorithm::Naive, Algorithm::DatafrogOpt] {
+        let output = Output::compute(&facts, algorithm, true);
+        assert_eq!(
+            output.never_live_loans(&facts),
+            [tables.loans.intern("L1")],
+            "{:?}",
+            algorithm
+        );
+    }
+}
+
+#[test]
+fn program_liveness_assertions() {
+    // `V0`, and its region `'a`, are live between its definition and its use.
+    let program = r"
+        universal_regions { }
+        var_uses_region { (V0, 'a) }
+
+        block B0 {
+            var_defined(V0);
+            var_used(V0);
+            ;
+        }
+
+        assert_live(V0 @ Mid(B0[1]))
+        assert_live('a @ Start(B0[1]))
+        assert_live(V0 @ Start(B0[2]))
+        assert_live('a @ Start(B0[2]))
+    ";
+
+    let mut tables = intern::InternerTables::new();
+    let program = program::parse_program(program, &mut tables, LoweringOptions::default())
+        .expect("Parsing failure");
+    let output = Output::compute(&program.facts, Algorithm::Naive, true);
+    let failed: Vec<_> = program
+        .failed_assertions(&output, &mut tables)
+        .into_iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        failed,
+        [
+            "assert_live(V0 @ Start(B0[2]))",
+            "assert_live('a @ Start(B0[2]))"
+        ]
+    );
+}
+
+#[test]
+fn error_ranges_of_consecutive_points() {
+    // `L0` errors at 3 consecutive points, from `Start(B0[1])` to `Start(B0[2])`, and then at
+    // `Mid(B0[3])` alone.
+    let program = r"
+        universal_regions { }
+
+        block B0 {
+            borrow_region_at('a, L0), region_live_at('a);
+            invalidates(L0), region_live_at('a) / invalidates(L0), region_live_at('a);
+            invalidates(L0), region_live_at('a) / region_live_at('a);
+            invalidates(L0), region_live_at('a);
+        }
+    ";
+
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    let output = Output::compute(&facts, Algorithm::Naive, false);
+    assert_eq!(output.all_errors().len(), 4);
+
+    let ranges: Vec<_> = output
+        .error_ranges(&facts)
+        .into_iter()
+        .map(|(loan, first, last)| {
+            (
+                tables.loans.untern(loan),
+                tables.points.untern(first),
+                tables.points.untern(last),
+            )
+        })
+        .collect();
+    assert_eq!(
+        ranges,
+        [
+            ("L0", "\"Start(B0[1])\"", "\"Start(B0[2])\""),
+            ("L0", "\"Mid(B0[3])\"", "\"Mid(B0[3])\""),
+        ]
+    );
+}
+
+#[test]
+fn invalidation_points_in_cfg_order() {
+    let program = r"
+        universal_regions { }
+
+        block B0 {
+            invalidates(L0);
+            goto B2;
+        }
+
+        block B1 {
+            invalidates(L1), invalidates(L0);
+        }
+
+        block B2 {
+            invalidates(L1);
+            invalidates(L0) / invalidates(L0);
+            goto B1;
+        }
+    ";
+
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    let loan = tables.loans.intern("L0");
+    let points: Vec<_> = facts
+        .invalidation_points(loan)
+        .into_iter()
+        .map(|p| tables.points.untern(p))
+        .collect();
+    assert_eq!(
+        points,
+        [
+            "\"Mid(B0[0])\"",
+            "\"Start(B2[1])\"",
+            "\"Mid(B2[1])\"",
+            "\"Mid(B1[0])\"",
+        ]
+    );
+}
+
+#[test]
+fn join_points_in_cfg_order() {
+    let program = r"
+        universal_regions { }
+
+        block B0 {
+            var_used(V0);
+            goto B1, B2;
+        }
+
+        block B1 {
+            var_used(V0);
+            goto B3;
+        }
+
+        block B2 {
+            var_used(V0);
+            goto B3;
+        }
+
+        block B3 {
+            var_used(V0);
+            goto B1;
+        }
+    ";
+
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    let points: Vec<_> = facts
+        .join_points()
+        .into_iter()
+        .map(|p| tables.points.untern(p))
+        .collect();
+    assert_eq!(points, ["\"Start(B1[0])\"", "\"Start(B3[0])\""]);
+}
+
+#[test]
+fn dead_regions() {
+    let (facts, tables) = load_fixture("smoke-test", "foo");
+
+    let output = Output::compute(&facts, Algorithm::Naive, true);
+    let dead_regions: Vec<_> = output
+        .dead_regions(&facts)
+        .into_iter()
+        .map(|r| tables.regions.untern(r))
+        .collect();
+    assert_eq!(dead_regions, ["\"\\'_#4r\"", "\"\\'_#6r\""]);
+}
+
+#[test]
+fn loan_death_points() {
+    // `L0` is live until the end of `B1`, and only along the `B0 -> B1` path.
+    let program = r"
+        universal_regions { }
+
+        block B0 {
+            borrow_region_at('a, L0);
+            region_live_at('a);
+            goto B1, B2;
+        }
+
+        block B1 {
+            region_live_at('a);
+            goto B3;
+        }
+
+        block B2 {
+            ;
+            goto B3;
+        }
+
+        block B3 {
+            invalidates(L0);
+        }
+    ";
+
+    let (output, mut tables) = program::run(program, Algorithm::Naive).unwrap();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    let loan = tables.loans.intern("L0");
+    let death_points: Vec<_> = output
+        .loan_death_points(loan, &facts)
+        .into_iter()
+        .map(|p| tables.points.untern(p))
+        .collect();
+    assert_eq!(death_points, ["\"Mid(B0[1])\"", "\"Mid(B1[0])\""]);
+    assert!(output.errors.is_empty());
+
+    // The death points are the last points of the scope on each path.
+    let scope: BTreeSet<Point> = [
+        "\"Start(B0[1])\"",
+        "\"Mid(B0[1])\"",
+        "\"Start(B1[0])\"",
+        "\"Mid(B1[0])\"",
+    ]
+    .iter()
+    .map(|name| tables.points.intern(name))
+    .collect();
+    assert_eq!(output.loan_scope(loan), scope);
+}
+
+#[test]
+fn regions_live_without_loans() {
+    let program = r"
+        universal_regions { }
+
+        block B0 {
+            region_live_at('a);
+            borrow_region_at('b, L0), region_live_at('b);
+        }
+    ";
+
+    let (output, tables) = program::run(program, Algorithm::Naive).unwrap();
+    let points: Vec<_> = output
+        .region_live_no_loans()
+        .into_iter()
+        .map(|p| tables.points.untern(p))
+        .collect();
+    assert_eq!(
+        points,
+        ["\"Start(B0[0])\"", "\"Mid(B0[0])\"", "\"Start(B0[1])\""]
+    );
+}
+
+#[test]
+fn cost_estimate() {
+    let program = r"
+        universal_regions { 'a }
+
+        block B0 {
+            borrow_region_at('b, L0), outlives('b: 'c);
+            borrow_region_at('c, L1), outlives('c: 'a), region_live_at('d);
+        }
+    ";
+
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    assert_eq!(
+        Output::estimate_cost(&facts),
+        CostEstimate {
+            points: 4,
+            regions: 4,
+            loans: 2,
+            outlives: 2,
+            work: 32,
+        }
+    );
+}
+
+#[test]
+fn universal_regions_are_kept_in_the_output() {
+    let program = r"
+        universal_regions { 'a, 'b }
+
+        block B0 {
+            outlives('c: 'a);
+        }
+    ";
+
+    for &algorithm in &[
+        Algorithm::Naive,
+        Algorithm::DatafrogOpt,
+        Algorithm::LocationInsensitive,
+    ] {
+        let (output, tables) = program::run(program, algorithm).unwrap();
+        let universal_regions: Vec<_> = output
+            .universal_regions()
+            .iter()
+            .map(|&r| tables.regions.untern(r))
+            .collect();
+        assert_eq!(universal_regions, ["'a", "'b"]);
+    }
+}
+
+#[test]
+fn cfg_graphviz_annotations() {
+    let program = r"
+        universal_regions { }
+
+        block B0 {
+            borrow_region_at('a, L0);
+            invalidates(L0), region_live_at('a);
+        }
+    ";
+
+    let (output, mut tables) = program::run(program, Algorithm::Naive).unwrap();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+
+    let plain = dump::render_cfg_graphviz(&facts, None, &tables);
+    assert!(plain.contains("\"node0\" -> \"node1\""));
+    assert!(!plain.contains("errors"));
+
+    let annotated = dump::render_cfg_graphviz(&facts, Some(&output), &tables);
+    let error_point = tables.points.intern("\"Mid(B0[1])\"");
+    assert!(annotated.contains(&format!(
+        "\"node{}\" [label = \"\\\"Mid\\(B0[1]\\)\\\"\\lerrors: 1\\llive loans: 1\\l\" \
+         style = \"filled\" fillcolor = \"#FD6467\"]",
+        error_point.index()
+    )));
+}
+
+#[test]
+fn subset_graphviz() {
+    let program = r"
+        universal_regions { }
+        block B0 {
+            outlives('a: 'b);
+            outlives('b: 'c);
+        }
+    ";
+    let (mut output, mut tables) = program::run(program, Algorithm::Naive).unwrap();
+    let (a, b) = (tables.regions.intern("'a"), tables.regions.intern("'b"));
+    let mid = tables.points.intern("\"Mid(B0[0])\"");
+
+    assert_eq!(
+        dump::subset_to_dot(&output, mid, &tables),
+        "digraph g {\n\
+         \"region0\" [label = \"'a\"];\n\
+         \"region1\" [label = \"'b\"];\n\
+         \"region0\" -> \"region1\";\n\
+         }"
+    );
+
+    // A subset symmetry is highlighted.
+    output
+        .subset
+        .get_mut(&mid)
+        .unwrap()
+        .get_mut(&a)
+        .unwrap()
+        .insert(a);
+    let dot = dump::subset_to_dot(&output, mid, &tables);
+    assert!(dot.contains(&format!(
+        "\"region{0}\" -> \"region{0}\" [color = \"#FD6467\" penwidth = 3];",
+        a.index()
+    )));
+    assert!(dot.contains(&format!(
+        "\"region{}\" -> \"region{}\";",
+        a.index(),
+        b.index()
+    )));
+
+    let start = tables.points.intern("\"Start(B0[0])\"");
+    assert_eq!(
+        dump::subset_to_dot(&output, start, &tables),
+        "digraph g {\n}"
+    );
+}
+
+#[test]
+fn opt_matches_naive_or_dump() {
+    let (facts, tables) = load_fixture("issue-47680", "main");
+    assert_opt_matches_naive_or_dump(&facts, &tables);
+}
+
+#[test]
+fn opt_subset_matches_naive() {
+    let (facts, tables) = load_fixture("smoke-test", "use_while_mut");
+    assert_opt_subset_matches_naive(&facts, &tables);
+}
+
+#[test]
+#[should_panic(expected = "DatafrogOpt and Naive subsets differ")]
+fn opt_subset_differs_from_naive() {
+    // `DatafrogOpt` only computes the subsets it needs to find the errors of `foo`.
+    let (facts, tables) = load_fixture("smoke-test", "foo");
+    assert_opt_subset_matches_naive(&facts, &tables);
+}
+
+#[test]
+fn location_insensitive_errors_are_a_superset_of_naive_on_random_facts() {
+    let cases_with_errors = Cell::new(0);
+    check_random_facts(50, |facts| {
+        let naive = Output::compute(facts, Algorithm::Naive, false);
+        let insensitive = Output::compute(facts, Algorithm::LocationInsensitive, false);
+        if !naive.errors.is_empty() {
+            cases_with_errors.set(cases_with_errors.get() + 1);
+        }
+
+        naive
+            .errors
+            .iter()
+            .all(|(point, loans)| match insensitive.errors.get(point) {
+                Some(insensitive_loans) => {
+                    loans.iter().all(|loan| insensitive_loans.contains(loan))
+                }
+                None => false,
+            })
+    });
+
+    // The property is only meaningful if the random programs have errors.
+    assert!(cases_with_errors.get() > 0);
+}
+
+#[test]
+fn normalized_facts_are_canonical() {
+    // The same facts, in a different order, with a duplicate, and with the atoms interned in a
+    // different order.
+    let programs = [
+        r"
+        universal_regions { 'b, 'a }
+
+        block B0 {
+            outlives('b: 'c), outlives('a: 'c), outlives('b: 'c);
+        }
+    ",
+        r"
+        universal_regions { 'a, 'b }
+
+        block B0 {
+            outlives('a: 'c), outlives('b: 'c);
+        }
+    ",
+    ];
+
+    let written_facts: Vec<Vec<String>> = programs
+        .iter()
+        .enumerate()
+        .map(|(index, program)| {
+            let mut tables = intern::InternerTables::new();
+            let mut facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+            tab_delim::normalize_facts(&mut facts, &tables);
+
+            let facts_dir = std::env::temp_dir().join(format!(
+                "polonius-normalized-{}-{}",
+                std::process::id(),
+                index
+            ));
+            tab_delim::write_tab_delimited_facts(&facts, &tables, &facts_dir)
+                .expect("failed to write facts");
+            let contents = ["universal_region.facts", "outlives.facts"]
+                .iter()
+                .map(|file| fs::read_to_string(facts_dir.join(file)).expect("facts"))
+                .collect();
+            fs::remove_dir_all(&facts_dir).expect("failed to remove facts");
+            contents
+        })
+        .collect();
+
+    assert_eq!(
+        written_facts[0],
+        [
+            "'a\n'b\n",
+            "'a\t'c\t\"Mid(B0[0])\"\n'b\t'c\t\"Mid(B0[0])\"\n"
+        ]
+    );
+    assert_eq!(written_facts[0], written_facts[1]);
+}
+
+#[test]
+fn minimized_facts_round_trip() {
+    let program = r"
+        universal_regions { 'static }
+
+        block B0 {
+            borrow_region_at('a, L0), outlives('a: 'b), region_live_at('b);
+            borrow_region_at('c, L1), invalidates(L1), region_live_at('c);
+            invalidates(L0);
+        }
+    ";
+
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    let has_errors = |facts: &AllFacts| {
+        !Output::compute(facts, Algorithm::Naive, false)
+            .errors
+            .is_empty()
+    };
+
+    // Only the error of `L1` needs to be kept.
+    let reduced_facts = facts.minimize(has_errors);
+    assert_eq!(reduced_facts.borrow_region.len(), 1);
+    assert_eq!(reduced_facts.invalidates.len(), 1);
+    assert_eq!(reduced_facts.region_live_at.len(), 1);
+    assert!(reduced_facts.universal_region.is_empty());
+    assert!(reduced_facts.cfg_edge.is_empty());
+    assert!(reduced_facts.outlives.is_empty());
+
+    let facts_dir =
+        std::env::temp_dir().join(format!("polonius-round-trip-{}", std::process::id()));
+    tab_delim::write_tab_delimited_facts(&reduced_facts, &tables, &facts_dir)
+        .expect("failed to write facts");
+    let mut loaded_tables = intern::InternerTables::new();
+    let loaded_facts =
+        tab_delim::load_tab_delimited_facts(&mut loaded_tables, &facts_dir).expect("facts");
+    fs::remove_dir_all(&facts_dir).expect("failed to remove facts");
+
+    let errors = Output::compute(&loaded_facts, Algorithm::Naive, false).errors;
+    let (&point, loans) = errors.iter().next().unwrap();
+    assert_eq!(loaded_tables.points.untern(point), "\"Mid(B0[1])\"");
+    assert_eq!(loaded_tables.loans.untern(loans[0]), "L1");
+}
+
+/// Builds a ustar archive of the given files. The checksums are not computed, as they are not
+/// verified when loading facts.
+fn tar_archive(files: &[(String, Vec<u8>)]) -> Vec<u8> {
+    let mut archive = Vec::new();
+    for (path, contents) in files {
+        let mut header = [0; 512];
+        header[..path.len()].copy_from_slice(path.as_bytes());
+        header[124..135].copy_from_slice(format!("{:011o}", contents.len()).as_bytes());
+        header[156] = b'0';
+        header[257..262].copy_from_slice(b"ustar");
+        archive.extend_from_slice(&header);
+        archive.extend_from_slice(contents);
+        archive.resize(archive.len().div_ceil(512) * 512, 0);
+    }
+    archive.resize(archive.len() + 1024, 0);
+    archive
+}
+
+#[test]
+fn load_facts_with_a_custom_interner() {
+    /// A single name space for all the atoms, whatever their kind.
+    #[derive(Default)]
+    struct SharedNames {
+        names: Vec<String>,
+    }
+
+    impl SharedNames {
+        fn intern(&mut self, name: &str) -> usize {
+            match self.names.iter().position(|n| n == name) {
+                Some(index) => index,
+                None => {
+                    self.names.push(name.to_string());
+                    self.names.len() - 1
+                }
+            }
+        }
+    }
+
+    impl intern::AtomInterner for SharedNames {
+        fn intern_region(&mut self, name: &str) -> Region {
+            self.intern(name).into()
+        }
+
+        fn intern_loan(&mut self, name: &str) -> Loan {
+            self.intern(name).into()
+        }
+
+        fn intern_point(&mut self, name: &str) -> Point {
+            self.intern(name).into()
+        }
+
+        fn intern_variable(&mut self, name: &str) -> Variable {
+            self.intern(name).into()
+        }
+    }
+
+    let facts_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("inputs")
+        .join("smoke-test")
+        .join("nll-facts")
+        .join("foo");
+    let mut names = SharedNames::default();
+    let facts = tab_delim::load_tab_delimited_facts(&mut names, &facts_dir).expect("facts");
+
+    let tables = &mut intern::InternerTables::new();
+    let expected_facts = tab_delim::load_tab_delimited_facts(tables, &facts_dir).expect("facts");
+    assert_eq!(
+        facts.borrow_region.len(),
+        expected_facts.borrow_region.len()
+    );
+    for (&(r, l, p), &(expected_r, expected_l, expected_p)) in facts
+        .borrow_region
+        .iter()
+        .zip(&expected_facts.borrow_region)
+    {
+        assert_eq!(names.names[r.index()], tables.regions.untern(expected_r));
+        assert_eq!(names.names[l.index()], tables.loans.untern(expected_l));
+        assert_eq!(names.names[p.index()], tables.points.untern(expected_p));
+    }
+}
+
+#[test]
+fn errors_with_point_lines() {
+    let program = r"
+        universal_regions { }
+
+        block B0 {
+            borrow_region_at('a, L0), borrow_region_at('b, L1);
+            invalidates(L0), region_live_at('a), region_live_at('b);
+            invalidates(L1), region_live_at('b);
+        }
+    ";
+
+    let (output, mut tables) = program::run(program, Algorithm::Naive).unwrap();
+    let facts_dir =
+        std::env::temp_dir().join(format!("polonius-point-lines-{}", std::process::id()));
+    fs::create_dir_all(&facts_dir).expect("failed to create facts");
+    fs::write(facts_dir.join("point_line.facts"), "\"Mid(B0[1])\"\t12\n")
+        .expect("failed to write facts");
+    let lines = tab_delim::load_point_lines(&mut tables, &facts_dir).expect("facts");
+    fs::remove_dir_all(&facts_dir).expect("failed to remove facts");
+
+    let errors: Vec<_> = output
+        .errors_with_lines(&lines)
+        .into_iter()
+        .map(|(p, l, line)| (tables.points.untern(p), tables.loans.untern(l), line))
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            ("\"Mid(B0[1])\"", "L0", Some(12)),
+            ("\"Mid(B0[2])\"", "L1", None),
+        ]
+    );
+
+    // The file is optional.
+    let lines = tab_delim::load_point_lines(&mut tables, Path::new("/nonexistent")).unwrap();
+    assert!(lines.is_empty());
+}
+
+#[test]
+fn canonicalized_region_names() {
+    let mut tables = intern::InternerTables::new();
+    let r0 = tables.regions.intern("'_#0r");
+    let r1 = tables.regions.intern("'_#1r");
+    tables.canonicalize_regions(|name| name.replace("_#", "").trim_end_matches('r').to_string());
+
+    assert_eq!(tables.regions.untern(r0), "'0");
+    assert_eq!(tables.regions.untern(r1), "'1");
+    assert_eq!(tables.regions.intern("'1"), r1);
+    assert_ne!(tables.regions.intern("'_#1r"), r1);
+}
+
+#[test]
+fn load_combined_facts() {
+    let facts_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("inputs")
+        .join("smoke-test")
+        .join("nll-facts")
+        .join("foo");
+
+    // Combine the relations in reverse order, with one line per tuple.
+    let mut paths: Vec<_> = fs::read_dir(&facts_dir)
+        .expect("facts")
+        .map(|entry| entry.expect("facts").path())
+        .filter(|path| path.extension() == Some("facts".as_ref()))
+        .collect();
+    paths.sort();
+    paths.reverse();
+    let mut combined = String::new();
+    for path in &paths {
+        let relation = path.file_stem().unwrap().to_str().unwrap();
+        for line in fs::read_to_string(path).expect("facts").lines() {
+            combined.push_str(&format!("{}\t{}\n", relation, line));
+        }
+    }
+    let combined_file =
+        std::env::temp_dir().join(format!("polonius-combined-{}.facts", std::process::id()));
+    fs::write(&combined_file, &combined).expect("failed to write facts");
+
+    let tables = &mut intern::InternerTables::new();
+    let facts = tab_delim::load_tab_delimited_facts(tables, &facts_dir).expect("facts");
+    let combined_tables = &mut intern::InternerTables::new();
+    let combined_facts = tab_delim::load_combined(combined_tables, &combined_file).expect("facts");
+    assert_eq!(format!("{:?}", combined_facts), format!("{:?}", facts));
+
+    fs::write(&combined_file, "borrow_regions\t'a\tL0\t\"Mid(bb0[0])\"\n")
+        .expect("failed to write facts");
+    let error = tab_delim::load_combined(combined_tables, &combined_file).unwrap_err();
+    fs::remove_file(&combined_file).expect("failed to remove facts");
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn load_facts_from_tar() {
+    let facts_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("inputs")
+        .join("smoke-test")
+        .join("nll-facts");
+
+    let mut files = Vec::new();
+    for function in &["foo", "main"] {
+        for entry in fs::read_dir(facts_dir.join(function)).expect("facts") {
+            let path = entry.expect("facts").path();
+            let file_name = path.file_name().unwrap().to_str().unwrap();
+            files.push((
+                format!("nll-facts/{}/{}", function, file_name),
+                fs::read(&path).expect("facts"),
+            ));
+        }
+    }
+    let archive = tar_archive(&files);
+
+    let tables = &mut intern::InternerTables::new();
+    let facts = tab_delim::load_tab_delimited_facts(tables, &facts_dir.join("foo")).expect("facts");
+
+    let tar_tables = &mut intern::InternerTables::new();
+    let tar_facts = tab_delim::load_from_tar(tar_tables, &archive[..], "foo").expect("facts");
+    assert_eq!(format!("{:?}", tar_facts), format!("{:?}", facts));
+
+    let error = tab_delim::load_from_tar(tar_tables, &archive[..], "baz").unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn drop_conflicts_are_classified() {
+    for fn_name in &["finish_1", "finish_2", "finish_3"] {
+        let (facts, _) = load_fixture("issue-52059-report-when-borrow-and-drop-conflict", fn_name);
+        let output = Output::compute(&facts, Algorithm::Naive, true);
+
+        assert!(!output.errors.is_empty());
+        assert_eq!(output.errors_classified.len(), output.errors.len());
+        let undumped = Output::compute(&facts, Algorithm::Naive, false);
+        assert!(undumped.errors_classified.is_empty());
+        for classified in output.errors_classified.values() {
+            for &(_, kind) in classified {
+                assert_eq!(kind, ErrorKind::DropConflict);
+            }
+        }
+    }
+}
+
+#[test]
+fn use_errors_are_classified() {
+    let program = r"
+        universal_regions { 'static }
+
+        block B0 {
+            borrow_region_at('a, L0), outlives('a: 'static);
+            borrow_region_at('b, L1), region_live_at('b);
+            invalidates(L0), invalidates(L1), region_live_at('b);
+        }
+    ";
+
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    let output = Output::compute(&facts, Algorithm::Naive, true);
+    let point = tables.points.intern("\"Mid(B0[2])\"");
+    let mut errors = output.errors_classified[&point].clone();
+    errors.sort();
+    assert_eq!(
+        errors,
+        [
+            (tables.loans.intern("L0"), ErrorKind::EscapingBorrow),
+            (tables.loans.intern("L1"), ErrorKind::ConflictingUse),
+        ]
+    );
+}
+
+#[test]
+fn use_errors_before_unrelated_drops_are_classified() {
+    let program = r"
+        universal_regions { }
+
+        block B0 {
+            borrow_region_at('a, L0), region_live_at('a);
+            invalidates(L0), region_live_at('a);
+            var_defined(V1);
+        }
+    ";
+
+    let mut tables = intern::InternerTables::new();
+    let mut facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    let output = Output::compute(&facts, Algorithm::Naive, true);
+    let error_point = tables.points.intern("\"Mid(B0[1])\"");
+    let l0 = tables.loans.intern("L0");
+    assert_eq!(output.errors_at(error_point), [l0]);
+    assert_eq!(
+        output.errors_classified[&error_point],
+        [(l0, ErrorKind::ConflictingUse)]
+    );
+
+    // `V1`, which doesn't hold `L0`, is dropped right after the error.
+    let v1 = tables.variables.intern("V1");
+    facts
+        .var_drop_used
+        .push((v1, tables.points.intern("\"Start(B0[2])\"")));
+    facts
+        .var_drops_region
+        .push((v1, tables.regions.intern("'b")));
+    let output = Output::compute(&facts, Algorithm::Naive, true);
+    assert_eq!(
+        output.errors_classified[&error_point],
+        [(l0, ErrorKind::ConflictingUse)]
+    );
+
+    // Once it holds `L0`, the error is a drop conflict.
+    facts
+        .var_drops_region
+        .push((v1, tables.regions.intern("'a")));
+    let output = Output::compute(&facts, Algorithm::Naive, true);
+    assert_eq!(
+        output.errors_classified[&error_point],
+        [(l0, ErrorKind::DropConflict)]
+    );
+}
+
+#[test]
+fn overlapping_loans_conflict() {
+    let program = r"
+        universal_regions { }
+
+        block B0 {
+            borrow_region_at('a, L0), region_live_at('a);
+            borrow_region_at('b, L1), region_live_at('a), region_live_at('b);
+            region_live_at('a), region_live_at('b);
+            borrow_region_at('c, L2), region_live_at('c);
+        }
+    ";
+
+    let (output, mut tables) = program::run(program, Algorithm::Naive).unwrap();
+    let l0 = tables.loans.intern("L0");
+    let l1 = tables.loans.intern("L1");
+    let l2 = tables.loans.intern("L2");
+
+    let point = output.loans_conflict(l0, l1).expect("no conflict");
+    assert_eq!(tables.points.untern(point), "\"Mid(B0[1])\"");
+    assert_eq!(output.loans_conflict(l1, l0), Some(point));
+    assert_eq!(output.loans_conflict(l0, l2), None);
+}
+
+#[test]
+fn two_phase_borrow_facts() {
+    let program = r"
+        universal_regions { }
+
+        block B0 {
+            borrow_region_at('a, L0), borrow_reserved_at(L0);
+            region_live_at('a);
+            borrow_activated_at(L0), region_live_at('a);
+        }
+    ";
+
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    let l0 = tables.loans.intern("L0");
+    assert_eq!(
+        facts.borrow_reserved_at,
+        [(l0, tables.points.intern("\"Mid(B0[0])\""))]
+    );
+    assert_eq!(
+        facts.borrow_activated_at,
+        [(l0, tables.points.intern("\"Mid(B0[2])\""))]
+    );
+
+    // The relations are loaded when their files are present.
+    let facts_dir = std::env::temp_dir().join(format!("polonius-two-phase-{}", std::process::id()));
+    tab_delim::write_tab_delimited_facts(&facts, &tables, &facts_dir)
+        .expect("failed to write facts");
+    let mut loaded_tables = intern::InternerTables::new();
+    let loaded_facts =
+        tab_delim::load_tab_delimited_facts(&mut loaded_tables, &facts_dir).expect("facts");
+    fs::remove_dir_all(&facts_dir).expect("failed to remove facts");
+    assert_eq!(loaded_facts.borrow_reserved_at.len(), 1);
+    assert_eq!(loaded_facts.borrow_activated_at.len(), 1);
+}
+
+#[test]
+fn facts_schema_version() {
+    let program = r"
+        universal_regions { }
+
+        block B0 {
+            borrow_region_at('a, L0), region_live_at('a);
+            invalidates(L0), region_live_at('a);
+        }
+    ";
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    let facts_dir = std::env::temp_dir().join(format!("polonius-schema-{}", std::process::id()));
+    tab_delim::write_tab_delimited_facts(&facts, &tables, &facts_dir)
+        .expect("failed to write facts");
+    assert_eq!(
+        tab_delim::schema_version(&facts_dir).expect("schema"),
+        Some(FACTS_SCHEMA_VERSION)
+    );
+
+    // Stale or invalid versions are only warned about, and the facts still load.
+    for &(schema, expected) in &[
+        ("0\n", Ok(Some(0))),
+        ("new\n", Err(std::io::ErrorKind::InvalidData)),
+    ] {
+        fs::write(facts_dir.join("schema.txt"), schema).expect("failed to write schema");
+        let version = tab_delim::schema_version(&facts_dir).map_err(|e| e.kind());
+        assert_eq!(version, expected);
+        let mut loaded_tables = intern::InternerTables::new();
+        let loaded_facts =
+            tab_delim::load_tab_delimited_facts(&mut loaded_tables, &facts_dir).expect("facts");
+        assert_eq!(loaded_facts.invalidates.len(), 1);
+    }
+
+    // Facts extracted by rustc have no schema.
+    fs::remove_file(facts_dir.join("schema.txt")).expect("failed to remove schema");
+    assert_eq!(tab_delim::schema_version(&facts_dir).expect("schema"), None);
+    fs::remove_dir_all(&facts_dir).expect("failed to remove facts");
+}
+
+#[test]
+fn outlives_cycles() {
+    let program = r"
+        universal_regions { }
+
+        block B0 {
+            outlives('a: 'b), outlives('b: 'c), outlives('c: 'a), outlives('c: 'd), outlives('e: 'e);
+            outlives('d: 'f), outlives('f: 'd), outlives('a: 'b);
+        }
+    ";
+
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    let cycles_at = |facts: &AllFacts, tables: &mut intern::InternerTables, point: &str| {
+        let point = tables.points.intern(point);
+        facts
+            .outlives_cycles_at(point)
+            .into_iter()
+            .map(|cycle| {
+                let mut cycle: Vec<_> = cycle
+                    .into_iter()
+                    .map(|r| tables.regions.untern(r).to_string())
+                    .collect();
+                cycle.sort();
+                cycle
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        cycles_at(&facts, &mut tables, "\"Mid(B0[0])\""),
+        [vec!["'a", "'b", "'c"]]
+    );
+    assert_eq!(
+        cycles_at(&facts, &mut tables, "\"Mid(B0[1])\""),
+        [vec!["'d", "'f"]]
+    );
+    assert!(cycles_at(&facts, &mut tables, "\"Start(B0[0])\"").is_empty());
+}
+
+#[test]
+fn error_fallout_is_forward_reachable() {
+    let program = r"
+        universal_regions { }
+
+        block B0 {
+            borrow_region_at('a, L0);
+            invalidates(L0), region_live_at('a);
+            goto B1, B2;
+        }
+
+        block B1 {
+            ;
+            goto B3;
+        }
+
+        block B2 {
+            ;
+            goto B3;
+        }
+
+        block B3 {
+            ;
+            goto B1;
+        }
+    ";
+
+    let (output, mut tables) = program::run(program, Algorithm::Naive).unwrap();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    let error_point = tables.points.intern("\"Mid(B0[1])\"");
+
+    let fallout: BTreeSet<_> = output
+        .error_fallout(error_point, &facts)
+        .into_iter()
+        .map(|p| tables.points.untern(p))
+        .collect();
+    let expected: BTreeSet<_> = [
+        "\"Mid(B0[1])\"",
+        "\"Start(B1[0])\"",
+        "\"Mid(B1[0])\"",
+        "\"Start(B2[0])\"",
+        "\"Mid(B2[0])\"",
+        "\"Start(B3[0])\"",
+        "\"Mid(B3[0])\"",
+    ]
+    .iter()
+    .cloned()
+    .collect();
+    assert_eq!(fallout, expected);
+
+    let no_error_point = tables.points.intern("\"Mid(B0[0])\"");
+    assert!(output.error_fallout(no_error_point, &facts).is_empty());
+}
+
+#[test]
+fn liveness_diff_between_extractions() {
+    let (mut facts, _) = load_fixture("drop-liveness", "main");
+    facts.region_live_at = Vec::default();
+    let output = Output::compute(&facts, Algorithm::Naive, true);
+    assert!(Output::liveness_diff(&output, &output).is_empty());
+
+    // An extraction missing the last use of a variable.
+    let mut changed_facts = facts.clone();
+    let (variable, point) = changed_facts.var_used.pop().unwrap();
+    let changed_output = Output::compute(&changed_facts, Algorithm::Naive, true);
+
+    let diff = Output::liveness_diff(&output, &changed_output);
+    assert!(!diff.is_empty());
+    let (only_before, only_after) = &diff.var_live_at[&point];
+    assert!(only_before.contains(&variable));
+    assert!(only_after.is_empty());
+}
+
+#[test]
+fn escaping_closure_requirements() {
+    // The pattern of `escape_upvar_nested`, where the closure requires the region of the
+    // captured borrow to outlive it.
+    let program = r"
+        universal_regions { }
+        block B0 {
+            borrow_region_at('a, L0), outlives('a: 'b), outlives('b: 'c), region_live_at('c),
+            closure_requires('c), closure_requires('d);
+        }
+    ";
+
+    let (output, mut tables) = program::run(program, Algorithm::Naive).unwrap();
+    let point = tables.points.intern("\"Mid(B0[0])\"");
+    let c = tables.regions.intern("'c");
+    assert_eq!(output.closure_errors.len(), 1);
+    assert_eq!(output.closure_errors[&point], [c]);
+}
+
+#[test]
+fn verbose_relations_over_budget_are_truncated() {
+    let (facts, _) = load_fixture(
+        "issue-52059-report-when-borrow-and-drop-conflict",
+        "finish_1",
+    );
+
+    for &algorithm in &[
+        Algorithm::Naive,
+        Algorithm::DatafrogOpt,
+        Algorithm::LocationInsensitive,
+    ] {
+        let output = Output::compute(&facts, algorithm, true);
+        assert!(!output.truncated);
+        assert!(!output.restricts.is_empty() || !output.restricts_anywhere.is_empty());
+
+        let options = ComputeOptions {
+            dump_enabled: true,
+            max_output_tuples: Some(1),
+            ..ComputeOptions::default()
+        };
+        let truncated_output = Output::compute_with_options(&facts, algorithm, options);
+        assert!(truncated_output.truncated);
+        assert!(truncated_output.restricts.is_empty());
+        assert!(truncated_output.restricts_anywhere.is_empty());
+        assert!(truncated_output.subset_anywhere.is_empty());
+        assert_equal(&output.errors, &truncated_output.errors);
+
+        let options = ComputeOptions {
+            dump_enabled: true,
+            max_output_tuples: Some(usize::MAX),
+            ..ComputeOptions::default()
+        };
+        let output_within_budget = Output::compute_with_options(&facts, algorithm, options);
+        assert!(!output_within_budget.truncated);
+        assert_equal(&output.restricts, &output_within_budget.restricts);
+    }
+}
+
+#[test]
+fn trace_derived_tuples() {
+    let program = r"
+        universal_regions { }
+
+        block B0 {
+            borrow_region_at('a, L0);
+            invalidates(L0), region_live_at('a);
+        }
+    ";
+
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    let l0 = tables.loans.intern("L0").index();
+    let p = tables.points.intern("\"Mid(B0[1])\"").index();
+
+    // Nothing is traced by default.
+    assert!(ComputeOptions::default().trace.is_none());
+
+    let sink = Arc::new(Mutex::new(Vec::new()));
+    for &algorithm in &[Algorithm::Naive, Algorithm::DatafrogOpt] {
+        let options = ComputeOptions {
+            trace: Some(Trace::new(sink.clone(), TraceFormat::Ndjson)),
+            ..ComputeOptions::default()
+        };
+        Output::compute_with_options(&facts, algorithm, options);
+    }
+
+    let trace = String::from_utf8(sink.lock().unwrap().clone()).unwrap();
+    // The algorithms take a different number of rounds to derive the error.
+    let errors: Vec<_> = trace
+        .lines()
+        .filter(|line| line.contains("\"relation\":\"errors\""))
+        .collect();
+    assert_eq!(errors.len(), 2);
+    for (line, algorithm) in errors.iter().zip(&["naive", "datafrog_opt"]) {
+        let prefix = format!("{{\"algorithm\":\"{}\",\"round\":", algorithm);
+        let suffix = format!(",\"relation\":\"errors\",\"tuple\":[{},{}]}}", l0, p);
+        assert!(
+            line.starts_with(&prefix) && line.ends_with(&suffix),
+            "{}",
+            line
+        );
+    }
+
+    let sink = Arc::new(Mutex::new(Vec::new()));
+    let options = ComputeOptions {
+        trace: Some(Trace::new(sink.clone(), TraceFormat::Text)),
+        ..ComputeOptions::default()
+    };
+    Output::compute_with_options(&facts, Algorithm::Naive, options);
+    let trace = String::from_utf8(sink.lock().unwrap().clone()).unwrap();
+    let suffix = format!(": errors({}, {})", l0, p);
+    assert!(trace
+        .lines()
+        .any(|line| line.starts_with("naive round ") && line.ends_with(&suffix)));
+}
+
+#[test]
+fn region_equivalence_classes() {
+    // `'a` and `'b` outlive each other, `'c` is only outlived by them.
+    let program = r"
+        universal_regions { }
+        block B0 {
+            outlives('a: 'b), outlives('b: 'a), outlives('a: 'c);
+        }
+    ";
+
+    let classes_of = |program| {
+        let (output, tables) = program::run(program, Algorithm::Naive).unwrap();
+        let classes: Vec<Vec<_>> = output
+            .region_equivalence_classes()
+            .iter()
+            .map(|class| {
+                class
+                    .iter()
+                    .map(|&r| tables.regions.untern(r).to_string())
+                    .collect()
+            })
+            .collect();
+        classes
+    };
+    assert_eq!(classes_of(program), [vec!["'a", "'b"], vec!["'c"]]);
+
+    // `'a` and `'b` are no longer equivalent if only one outlives the other at some point.
+    let program = r"
+        universal_regions { }
+        block B0 {
+            outlives('a: 'b), outlives('b: 'a), outlives('a: 'c);
+            outlives('a: 'b);
+        }
+    ";
+    assert_eq!(classes_of(program), [vec!["'a"], vec!["'b"], vec!["'c"]]);
+}
+
+#[test]
+fn errors_with_added_outlives() {
+    let program = r"
+        universal_regions { }
+        block B0 {
+            borrow_region_at('a, L0), outlives('a: 'b), outlives('b: 'c);
+            invalidates(L0), region_live_at('c);
+        }
+    ";
+
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    let mid = tables.points.intern("\"Mid(B0[0])\"");
+    let (a, b, c) = (
+        tables.regions.intern("'a"),
+        tables.regions.intern("'b"),
+        tables.regions.intern("'c"),
+    );
+
+    for &algorithm in &[Algorithm::Naive, Algorithm::DatafrogOpt] {
+        let output = Output::compute(&facts, algorithm, false);
+        assert_eq!(output.errors.len(), 1);
+
+        // `'a: 'c` already follows from `'a: 'b` and `'b: 'c`.
+        let redundant = Output::with_added_outlives(&facts, (a, c, mid), algorithm);
+        assert_equal(&output.errors, &redundant.errors);
+
+        let duplicate = Output::with_added_outlives(&facts, (a, b, mid), algorithm);
+        assert_equal(&output.errors, &duplicate.errors);
+    }
+
+    // The facts themselves are left untouched.
+    assert_eq!(facts.outlives.len(), 2);
+}
+
+#[test]
+fn errors_with_added_kill() {
+    let (facts, _) = load_fixture("smoke-test", "use_while_mut");
+    let kills = facts.killed.len();
+
+    for &algorithm in &[Algorithm::Naive, Algorithm::DatafrogOpt] {
+        let output = Output::compute(&facts, algorithm, false);
+        let errors = output.all_errors();
+        assert_eq!(errors.len(), 1);
+        let (error_point, loan) = errors[0];
+
+        // Killing the loan where it is invalidated is too late to remove the error.
+        let late = Output::with_added_kill(&facts, loan, error_point, algorithm);
+        assert_equal(&output.errors, &late.errors);
+
+        // Killing it where it is issued, it doesn't flow to the invalidation anymore.
+        let (_, borrow_point) = facts.loan_origin(loan).expect("loan without origin");
+        let early = Output::with_added_kill(&facts, loan, borrow_point, algorithm);
+        assert!(early.all_errors().is_empty(), "{:?}", algorithm);
+    }
+
+    // The facts themselves are left untouched.
+    assert_eq!(facts.killed.len(), kills);
+}
+
+#[test]
+fn self_invalidating_loans() {
+    let (facts, tables) = load_fixture("smoke-test", "use_while_mut");
+
+    for &algorithm in &[Algorithm::Naive, Algorithm::DatafrogOpt] {
+        let output = Output::compute(&facts, algorithm, true);
+        let loans: Vec<(&str, &str)> = output
+            .self_invalidating_loans(&facts)
+            .into_iter()
+            .map(|(loan, point)| (tables.loans.untern(loan), tables.points.untern(point)))
+            .collect();
+        assert_eq!(loans, [("\"bw0\"", "\"Start(bb0[7])\"")], "{:?}", algorithm);
+
+        let errors: Vec<(Loan, Point)> = output
+            .all_errors()
+            .into_iter()
+            .map(|(point, loan)| (loan, point))
+            .collect();
+        assert_eq!(output.self_invalidating_loans(&facts), errors);
+    }
+}
+
+#[test]
+fn facts_spec_scenarios() {
+    const EDGES: &[(&str, &str)] = &[("P0", "P1"), ("P1", "P2")];
+    const BORROWS: &[(&str, &str, &str)] = &[("'a", "L0", "P0")];
+    let scenarios: &[(FactsSpec, usize)] = &[
+        // The loan is live, through `'a`, when it is invalidated.
+        (
+            FactsSpec {
+                edges: EDGES,
+                borrows: BORROWS,
+                invalidations: &[("L0", "P2")],
+                uses: &[("'a", "P1"), ("'a", "P2")],
+                ..FactsSpec::default()
+            },
+            1,
+        ),
+        // `'a` is dead when the loan is invalidated.
+        (
+            FactsSpec {
+                edges: EDGES,
+                borrows: BORROWS,
+                invalidations: &[("L0", "P2")],
+                uses: &[("'a", "P1")],
+                ..FactsSpec::default()
+            },
+            0,
+        ),
+        // The loan is killed before being invalidated.
+        (
+            FactsSpec {
+                edges: EDGES,
+                borrows: BORROWS,
+                kills: &[("L0", "P1")],
+                invalidations: &[("L0", "P2")],
+                uses: &[("'a", "P1"), ("'a", "P2")],
+            },
+            0,
+        ),
+    ];
+
+    for (spec, error_count) in scenarios {
+        let mut tables = intern::InternerTables::new();
+        let facts = spec.build(&mut tables);
+        for &algorithm in &[Algorithm::Naive, Algorithm::DatafrogOpt] {
+            let output = Output::compute(&facts, algorithm, false);
+            assert_eq!(output.all_errors().len(), *error_count, "{:?}", spec);
+        }
+    }
+
+    // Referential integrity is checked.
+    let invalid_specs = [
+        FactsSpec {
+            edges: EDGES,
+            borrows: &[("'a", "L0", "P3")],
+            ..FactsSpec::default()
+        },
+        FactsSpec {
+            edges: EDGES,
+            borrows: BORROWS,
+            invalidations: &[("L1", "P2")],
+            ..FactsSpec::default()
+        },
+    ];
+    for spec in &invalid_specs {
+        let result = panic::catch_unwind(|| spec.build(&mut intern::InternerTables::new()));
+        assert!(result.is_err(), "{:?}", spec);
+    }
+}
+
+#[test]
+fn facts_pushed_into_a_sink() {
+    // The sink can be moved to the thread receiving the tuples.
+    fn assert_send<T: Send>() {}
+    assert_send::<tab_delim::FactSink<'_>>();
+
+    let mut tables = intern::InternerTables::new();
+    let mut sink = tables.fact_sink();
+    sink.push("cfg_edge", &["\"Mid(bb0[0])\"", "\"Mid(bb0[1])\""])
+        .unwrap();
+    sink.push("borrow_region", &["'a", "L0", "\"Mid(bb0[0])\""])
+        .unwrap();
+    sink.push("invalidates", &["\"Mid(bb0[1])\"", "L0"])
+        .unwrap();
+    sink.push("region_live_at", &["'a", "\"Mid(bb0[1])\""])
+        .unwrap();
+
+    let error = sink.push("borrow_regions", &["'a"]).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    let error = sink.push("killed", &["L0"]).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    let error = sink
+        .push("killed", &["L0", "\"Mid(bb0[1])\"", "L1"])
+        .unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+
+    let facts = sink.finish();
+    assert!(facts.killed.is_empty());
+    assert_eq!(
+        facts.invalidates,
+        [(
+            tables.points.intern("\"Mid(bb0[1])\""),
+            tables.loans.intern("L0")
+        )]
+    );
+
+    let output = Output::compute(&facts, Algorithm::Naive, false);
+    assert_eq!(output.errors.len(), 1);
+}
+
+#[test]
+fn redundant_facts() {
+    let program = r"
+        universal_regions { }
+        block B0 {
+            borrow_region_at('a, L0), outlives('b: 'c);
+            invalidates(L0), region_live_at('a);
+        }
+    ";
+
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    let redundant = Output::redundant_facts(&facts, Algorithm::Naive);
+
+    // The error only depends on the borrow, the invalidation, and on `'a` being live there.
+    assert_eq!(redundant.outlives, facts.outlives);
+    assert!(redundant.borrow_region.is_empty());
+    assert!(redundant.invalidates.is_empty());
+    assert!(redundant.region_live_at.is_empty());
+
+    // The edge towards the borrow is redundant, but not the one towards the invalidation.
+    assert_eq!(redundant.cfg_edge.len(), 1);
+    let start = tables.points.intern("\"Start(B0[0])\"");
+    assert_eq!(redundant.cfg_edge[0].0, start);
+}
+
+#[test]
+fn redundant_facts_are_removable_together() {
+    let program = r"
+        universal_regions { }
+        block B0 {
+            borrow_region_at('a, L0);
+            invalidates(L0), region_live_at('a);
+        }
+    ";
+
+    let mut tables = intern::InternerTables::new();
+    let mut facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    let invalidation = facts.invalidates[0];
+    facts.invalidates.push(invalidation);
+
+    // Each copy of the invalidation is redundant on its own, but not both of them.
+    let redundant = Output::redundant_facts(&facts, Algorithm::Naive);
+    assert_eq!(redundant.invalidates, [invalidation]);
+}
+
+#[test]
+fn output_digests() {
+    let program = r"
+        universal_regions { }
+        block B0 {
+            borrow_region_at('a, L0), region_live_at('b);
+            invalidates(L0), region_live_at('a), region_live_at('b);
+        }
+    ";
+
+    let (output, tables) = program::run(program, Algorithm::Naive).unwrap();
+    let digest = dump::digest(&output, &tables);
+    let (opt_output, opt_tables) = program::run(program, Algorithm::DatafrogOpt).unwrap();
+    assert_eq!(dump::digest(&opt_output, &opt_tables), digest);
+
+    // The order of the maps and of their values doesn't matter.
+    let mut reordered = output.clone();
+    let mut region_live_at: Vec<_> = output.region_live_at.clone().into_iter().collect();
+    region_live_at.sort();
+    reordered.region_live_at = region_live_at
+        .into_iter()
+        .rev()
+        .map(|(point, mut regions)| {
+            regions.reverse();
+            (point, regions)
+        })
+        .collect();
+    assert_eq!(dump::digest(&reordered, &tables), digest);
+
+    // But the results do.
+    let mut different = output.clone();
+    different.errors.clear();
+    assert_ne!(dump::digest(&different, &tables), digest);
+    let mut different = output.clone();
+    different.region_live_at.values_mut().next().unwrap().pop();
+    assert_ne!(dump::digest(&different, &tables), digest);
+}
+
+#[test]
+fn spliced_callee() {
+    // The callee returns a reference to one of its locals, through its universal region `'x`:
+    // an error on its own, but once inlined, only if the caller's region `'a` is still live.
+    let callee = r"
+        universal_regions { 'x }
+        block B1 {
+            borrow_region_at('y, L1), outlives('y: 'x);
+            invalidates(L1);
+        }
+    ";
+
+    let compute = |caller: &str| {
+        let mut tables = intern::InternerTables::new();
+        let caller = parse_from_program(caller, &mut tables).expect("Parsing failure");
+        let callee = parse_from_program(callee, &mut tables).expect("Parsing failure");
+        let call_point = tables.points.intern("\"Mid(B0[0])\"");
+        let mut mapping = RegionMapping::new();
+        mapping.insert(tables.regions.intern("'x"), tables.regions.intern("'a"));
+        let facts = caller.splice(call_point, &callee, mapping);
+
+        let entry = tables.points.intern("\"Start(B1[0])\"");
+        let exit = tables.points.intern("\"Mid(B1[1])\"");
+        let return_point = tables.points.intern("\"Start(B0[1])\"");
+        assert!(facts.cfg_edge.contains(&(call_point, entry)));
+        assert!(facts.cfg_edge.contains(&(exit, return_point)));
+        assert!(!facts.cfg_edge.contains(&(call_point, return_point)));
+        assert!(facts.universal_region.is_empty());
+
+        let output = Output::compute(&facts, Algorithm::Naive, false);
+        (output, tables)
+    };
+
+    // `'a` is used after the call.
+    let (output, mut tables) = compute(
+        r"
+        universal_regions { }
+        var_uses_region { (V0, 'a) }
+        block B0 {
+            var_defined(V0);
+            var_used(V0);
+        }
+    ",
+    );
+    let mut expected = FxHashMap::default();
+    expected.insert(
+        tables.points.intern("\"Mid(B1[1])\""),
+        vec![tables.loans.intern("L1")],
+    );
+    assert_equal(&expected, &output.errors);
+
+    // `'a` is dead after the call.
+    let (output, _) = compute(
+        r"
+        universal_regions { }
+        var_uses_region { (V0, 'a) }
+        block B0 {
+            var_defined(V0);
+            var_defined(V0);
+        }
+    ",
+    );
+    assert!(output.errors.is_empty());
+}
+
+#[test]
+#[should_panic(expected = "the universal region")]
+fn spliced_callee_with_unmapped_universal_region() {
+    let mut tables = intern::InternerTables::new();
+    let caller = parse_from_program(
+        "universal_regions { } block B0 { var_defined(V0); }",
+        &mut tables,
+    )
+    .expect("Parsing failure");
+    let callee = parse_from_program(
+        "universal_regions { 'x } block B1 { kill(L0); }",
+        &mut tables,
+    )
+    .expect("Parsing failure");
+    let call_point = tables.points.intern("\"Mid(B0[0])\"");
+    caller.splice(call_point, &callee, RegionMapping::new());
+}
+
+#[test]
+#[should_panic(expected = "the unmapped region")]
+fn spliced_callee_with_a_colliding_region() {
+    let mut tables = intern::InternerTables::new();
+    let caller = parse_from_program(
+        "universal_regions { } var_uses_region { (V0, 'y) } block B0 { var_defined(V0); }",
+        &mut tables,
+    )
+    .expect("Parsing failure");
+    let callee = parse_from_program(
+        "universal_regions { } block B1 { borrow_region_at('y, L1); }",
+        &mut tables,
+    )
+    .expect("Parsing failure");
+    let call_point = tables.points.intern("\"Mid(B0[0])\"");
+    caller.splice(call_point, &callee, RegionMapping::new());
+}
+
+#[test]
+fn errors_without_a_block() {
+    let program = r"
+        universal_regions { }
+        block B0 {
+            borrow_region_at('a, L0), region_live_at('a);
+            goto B1, B2;
+        }
+        block B1 {
+            invalidates(L0), region_live_at('a);
+            goto B3;
+        }
+        block B2 {
+            region_live_at('a);
+            goto B3;
+        }
+        block B3 {
+            region_live_at('a);
+        }
+    ";
+
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    let output = Output::compute(&facts, Algorithm::Naive, false);
+    let error_point = tables.points.intern("\"Mid(B1[0])\"");
+    assert_eq!(output.errors.keys().collect::<Vec<_>>(), [&error_point]);
+
+    let mut block_points = |block: &str| -> Vec<Point> {
+        vec![
+            tables.points.intern(&format!("\"Start({}[0])\"", block)),
+            tables.points.intern(&format!("\"Mid({}[0])\"", block)),
+        ]
+    };
+    let b1 = block_points("B1");
+    let b2 = block_points("B2");
+    let b3_entry = block_points("B3")[0];
+    let b0_exit = block_points("B0")[1];
+
+    // Without `B1`, which invalidates `L0`, there is no error, and `B0` flows into `B3`.
+    let without_b1 = facts.without_block(&b1);
+    assert!(without_b1.cfg_edge.contains(&(b0_exit, b3_entry)));
+    assert!(without_b1
+        .cfg_edge
+        .iter()
+        .all(|(p, q)| !b1.contains(p) && !b1.contains(q)));
+    assert!(without_b1.invalidates.is_empty());
+    let output = Output::compute(&without_b1, Algorithm::Naive, false);
+    assert!(output.errors.is_empty());
+
+    // Without `B2`, the error remains.
+    let output = Output::compute(&facts.without_block(&b2), Algorithm::Naive, false);
+    assert_eq!(output.errors.keys().collect::<Vec<_>>(), [&error_point]);
+}
+
+#[test]
+fn fact_file_line_counts() {
+    let program = r"
+        universal_regions { 'a }
+
+        block B0 {
+            borrow_region_at('a, L0), outlives('a: 'b);
+            invalidates(L0);
+            goto B1;
+        }
+
+        block B1 {
+            var_used(V0);
+        }
+    ";
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+    let facts_dir =
+        std::env::temp_dir().join(format!("polonius-line-counts-{}", std::process::id()));
+    tab_delim::write_tab_delimited_facts(&facts, &tables, &facts_dir)
+        .expect("failed to write facts");
+
+    // Optional relations can be missing, and the last line of a file may have no newline.
+    fs::remove_file(facts_dir.join("closure_requires.facts")).expect("failed to remove facts");
+    fs::write(facts_dir.join("var_used.facts"), "V0\t\"Mid(B1[0])\"").expect("facts");
+
+    let counts = tab_delim::count_lines(&facts_dir).expect("line counts");
+    assert_eq!(counts.len(), 18);
+    assert_eq!(counts["borrow_region"], facts.borrow_region.len());
+    assert_eq!(counts["cfg_edge"], facts.cfg_edge.len());
+    assert_eq!(counts["outlives"], 1);
+    assert_eq!(counts["var_used"], 1);
+    assert_eq!(counts["closure_requires"], 0);
+
+    // Other relations are required.
+    fs::remove_file(facts_dir.join("cfg_edge.facts")).expect("failed to remove facts");
+    let error = tab_delim::count_lines(&facts_dir).expect_err("cfg_edge.facts is missing");
+    assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+    fs::remove_dir_all(&facts_dir).expect("failed to remove facts");
+}
+
+#[test]
+fn algorithm_report() {
+    // The loan is killed before its invalidation, which only the location-insensitive analysis
+    // ignores.
+    let program = r"
+        universal_regions { }
+
+        block B0 {
+            borrow_region_at('a, L0), region_live_at('a);
+            kill(L0), region_live_at('a);
+            invalidates(L0), region_live_at('a);
+        }
+    ";
+    let mut tables = intern::InternerTables::new();
+    let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+
+    let report = Output::algorithm_report(&facts, false);
+    assert!(report.all_agree());
+    assert_eq!(
+        report.to_json(),
+        "{\"algorithms\":[\
+         {\"algorithm\":\"Naive\",\"error_count\":0,\"agrees_with_naive\":true,\"duration_secs\":null},\
+         {\"algorithm\":\"DatafrogOpt\",\"error_count\":0,\"agrees_with_naive\":true,\"duration_secs\":null},\
+         {\"algorithm\":\"LocationInsensitive\",\"error_count\":1,\"agrees_with_naive\":true,\"duration_secs\":null},\
+         {\"algorithm\":\"Hybrid\",\"error_count\":0,\"agrees_with_naive\":true,\"duration_secs\":null}\
+         ]}"
+    );
+
+    let facts_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("inputs")
+        .join("smoke-test")
+        .join("nll-facts")
+        .join("use_while_mut");
+    let facts = tab_delim::load_tab_delimited_facts(&mut tables, &facts_dir).expect("facts");
+    let report = Output::algorithm_report(&facts, true);
+    assert!(report.all_agree());
+    assert_eq!(report.algorithms.len(), 4);
+    let naive_errors = Output::compute(&facts, Algorithm::Naive, false).all_errors();
+    assert!(!naive_errors.is_empty());
+    assert_eq!(report.algorithms[0].error_count, naive_errors.len());
+    assert!(report
+        .algorithms
+        .iter()
+        .all(|summary| summary.duration.is_some()));
+}
+
+#[test]
+fn region_first_live_points() {
+    let (facts, mut tables) = load_fixture("drop-liveness", "main");
+    let output = Output::compute(&facts, Algorithm::Naive, true);
+    let first_live = output.region_first_live(&facts);
+
+    // Exactly the live regions have a first live point, where they are live.
+    let live_regions: BTreeSet<Region> =
+        output.region_live_at.values().flatten().cloned().collect();
+    assert_eq!(
+        first_live.keys().cloned().collect::<BTreeSet<_>>(),
+        live_regions
+    );
+    for (&region, &point) in &first_live {
+        assert!(output.regions_live_at(point).contains(&region));
+    }
+
+    // The universal regions are live from the entry point, the others from their first use.
+    let mut first_live_at = |region: &str| {
+        let region = tables.regions.intern(region);
+        tables.points.untern(first_live[&region]).to_string()
+    };
+    assert_eq!(first_live_at("\"\\'_#0r\""), "\"Start(bb0[0])\"");
+    assert_eq!(first_live_at("\"\\'_#1r\""), "\"Start(bb0[0])\"");
+    assert_eq!(first_live_at("\"\\'_#73r\""), "\"Start(bb0[9])\"");
+    assert_eq!(first_live_at("\"\\'_#76r\""), "\"Start(bb6[0])\"");
+}
+
+#[test]
+fn program_parsed_from_file() {
+    let program = r"
+        universal_regions { }
+        block B0 {
+            borrow_region_at('a, L0), region_live_at('a);
+            invalidates(L0), region_live_at('a);
+        }
+    ";
+    let path = std::env::temp_dir().join(format!("polonius-program-{}.txt", std::process::id()));
+    fs::write(&path, program).expect("failed to write program");
+
+    let mut tables = intern::InternerTables::new();
+    let mut facts = program::parse_from_file(&path, &mut tables).expect("Parsing failure");
+    let mut expected = parse_from_program(program, &mut tables).expect("Parsing failure");
+    tab_delim::normalize_facts(&mut facts, &tables);
+    tab_delim::normalize_facts(&mut expected, &tables);
+    assert_eq!(format!("{:?}", facts), format!("{:?}", expected));
+
+    fs::write(&path, "block B0 {").expect("failed to write program");
+    let error = program::parse_from_file(&path, &mut tables).unwrap_err();
+    assert!(error.to_string().starts_with("failed to parse `"));
+
+    fs::remove_file(&path).expect("failed to remove program");
+    let error = program::parse_from_file(&path, &mut tables).unwrap_err();
+    assert!(error
+        .to_string()
+        .starts_with(&format!("failed to read `{}`", path.display())));
+}
+
+#[test]
+fn drop_liveness_skipped_without_drop_facts() {
+    let facts_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("inputs")
+        .join("drop-liveness")
+        .join("nll-facts")
+        .join("main");
+    let tables = &mut intern::InternerTables::new();
+    let (mut facts, missing) =
+        tab_delim::load_tab_delimited_facts_with_missing(tables, &facts_dir).expect("facts");
+    assert!(!missing.contains(&"var_drop_used"));
+    facts.region_live_at = Vec::default();
+    let output = Output::compute(&facts, Algorithm::Naive, true);
+    assert!(output.drop_liveness_computed);
+    assert!(!output.var_drop_live_at.is_empty());
+
+    // The same dataset, without its drop facts.
+    let dataset_dir =
+        std::env::temp_dir().join(format!("polonius-without-drops-{}", std::process::id()));
+    fs::create_dir_all(&dataset_dir).expect("failed to create dataset");
+    for entry in fs::read_dir(&facts_dir).expect("facts") {
+        let path = entry.expect("facts").path();
+        let name = path.file_name().unwrap().to_str().unwrap().to_string();
+        if name != "var_drop_used.facts" && name != "var_drops_region.facts" {
+            fs::copy(&path, dataset_dir.join(name)).expect("failed to copy facts");
+        }
+    }
+    let tables = &mut intern::InternerTables::new();
+    let (mut facts_without_drops, missing) =
+        tab_delim::load_tab_delimited_facts_with_missing(tables, &dataset_dir).expect("facts");
+    fs::remove_dir_all(&dataset_dir).expect("failed to remove dataset");
+    assert!(missing.contains(&"var_drop_used"));
+    assert!(missing.contains(&"var_drops_region"));
+    assert!(facts_without_drops.var_drop_used.is_empty());
+
+    facts_without_drops.region_live_at = Vec::default();
+    let output = Output::compute(&facts_without_drops, Algorithm::Naive, true);
+    assert!(!output.drop_liveness_computed);
+    assert!(output.var_drop_live_at.is_empty());
+
+    // Skipping the drop-liveness is the same as ignoring the drop facts.
+    let options = ComputeOptions {
+        dump_enabled: true,
+        ignore_drop_liveness: true,
+        ..ComputeOptions::default()
+    };
+    let ignored = Output::compute_with_options(&facts, Algorithm::Naive, options);
+    assert!(!ignored.drop_liveness_computed);
+    assert_eq!(ignored.errors.len(), output.errors.len());
+    assert_eq!(ignored.var_live_at.len(), output.var_live_at.len());
+}
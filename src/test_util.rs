@@ -1,6 +1,16 @@
 #![cfg(test)]
 
-use std::fmt::Debug;
+use crate::facts::AllFacts;
+use crate::intern::InternerTables;
+use crate::program::parse_from_program;
+use crate::tab_delim;
+use polonius_engine::{Algorithm, Atom, Output};
+use std::collections::BTreeSet;
+use std::env;
+use std::fmt::{Debug, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Test that two values are equal, with a better error than `assert_eq`
 pub fn assert_equal<A>(expected_value: &A, actual_value: &A)
@@ -44,3 +54,327 @@ where
 
     panic!("debug comparison failed");
 }
+
+/// Returns the directory of the facts of the function `fn_name` of the `inputs/<dir>` fixture.
+pub fn fixture_dir(dir: &str, fn_name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("inputs")
+        .join(dir)
+        .join("nll-facts")
+        .join(fn_name)
+}
+
+/// Loads the facts of the function `fn_name` of the `inputs/<dir>` fixture, along with the
+/// tables their atoms are interned in.
+pub fn load_fixture(dir: &str, fn_name: &str) -> (AllFacts, InternerTables) {
+    let mut tables = InternerTables::new();
+    let facts = tab_delim::load_tab_delimited_facts(&mut tables, &fixture_dir(dir, fn_name))
+        .expect("facts");
+    (facts, tables)
+}
+
+/// Test that `DatafrogOpt` computes exactly the `expected` errors, as `(point, loan)` indices,
+/// for the function `fn_name` of the `inputs/<dir>` facts.
+pub fn assert_errors(dir: &str, fn_name: &str, expected: &[(u32, u32)]) {
+    assert_algorithm_errors(dir, fn_name, Algorithm::DatafrogOpt, expected);
+}
+
+/// Test that `algorithm` computes exactly the `expected` errors, like `assert_errors`.
+pub fn assert_algorithm_errors(
+    dir: &str,
+    fn_name: &str,
+    algorithm: Algorithm,
+    expected: &[(u32, u32)],
+) {
+    let (facts, _) = load_fixture(dir, fn_name);
+    let errors: Vec<(u32, u32)> = Output::compute(&facts, algorithm, false)
+        .all_errors()
+        .into_iter()
+        .map(|(point, loan)| (point.index() as u32, loan.index() as u32))
+        .collect();
+    let mut expected = expected.to_vec();
+    expected.sort();
+    assert_equal(&expected, &errors);
+}
+
+/// Test that the `cfg_edge` facts are exactly the `expected` edges, given by point name.
+/// Prints the missing and extra edges on failure.
+pub fn assert_cfg(facts: &AllFacts, tables: &InternerTables, expected: &[(&str, &str)]) {
+    let expected: BTreeSet<(&str, &str)> = expected.iter().cloned().collect();
+    let actual: BTreeSet<(&str, &str)> = facts
+        .cfg_edge
+        .iter()
+        .map(|&(p, q)| (tables.points.untern(p), tables.points.untern(q)))
+        .collect();
+
+    if expected == actual {
+        return;
+    }
+
+    println!("# missing edges");
+    for (p, q) in expected.difference(&actual) {
+        println!("-{} -> {}", p, q);
+    }
+
+    println!("# extra edges");
+    for (p, q) in actual.difference(&expected) {
+        println!("+{} -> {}", p, q);
+    }
+
+    panic!("cfg comparison failed");
+}
+
+/// A declarative description of small facts, for table-driven tests: the atoms are given by
+/// name, and interned by `build`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FactsSpec<'a> {
+    /// The `cfg_edge` facts, as `(point, successor)`.
+    pub edges: &'a [(&'a str, &'a str)],
+    /// The `borrow_region` facts, as `(region, loan, point)`.
+    pub borrows: &'a [(&'a str, &'a str, &'a str)],
+    /// The `killed` facts, as `(loan, point)`.
+    pub kills: &'a [(&'a str, &'a str)],
+    /// The `invalidates` facts, as `(loan, point)`.
+    pub invalidations: &'a [(&'a str, &'a str)],
+    /// The uses of regions, making them live: the `region_live_at` facts, as `(region, point)`.
+    pub uses: &'a [(&'a str, &'a str)],
+}
+
+impl<'a> FactsSpec<'a> {
+    /// Builds the facts described by the spec. Panics if a point is not part of the CFG, or if a
+    /// loan is killed or invalidated without being borrowed.
+    pub fn build(&self, tables: &mut InternerTables) -> AllFacts {
+        let points: BTreeSet<&str> = self.edges.iter().flat_map(|&(p, q)| vec![p, q]).collect();
+        let loans: BTreeSet<&str> = self.borrows.iter().map(|&(_, l, _)| l).collect();
+        let check_point = |point: &str| {
+            assert!(points.contains(point), "point {} is not in the CFG", point);
+        };
+        let check_loan = |loan: &str| {
+            assert!(loans.contains(loan), "loan {} is never borrowed", loan);
+        };
+
+        let mut facts = AllFacts::default();
+        for &(p, q) in self.edges {
+            facts
+                .cfg_edge
+                .push((tables.points.intern(p), tables.points.intern(q)));
+        }
+        for &(r, l, p) in self.borrows {
+            check_point(p);
+            facts.borrow_region.push((
+                tables.regions.intern(r),
+                tables.loans.intern(l),
+                tables.points.intern(p),
+            ));
+        }
+        for &(l, p) in self.kills {
+            check_loan(l);
+            check_point(p);
+            facts
+                .killed
+                .push((tables.loans.intern(l), tables.points.intern(p)));
+        }
+        for &(l, p) in self.invalidations {
+            check_loan(l);
+            check_point(p);
+            facts
+                .invalidates
+                .push((tables.points.intern(p), tables.loans.intern(l)));
+        }
+        for &(r, p) in self.uses {
+            check_point(p);
+            facts
+                .region_live_at
+                .push((tables.regions.intern(r), tables.points.intern(p)));
+        }
+        facts
+    }
+}
+
+/// Test that `DatafrogOpt` computes the same errors and live loans as `Naive` on these facts.
+/// On divergence, the facts are reduced to a minimal subset which still diverges, written to a
+/// temporary directory whose path is printed, and the test fails.
+pub fn assert_opt_matches_naive_or_dump(facts: &AllFacts, tables: &InternerTables) {
+    if !opt_diverges_from_naive(facts) {
+        return;
+    }
+
+    let facts_dir = write_reduced_facts(facts, tables, opt_diverges_from_naive);
+    println!(
+        "DatafrogOpt and Naive disagree, reduced facts written to {}",
+        facts_dir.display()
+    );
+
+    panic!("DatafrogOpt and Naive disagree");
+}
+
+/// Reduces the facts to a minimal subset for which `predicate` still holds, and writes them to a
+/// new temporary directory, whose path is returned.
+fn write_reduced_facts(
+    facts: &AllFacts,
+    tables: &InternerTables,
+    predicate: impl Fn(&AllFacts) -> bool,
+) -> PathBuf {
+    let reduced_facts = facts.minimize(predicate);
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let facts_dir = env::temp_dir().join(format!(
+        "polonius-reduction-{}-{}",
+        process::id(),
+        timestamp
+    ));
+    tab_delim::write_tab_delimited_facts(&reduced_facts, tables, &facts_dir)
+        .expect("failed to write reduced facts");
+    facts_dir
+}
+
+/// Test that `DatafrogOpt` computes exactly the same `subset` relation as `Naive` on these facts,
+/// which it doesn't have to: it only needs the subsets leading to errors. On divergence, the
+/// subsets which are only in one of the outputs are printed, the facts are reduced to a minimal
+/// subset which still diverges like `assert_opt_matches_naive_or_dump` does, and the test fails.
+pub fn assert_opt_subset_matches_naive(facts: &AllFacts, tables: &InternerTables) {
+    let naive = Output::compute(facts, Algorithm::Naive, true);
+    let opt = Output::compute(facts, Algorithm::DatafrogOpt, true);
+    if naive.subset == opt.subset {
+        return;
+    }
+
+    let subsets = |output: &Output<_, _, _, _>| -> BTreeSet<(String, String, String)> {
+        let mut subsets = BTreeSet::new();
+        for (&point, subset) in &output.subset {
+            for (&r1, r2s) in subset {
+                for &r2 in r2s {
+                    subsets.insert((
+                        tables.points.untern(point).to_string(),
+                        tables.regions.untern(r1).to_string(),
+                        tables.regions.untern(r2).to_string(),
+                    ));
+                }
+            }
+        }
+        subsets
+    };
+    let (naive_subsets, opt_subsets) = (subsets(&naive), subsets(&opt));
+    for (point, r1, r2) in naive_subsets.difference(&opt_subsets) {
+        println!("only in Naive: subset({}, {}, {})", r1, r2, point);
+    }
+    for (point, r1, r2) in opt_subsets.difference(&naive_subsets) {
+        println!("only in DatafrogOpt: subset({}, {}, {})", r1, r2, point);
+    }
+
+    let facts_dir = write_reduced_facts(facts, tables, opt_subset_diverges_from_naive);
+    println!(
+        "DatafrogOpt and Naive subsets differ, reduced facts written to {}",
+        facts_dir.display()
+    );
+
+    panic!("DatafrogOpt and Naive subsets differ");
+}
+
+fn opt_subset_diverges_from_naive(facts: &AllFacts) -> bool {
+    let naive = Output::compute(facts, Algorithm::Naive, true);
+    let opt = Output::compute(facts, Algorithm::DatafrogOpt, true);
+    naive.subset != opt.subset
+}
+
+fn opt_diverges_from_naive(facts: &AllFacts) -> bool {
+    let naive = Output::compute(facts, Algorithm::Naive, true);
+    let opt = Output::compute(facts, Algorithm::DatafrogOpt, true);
+    naive.errors != opt.errors || naive.borrow_live_at != opt.borrow_live_at
+}
+
+/// Test that `property` holds for the facts of the random programs generated from the seeds
+/// `0..cases`. On failure, the facts are reduced to a minimal subset which still fails, written
+/// to a temporary directory whose path is printed along with the program, and the test fails.
+pub fn check_random_facts(cases: u64, property: impl Fn(&AllFacts) -> bool) {
+    for seed in 0..cases {
+        let program = random_program(seed);
+        let mut tables = InternerTables::new();
+        let facts = parse_from_program(&program, &mut tables).expect("Parsing failure");
+        if property(&facts) {
+            continue;
+        }
+
+        let facts_dir = write_reduced_facts(&facts, &tables, |facts| !property(facts));
+        println!("# program (seed {})", seed);
+        println!("{}", program);
+        println!(
+            "property failed, reduced facts written to {}",
+            facts_dir.display()
+        );
+        panic!("property failed for seed {}", seed);
+    }
+}
+
+/// Generates a random program from `seed`: blocks of statements borrowing, invalidating and
+/// killing loans, relating regions and making them live, connected by random `goto`s. Each loan
+/// is borrowed by a single statement, and only the loans borrowed somewhere are invalidated or
+/// killed.
+pub fn random_program(seed: u64) -> String {
+    let mut rng = Rng::new(seed);
+    let region = |rng: &mut Rng| format!("'r{}", rng.below(4));
+
+    let mut program = String::from("universal_regions { ");
+    let universal_regions: Vec<_> = (0..rng.below(2)).map(|_| region(&mut rng)).collect();
+    program.push_str(&universal_regions.join(", "));
+    program.push_str(" }\n");
+
+    let block_count = 1 + rng.below(4);
+    let mut loan_count = 0;
+    for block in 0..block_count {
+        writeln!(program, "block B{} {{", block).unwrap();
+        for _ in 0..1 + rng.below(3) {
+            let mut effects = Vec::new();
+            for _ in 0..1 + rng.below(3) {
+                let effect = match rng.below(6) {
+                    0 => {
+                        loan_count += 1;
+                        format!(
+                            "borrow_region_at({}, L{})",
+                            region(&mut rng),
+                            loan_count - 1
+                        )
+                    }
+                    1 => format!("outlives({}: {})", region(&mut rng), region(&mut rng)),
+                    2 => format!("region_live_at({})", region(&mut rng)),
+                    3 | 4 if loan_count > 0 => {
+                        format!("invalidates(L{})", rng.below(loan_count))
+                    }
+                    5 if loan_count > 0 => format!("kill(L{})", rng.below(loan_count)),
+                    _ => format!("region_live_at({})", region(&mut rng)),
+                };
+                effects.push(effect);
+            }
+            writeln!(program, "    {};", effects.join(", ")).unwrap();
+        }
+
+        let successors: Vec<_> = (0..rng.below(3))
+            .map(|_| format!("B{}", rng.below(block_count)))
+            .collect();
+        if !successors.is_empty() {
+            writeln!(program, "    goto {};", successors.join(", ")).unwrap();
+        }
+        program.push_str("}\n");
+    }
+    program
+}
+
+/// A xorshift pseudo-random number generator, so that the programs generated from a seed are
+/// the same on every run.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    /// Returns a number in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
@@ -0,0 +1,248 @@
+use crate::facts::{AllFacts, Loan, Point, Region};
+use crate::intern::InternerTables;
+use rustc_hash::FxHashSet;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Checks the facts for inconsistencies which usually indicate a bug in their extraction,
+/// and returns a description of each problem found.
+pub(crate) fn check_facts(all_facts: &AllFacts, tables: &InternerTables) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    for (loan, borrow_regions) in conflicting_borrow_regions(all_facts) {
+        let tuples: Vec<_> = borrow_regions
+            .iter()
+            .map(|&(region, point)| {
+                format!(
+                    "borrow_region({}, {}, {})",
+                    tables.regions.untern(region),
+                    tables.loans.untern(loan),
+                    tables.points.untern(point)
+                )
+            })
+            .collect();
+        problems.push(format!(
+            "loan {} is introduced by conflicting `borrow_region` facts: {}",
+            tables.loans.untern(loan),
+            tuples.join(", ")
+        ));
+    }
+
+    for loan in loans_without_origin(all_facts) {
+        problems.push(format!(
+            "loan {} is used but never introduced by a `borrow_region` fact",
+            tables.loans.untern(loan)
+        ));
+    }
+
+    for (relation, tuple) in facts_outside_cfg(all_facts, tables) {
+        problems.push(format!(
+            "`{}` fact {} is at a point which is not in the CFG",
+            relation, tuple
+        ));
+    }
+
+    problems
+}
+
+/// Returns the `outlives`, `borrow_region` and `invalidates` facts whose point appears in no
+/// `cfg_edge` fact, which the analysis would then silently ignore: the name of their relation,
+/// and the fact itself.
+pub(crate) fn facts_outside_cfg(
+    all_facts: &AllFacts,
+    tables: &InternerTables,
+) -> Vec<(&'static str, String)> {
+    let cfg_points: FxHashSet<Point> = all_facts
+        .cfg_edge
+        .iter()
+        .flat_map(|&(p, q)| [p, q])
+        .collect();
+
+    let mut facts = Vec::new();
+    for &(r1, r2, point) in &all_facts.outlives {
+        if !cfg_points.contains(&point) {
+            let tuple = format!(
+                "({}, {}, {})",
+                tables.regions.untern(r1),
+                tables.regions.untern(r2),
+                tables.points.untern(point)
+            );
+            facts.push(("outlives", tuple));
+        }
+    }
+    for &(region, loan, point) in &all_facts.borrow_region {
+        if !cfg_points.contains(&point) {
+            let tuple = format!(
+                "({}, {}, {})",
+                tables.regions.untern(region),
+                tables.loans.untern(loan),
+                tables.points.untern(point)
+            );
+            facts.push(("borrow_region", tuple));
+        }
+    }
+    for &(point, loan) in &all_facts.invalidates {
+        if !cfg_points.contains(&point) {
+            let tuple = format!(
+                "({}, {})",
+                tables.points.untern(point),
+                tables.loans.untern(loan)
+            );
+            facts.push(("invalidates", tuple));
+        }
+    }
+    facts
+}
+
+/// A loan should be introduced at exactly one point, by exactly one region. Returns the loans
+/// appearing in `borrow_region` facts with different regions or points, along with these
+/// regions and points.
+pub(crate) fn conflicting_borrow_regions(
+    all_facts: &AllFacts,
+) -> BTreeMap<Loan, BTreeSet<(Region, Point)>> {
+    let mut borrow_regions: BTreeMap<Loan, BTreeSet<(Region, Point)>> = BTreeMap::new();
+    for &(region, loan, point) in &all_facts.borrow_region {
+        borrow_regions
+            .entry(loan)
+            .or_default()
+            .insert((region, point));
+    }
+
+    borrow_regions.retain(|_, borrow_regions| borrow_regions.len() > 1);
+    borrow_regions
+}
+
+/// Returns the loans appearing in the `invalidates`, `killed`, `borrow_reserved_at` or
+/// `borrow_activated_at` facts, but which no `borrow_region` fact introduces.
+pub(crate) fn loans_without_origin(all_facts: &AllFacts) -> BTreeSet<Loan> {
+    let borrowed: FxHashSet<Loan> = all_facts
+        .borrow_region
+        .iter()
+        .map(|&(_, loan, _)| loan)
+        .collect();
+    all_facts
+        .invalidates
+        .iter()
+        .map(|&(_, loan)| loan)
+        .chain(all_facts.killed.iter().map(|&(loan, _)| loan))
+        .chain(all_facts.borrow_reserved_at.iter().map(|&(loan, _)| loan))
+        .chain(all_facts.borrow_activated_at.iter().map(|&(loan, _)| loan))
+        .filter(|loan| !borrowed.contains(loan))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::program::parse_from_program;
+
+    #[test]
+    fn conflicting_borrow_region_facts() {
+        let program = r"
+            universal_regions { }
+
+            block B0 {
+                borrow_region_at('a, L0), borrow_region_at('b, L1);
+                borrow_region_at('a, L0);
+                borrow_region_at('c, L1);
+            }
+        ";
+
+        let mut tables = InternerTables::new();
+        let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+
+        let conflicts = conflicting_borrow_regions(&facts);
+        assert_eq!(conflicts.len(), 2);
+
+        let problems = check_facts(&facts, &tables);
+        assert_eq!(
+            problems,
+            [
+                "loan L0 is introduced by conflicting `borrow_region` facts: \
+                 borrow_region('a, L0, \"Mid(B0[0])\"), borrow_region('a, L0, \"Mid(B0[1])\")",
+                "loan L1 is introduced by conflicting `borrow_region` facts: \
+                 borrow_region('b, L1, \"Mid(B0[0])\"), borrow_region('c, L1, \"Mid(B0[2])\")",
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_borrow_region_facts_do_not_conflict() {
+        let program = r"
+            universal_regions { }
+
+            block B0 {
+                borrow_region_at('a, L0);
+            }
+        ";
+
+        let mut tables = InternerTables::new();
+        let mut facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+        let borrow_region = facts.borrow_region[0];
+        facts.borrow_region.push(borrow_region);
+        assert!(check_facts(&facts, &tables).is_empty());
+    }
+
+    #[test]
+    fn loans_without_origin_are_reported() {
+        let program = r"
+            universal_regions { }
+
+            block B0 {
+                borrow_region_at('a, L0), invalidates(L0);
+                invalidates(L1), kill(L2);
+            }
+        ";
+
+        let mut tables = InternerTables::new();
+        let facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+
+        let l0 = tables.loans.intern("L0");
+        assert_eq!(
+            facts.loan_origin(l0),
+            Some((
+                tables.regions.intern("'a"),
+                tables.points.intern("\"Mid(B0[0])\"")
+            ))
+        );
+        assert_eq!(facts.loan_origin(tables.loans.intern("L1")), None);
+
+        assert_eq!(
+            check_facts(&facts, &tables),
+            [
+                "loan L1 is used but never introduced by a `borrow_region` fact",
+                "loan L2 is used but never introduced by a `borrow_region` fact",
+            ]
+        );
+    }
+
+    #[test]
+    fn facts_outside_the_cfg_are_reported() {
+        let program = r"
+            universal_regions { }
+
+            block B0 {
+                borrow_region_at('a, L0), outlives('a: 'b);
+                invalidates(L0);
+            }
+        ";
+
+        let mut tables = InternerTables::new();
+        let mut facts = parse_from_program(program, &mut tables).expect("Parsing failure");
+        assert!(check_facts(&facts, &tables).is_empty());
+
+        let a = tables.regions.intern("'a");
+        let b = tables.regions.intern("'b");
+        let l0 = tables.loans.intern("L0");
+        let unknown = tables.points.intern("\"Mid(B1[0])\"");
+        facts.outlives.push((a, b, unknown));
+        facts.invalidates.push((unknown, l0));
+
+        assert_eq!(
+            check_facts(&facts, &tables),
+            [
+                "`outlives` fact ('a, 'b, \"Mid(B1[0])\") is at a point which is not in the CFG",
+                "`invalidates` fact (\"Mid(B1[0])\", L0) is at a point which is not in the CFG",
+            ]
+        );
+    }
+}